@@ -469,7 +469,8 @@ elif [[ $cmd == 'a' ]]; then
 
   goodinp=false
   while [ $goodinp == false ]; do
-    echo -e "\nWhat is the contract address of the oracle?"
+    echo -e "\nWhat is the contract address of the oracle?  Its owner must have set the auction"
+    echo -e "code hash with set_exposure_callers, or the auction fails at init"
     get_contract
   done
   oracleaddr=$conaddr
@@ -498,10 +499,12 @@ elif [[ $cmd == 'a' ]]; then
     #
     # change --gas amount below if getting out of gas error when creating a new auction
     #
+    prngseed=$(head -c 32 /dev/urandom | base64)
     resp=$(secretcli tx compute instantiate $contractcode "{\"sell_contract\":{\"code_hash\":\
             \"$sellhash\",\"address\":\"$selladdr\"},\"bid_contract\":{\"code_hash\":\"$bidhash\",\"address\":\
             \"$bidaddr\"},\"expected\":\"$expected\",\"payment\":\"$payment\",\"oracle_contract\":{\"code_hash\":\
-            \"$oraclehash\",\"address\":\"$oracleaddr\"},\"description\":\"$descinp\"}" --from $addr \
+            \"$oraclehash\",\"address\":\"$oracleaddr\"},\"description\":\"$descinp\",\"prng_seed\":\
+            \"$prngseed\"}" --from $addr \
       --label "$auctionlabel" --gas 300000 --broadcast-mode block --trust-node=true \
       -o json -y 2>&1)
     if echo $resp | grep "label already exists"; then
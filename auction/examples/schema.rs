@@ -3,7 +3,10 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use diploma_master::msg::{HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg};
+use diploma_master::msg::{
+    HandleAnswer, HandleMsg, InitMsg, MarketHandleAnswer, MarketHandleMsg, MarketInitMsg,
+    MarketQueryAnswer, MarketQueryMsg, MarketReceiveMsg, QueryAnswer, QueryMsg, ReceiveMsg,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -16,4 +19,11 @@ fn main() {
     export_schema(&schema_for!(HandleAnswer), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(QueryAnswer), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(MarketInitMsg), &out_dir);
+    export_schema(&schema_for!(MarketHandleMsg), &out_dir);
+    export_schema(&schema_for!(MarketHandleAnswer), &out_dir);
+    export_schema(&schema_for!(MarketReceiveMsg), &out_dir);
+    export_schema(&schema_for!(MarketQueryMsg), &out_dir);
+    export_schema(&schema_for!(MarketQueryAnswer), &out_dir);
 }
@@ -1,15 +1,24 @@
 use cosmwasm_std::{
-    log, to_binary, Api, CanonicalAddr, Env, Extern, HandleResponse, HandleResult, HumanAddr,
-    InitResponse, InitResult, Querier, QueryResult, StdError, Storage, Uint128,
+    from_binary, log, to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, Extern,
+    HandleResponse, HandleResult, HumanAddr, InitResponse, InitResult, LogAttribute, Querier,
+    QueryResult, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
 };
 
+use serde::Serialize;
 use std::collections::HashSet;
 
-use secret_toolkit::utils::{pad_handle_result, pad_query_result, Query};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result, HandleCallback, Query};
 
-use crate::msg::{HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, ResponseStatus, ResponseStatus::{Failure, Success}, Token, CONFIG_KEY, BLOCK_SIZE, OracleQueryMsg, OracleQueryResponse, History};
-use crate::state::{load, may_load, remove, save, Bid, State};
+use crate::msg::{HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, QueryWithPermit, ReceiveMsg, AuctionKind, BidInfo, BidPreferences, FeeConfig, FeeSource, ResponseStatus, ResponseStatus::{Failure, Success}, Token, AUCTION_ID_KEY, PRNG_SEED_KEY, PREFIX_REVOKED_PERMITS, BLOCK_SIZE, RESPONSE_BLOCK_SIZE, MAX_NOTE_LEN, MAX_PAGE_SIZE, MAX_PREPAYMENT_PENALTY, OracleHandleMsg, OracleQueryMsg, OracleQueryResponse, RegistryHandleMsg, RegistryQueryAnswer, RegistryQueryMsg, ContractInfo, REGISTRY_KEY_KEY};
+use crate::error::ContractError;
+use crate::state::{
+    load, load_bid, load_state, may_load, migrate_state, read_viewing_key, remove, save,
+    save_state, write_viewing_key, Bid, State, STATE_VERSION,
+};
+use crate::viewing_key::{sha_256, ViewingKey, VIEWING_KEY_SIZE};
 use chrono::NaiveDateTime;
+use scoring::perfect_proposal;
+use secret_toolkit::permit::{validate, Permission, Permit, RevokedPermits};
 
 ////////////////////////////////////// Init ///////////////////////////////////////
 /// Initializes the auction state and registers Receive function with sell and bid
@@ -25,39 +34,113 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: InitMsg,
 ) -> InitResult {
+    let code_hash = env.contract_code_hash.clone();
+    let mut messages = vec![
+        msg.sell_contract.register_receive_msg(code_hash.clone())?,
+        msg.bid_contract.register_receive_msg(code_hash)?,
+    ];
+    let mut response = create_auction(deps, env, msg, None)?;
+    messages.append(&mut response.messages);
+    response.messages = messages;
+    Ok(response)
+}
+
+/// settings a marketplace gives the auctions it hosts.  They come from the marketplace's own
+/// instantiation, so a seller can not choose them
+pub struct HostConfig {
+    /// id of the auction in the marketplace
+    pub auction_id: u64,
+    /// Optional address allowed to administer the auction
+    pub admin: Option<HumanAddr>,
+    /// Optional platform fee taken at settlement
+    pub fee: Option<FeeConfig>,
+    /// Optional viewing key the marketplace set with the lender registry
+    pub registry_key: Option<String>,
+}
 
+/// Creates an auction in the given storage without registering with the token contracts
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - InitMsg describing the auction
+/// * `host` - Optional settings of the marketplace if the auction is hosted by one
+pub fn create_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: InitMsg,
+    host: Option<HostConfig>,
+) -> InitResult {
     if msg.sell_contract.address == msg.bid_contract.address {
-        return Err(StdError::generic_err(
-            "Sell contract and bid contract must be different",
-        ));
+        return Err(ContractError::SameTokens.into());
     }
     if msg.payment <= msg.expected {
-        return Err(StdError::generic_err(
-            "You can't expect to pay less than sum of credit",
-        ));
+        return Err(ContractError::PaymentTooLow.into());
+    }
+    let preferences = msg.preferences.unwrap_or_default();
+    if preferences.is_empty() {
+        return Err(ContractError::NoPreferences.into());
     }
-    let mut number = msg.payment.u128();
-    let mut len : u32 = 0;
-    while number > 0 {
-        number /= 10;
-        len += 1;
+    let kind = msg.kind.unwrap_or_default();
+    let perfect_proposal = perfect_proposal(msg.payment.u128());
+    let average_bid = calculate_estimation(msg.payment, msg.expected, perfect_proposal);
+    check_dutch_schedule(&kind, average_bid)?;
+    let fee = match &host {
+        Some(host) => host.fee.clone(),
+        None => msg.fee,
+    };
+    if let Some(config) = &fee {
+        config.validate()?;
     }
-    let perfect_proposal = 10u128.pow(len - 1);
 
-    let seller = env.message.sender;
+    let seller = env.message.sender.clone();
     let get_history = OracleQueryMsg::GetHistory {user: seller.clone()};
     let history_response: OracleQueryResponse = get_history.query(
         &deps.querier,
-        msg.oracle_contract.code_hash,
-        msg.oracle_contract.address,
+        msg.oracle_contract.code_hash.clone(),
+        msg.oracle_contract.address.clone(),
     )?;
 
     if history_response.history.is_none(){
-        return Err(StdError::generic_err("You have no credit history to calculate score"));
+        return Err(ContractError::NoCreditHistory.into());
     };
-    let current_scope = calculate_score(history_response.history.unwrap(), perfect_proposal);
+    let current_scope = history_response.history.unwrap().score(msg.payment);
     if current_scope.is_none(){
-        return Err(StdError::generic_err("You have too bad score"));
+        return Err(ContractError::ScoreTooLow.into());
+    };
+    let admin = match &host {
+        Some(host) => host.admin.clone(),
+        None => check_admin(&seller, msg.admin)?,
+    };
+
+    // the oracle rejects the auction if it would take the seller over the exposure limit of
+    // their score
+    let open_exposure = OracleHandleMsg::OpenExposure {
+        borrower: seller.clone(),
+        amount: msg.expected,
+        payment: msg.payment,
+        auction_id: host.as_ref().map(|host| host.auction_id),
+    }
+    .to_cosmos_msg(
+        msg.oracle_contract.code_hash.clone(),
+        msg.oracle_contract.address.clone(),
+        None,
+    )?;
+
+    let mut messages = vec![open_exposure];
+
+    let prng_seed = sha_256(&msg.prng_seed.0);
+    // the auction checks approvals with a viewing key of its own.  A marketplace sets one key
+    // for all the auctions it hosts
+    let registry_key = match (&msg.lender_registry, host.as_ref()) {
+        (None, _) => None,
+        (Some(_), Some(host)) => host.registry_key.clone(),
+        (Some(registry), None) => {
+            let key = ViewingKey::new(&env, &prng_seed, b"registry").0;
+            messages.push(registry_key_msg(registry, key.clone())?);
+            Some(key)
+        }
     };
 
     let state = State {
@@ -66,60 +149,328 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         sell_contract: msg.sell_contract,
         bid_contract: msg.bid_contract,
         score: current_scope.unwrap(),
-        average_bid: calculate_estimation(msg.payment, msg.expected, perfect_proposal),
+        average_bid,
         currently_consigned: 0,
         bidders: HashSet::new(),
         is_completed: false,
+        is_cancelled: false,
+        is_paused: false,
+        is_unwound: false,
+        ends_at: None,
         tokens_consigned: false,
         description: msg.description,
         winning_bid: 0,
+        preferences,
+        admin,
+        fee,
+        lender_registry: msg.lender_registry,
+        oracle_contract: Some(msg.oracle_contract),
+        kind,
+        started_at: env.block.time,
+        lender: None,
     };
 
-    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    save_state(&mut deps.storage, &state)?;
+    if let Some(host) = &host {
+        save(&mut deps.storage, AUCTION_ID_KEY, &host.auction_id)?;
+    }
+
+    save(&mut deps.storage, PRNG_SEED_KEY, &prng_seed.to_vec())?;
+    if let Some(key) = &registry_key {
+        save(&mut deps.storage, REGISTRY_KEY_KEY, key)?;
+    }
 
     Ok(InitResponse {
-        messages: vec![
-            state
-                .sell_contract
-                .register_receive_msg(env.contract_code_hash.clone())?,
-            state
-                .bid_contract
-                .register_receive_msg(env.contract_code_hash)?,
-        ],
-        log: vec![],
+        messages,
+        log: event_log(&deps.storage, "init", &state, Some(state.score))?,
     })
 }
 
-/// Convert credit history to SNIP20 sale tokens
+/// Returns StdResult<CosmosMsg> that sets the viewing key approvals are checked with at the
+/// lender registry
+///
+/// # Arguments
+///
+/// * `registry` - a reference to the code hash and address of the lender registry
+/// * `key` - the viewing key
+pub fn registry_key_msg(registry: &ContractInfo, key: String) -> StdResult<CosmosMsg> {
+    RegistryHandleMsg::SetViewingKey { key, padding: None }.to_cosmos_msg(
+        registry.code_hash.clone(),
+        registry.address.clone(),
+        None,
+    )
+}
+
+/// Returns StdResult<Option<HumanAddr>> of the admin a seller named for a standalone auction.
+/// The admin can pause and unwind the auction, so it can never be the seller.  Being an admin
+/// grants no rights at the oracle
 ///
 /// # Arguments
 ///
-/// * `history` - vector of credit histories
-fn calculate_score(history : History, mul : u128) -> Option<u128> {
-    let mut score: i128 = 0;
-    let credits = history.credits;
-    let length = credits.len();
-    let mut cred_hist: i128 = 0;
-    for credit in credits {
-        let sum: u128 = credit.sum.u128() * credit.interest_rate.u128() * credit.time.u128();
-        if credit.is_closed {
-            cred_hist += sum as i128;
-        } else { cred_hist -= sum as i128; }
+/// * `seller` - reference to the address of the seller
+/// * `admin` - Optional admin named in the InitMsg
+fn check_admin(seller: &HumanAddr, admin: Option<HumanAddr>) -> StdResult<Option<HumanAddr>> {
+    if admin.as_ref() == Some(seller) {
+        return Err(ContractError::SellerAdmin.into());
     }
-    if cred_hist > 0 {
-        score += cred_hist / 2 + cred_hist % 2;
+    Ok(admin)
+}
+
+fn calculate_estimation(a: Uint128, b: Uint128, mul : u128) -> u128 { (a.u128() / b.u128()) * mul + (a.u128() % b.u128()) }
+
+/// Returns StdResult<()> which is an error if a Dutch auction's rate schedule never descends,
+/// descends to 0, or starts below its floor
+///
+/// # Arguments
+///
+/// * `kind` - a reference to the kind of auction
+/// * `average_bid` - rate offered when the auction starts
+fn check_dutch_schedule(kind: &AuctionKind, average_bid: u128) -> StdResult<()> {
+    if let AuctionKind::Dutch {
+        step,
+        interval,
+        floor,
+    } = kind
+    {
+        if *step == Uint128(0) || *interval == 0 || *floor == Uint128(0) {
+            return Err(ContractError::InvalidDutchSchedule.into());
+        }
+        if floor.u128() > average_bid {
+            return Err(ContractError::DutchFloorTooHigh.into());
+        }
     }
-    if history.debts.is_some() {
-        score -= (history.debts.unwrap().u128() / 3 + history.debts.unwrap().u128() % 3) as i128;
+    Ok(())
+}
+
+/// Returns the reason the lender may not bid, None if they may.  Every lender may bid in an
+/// auction without a lender registry, and bids are refused when the registry can not answer
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `state` - reference to auction state
+/// * `lender` - reference to the address of the lender
+/// * `time` - time of the bid
+fn lender_refusal<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    state: &State,
+    lender: &HumanAddr,
+    time: u64,
+) -> Option<ContractError> {
+    let registry = state.lender_registry.as_ref()?;
+    let key = match may_load(&deps.storage, REGISTRY_KEY_KEY) {
+        Ok(Some(key)) => key,
+        _ => return Some(ContractError::NoRegistryKey),
+    };
+    let is_approved = RegistryQueryMsg::IsApproved {
+        address: lender.clone(),
+        time,
+        viewer: state.auction_addr.clone(),
+        key,
+    };
+    let answer: StdResult<RegistryQueryAnswer> = is_approved.query(
+        &deps.querier,
+        registry.code_hash.clone(),
+        registry.address.clone(),
+    );
+    match answer {
+        Ok(RegistryQueryAnswer::IsApproved { approved: true }) => None,
+        Ok(RegistryQueryAnswer::IsApproved { approved: false }) => {
+            Some(ContractError::NotApprovedLender)
+        }
+        // the registry's admin has not added the auction as a viewer yet
+        Ok(RegistryQueryAnswer::ViewingKeyError { msg }) => {
+            Some(ContractError::RegistryRefused { reason: msg })
+        }
+        Err(err) => Some(ContractError::RegistryUnavailable {
+            reason: err.to_string(),
+        }),
     }
-    score += (length as i128 * mul as i128) / 5 + (length as i128 * mul as i128) % 5;
-    let result = (score as u128 / mul + score as u128 % mul) / 100;
-    if result == 0 {
-        None
-    } else { Some(result) }
 }
 
-fn calculate_estimation(a: Uint128, b: Uint128, mul : u128) -> u128 { (a.u128() / b.u128()) * mul + (a.u128() % b.u128()) }
+/// Returns StdResult<CosmosMsg> that settles the seller's exposure recorded by the oracle
+///
+/// # Arguments
+///
+/// * `storage` - reference to the auction's storage
+/// * `state` - reference to auction state
+/// * `outstanding` - true if the auction resulted in a loan that is still outstanding
+fn settle_exposure_msg<S: ReadonlyStorage>(
+    storage: &S,
+    state: &State,
+    outstanding: bool,
+) -> StdResult<Option<CosmosMsg>> {
+    // auctions migrated from the first state layout never opened an exposure
+    let oracle = match &state.oracle_contract {
+        Some(oracle) => oracle,
+        None => return Ok(None),
+    };
+    OracleHandleMsg::SettleExposure {
+        borrower: state.seller.clone(),
+        outstanding,
+        auction_id: may_load(storage, AUCTION_ID_KEY)?,
+    }
+    .to_cosmos_msg(oracle.code_hash.clone(), oracle.address.clone(), None)
+    .map(Some)
+}
+
+/// Returns the log attributes of an auction event.  Amounts are only logged as their order of
+/// magnitude, and bid events log no amount at all, so events never reveal the size of a private
+/// bid.  Auctions hosted by a marketplace are logged as `<marketplace>/<auction id>`
+///
+/// # Arguments
+///
+/// * `storage` - reference to the auction's storage
+/// * `action` - name of the action that caused the event
+/// * `state` - reference to auction state after the action
+/// * `amount` - Optional amount of tokens the action moved, None for bids
+fn event_log<S: ReadonlyStorage>(
+    storage: &S,
+    action: &str,
+    state: &State,
+    amount: Option<u128>,
+) -> StdResult<Vec<LogAttribute>> {
+    let auction = match may_load::<u64, _>(storage, AUCTION_ID_KEY)? {
+        Some(auction_id) => format!("{}/{}", state.auction_addr, auction_id),
+        None => state.auction_addr.to_string(),
+    };
+    let mut logs = vec![log("action", action), log("auction", auction)];
+    if let Some(amount) = amount {
+        logs.push(log("amount_bucket", amount_bucket(amount)));
+    }
+    logs.push(log("status", event_status(state)));
+    Ok(logs)
+}
+
+/// status and message of a response, which is all a response log holds.  The amounts of the
+/// HandleAnswer are left out so the log never reveals them
+#[derive(Serialize)]
+struct ResponseLog<'a> {
+    /// success or failure
+    status: ResponseStatus,
+    /// execution description
+    message: &'a str,
+    /// Optional code of the error that refused the action without failing the transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<u16>,
+}
+
+/// Returns the log attribute holding the status and message of a response, padded with trailing
+/// spaces to a multiple of RESPONSE_BLOCK_SIZE so the size of the log does not reveal the outcome
+///
+/// # Arguments
+///
+/// * `status` - success or failure
+/// * `message` - execution description
+fn response_log(status: ResponseStatus, message: &str) -> LogAttribute {
+    padded_response_log(&ResponseLog {
+        status,
+        message,
+        code: None,
+    })
+}
+
+/// Returns the response log of an action refused without failing the transaction, such as a
+/// refunded bid.  It states the code of the error like a failed transaction would
+///
+/// # Arguments
+///
+/// * `error` - reference to the error that refused the action
+fn error_log(error: &ContractError) -> LogAttribute {
+    padded_response_log(&ResponseLog {
+        status: Failure,
+        message: &error.to_string(),
+        code: Some(error.code()),
+    })
+}
+
+/// Returns the "response" log attribute of a response, padded to a multiple of
+/// RESPONSE_BLOCK_SIZE
+///
+/// # Arguments
+///
+/// * `response` - reference to the status, message and code of the response
+fn padded_response_log(response: &ResponseLog) -> LogAttribute {
+    let mut resp = serde_json::to_string(response).unwrap();
+    let surplus = resp.len() % RESPONSE_BLOCK_SIZE;
+    if surplus > 0 {
+        resp.push_str(&" ".repeat(RESPONSE_BLOCK_SIZE - surplus));
+    }
+    log("response", resp)
+}
+
+/// Returns the order of magnitude of an amount, "0e00" or "1e<exponent>" with a two digit
+/// exponent, so every bucket has the same length
+///
+/// # Arguments
+///
+/// * `amount` - amount of tokens
+fn amount_bucket(amount: u128) -> String {
+    match amount {
+        0 => String::from("0e00"),
+        _ => format!("1e{:02}", amount.to_string().len() - 1),
+    }
+}
+
+/// Returns the status of the auction logged with its events, in the order AuctionInfo reports it
+///
+/// # Arguments
+///
+/// * `state` - reference to auction state
+fn event_status(state: &State) -> &'static str {
+    if state.is_unwound {
+        "unwound"
+    } else if state.is_cancelled {
+        "cancelled"
+    } else if state.is_completed {
+        "closed"
+    } else if state.is_paused {
+        "paused"
+    } else {
+        "open"
+    }
+}
+
+/// Returns HandleResult that sends rejected bid tokens back to the bidder
+///
+/// # Arguments
+///
+/// * `storage` - reference to the auction's storage
+/// * `state` - reference to auction state
+/// * `bidder` - address of owner of the rejected tokens
+/// * `amount` - Uint128 amount of the rejected tokens
+/// * `response` - response log stating why the bid was rejected
+fn refund_bid<S: ReadonlyStorage>(
+    storage: &S,
+    state: &State,
+    bidder: HumanAddr,
+    amount: Uint128,
+    response: LogAttribute,
+) -> HandleResult {
+    let mut logs = event_log(storage, "bid", state, None)?;
+    logs.push(response);
+    Ok(HandleResponse {
+        messages: vec![state.bid_contract.transfer_msg(bidder, amount)?],
+        log: logs,
+        data: None,
+    })
+}
+
+/// Returns the score of a bid according to the borrower's preferences
+///
+/// # Arguments
+///
+/// * `state` - reference to auction state
+/// * `bid` - reference to the bid being scored
+fn score_bid(state: &State, bid: &Bid) -> u128 {
+    state.preferences.score(
+        state.average_bid,
+        bid.amount,
+        bid.max_term.unwrap_or(0),
+        bid.grace_period,
+        bid.prepayment_penalty,
+    )
+}
 
 ///////////////////////////////////// Handle //////////////////////////////////////
 /// Handle incoming messages from nodes
@@ -134,16 +485,151 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
+    // upgrade state saved by an older version of the contract before handling anything
+    let migrated_from = match migrate_state(&mut deps.storage, &env) {
+        Ok(version) => version,
+        Err(e) => return pad_handle_result(Err(e), BLOCK_SIZE),
+    };
     let response = match msg {
         HandleMsg::Finalize { only_if_bids, .. } => try_finalize(deps, env, only_if_bids, false),
         HandleMsg::ReturnAll { .. } => try_finalize(deps, env, false, true),
-        HandleMsg::Receive { from, amount, ..} => try_receive(deps, env, from, amount),
-        HandleMsg::ViewBid { .. } => try_view_bid(deps, &env.message.sender)
+        HandleMsg::Receive { from, amount, msg, ..} => try_receive(deps, env, from, amount, msg),
+        HandleMsg::ViewBid { .. } => try_view_bid(deps, &env.message.sender),
+        HandleMsg::UpdateAuction {
+            description,
+            max_bid,
+            ends_at,
+        } => try_update_auction(deps, env, description, max_bid, ends_at),
+        HandleMsg::CancelAuction { .. } => try_cancel_auction(deps, env),
+        HandleMsg::SetFee { fee, .. } => try_set_fee(deps, env, fee),
+        HandleMsg::Pause { .. } => try_set_paused(deps, env, true),
+        HandleMsg::Unpause { .. } => try_set_paused(deps, env, false),
+        HandleMsg::EmergencyUnwind { .. } => try_emergency_unwind(deps, env),
+        HandleMsg::Migrate { .. } => try_migrate(migrated_from),
+        HandleMsg::CreateViewingKey { entropy, .. } => try_create_key(deps, env, entropy),
+        HandleMsg::SetViewingKey { key, .. } => try_set_key(deps, env, key),
+        HandleMsg::RevokePermit { permit_name, .. } => try_revoke_permit(deps, env, permit_name),
     };
     pad_handle_result(response, BLOCK_SIZE)
 }
 
-/// View active bid on auction
+/// Report the state migration performed before handling the message
+///
+/// # Arguments
+///
+/// * `migrated_from` - Optional version the state was upgraded from, None if it was current
+fn try_migrate(migrated_from: Option<u16>) -> HandleResult {
+    let message = match migrated_from {
+        Some(version) => format!(
+            "State migrated from version {} to version {}",
+            version, STATE_VERSION
+        ),
+        None => format!("State is already at version {}", STATE_VERSION),
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message,
+        })?),
+    })
+}
+
+/// Generate a viewing key for the calling address
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `entropy` - entropy used to generate the key
+fn try_create_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: String,
+) -> HandleResult {
+    let prng_seed: Vec<u8> = load(&deps.storage, PRNG_SEED_KEY)?;
+    let key = ViewingKey::new(&env, &prng_seed, entropy.as_bytes());
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    write_viewing_key(&mut deps.storage, &owner, &key);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CreateViewingKey { key })?),
+    })
+}
+
+/// Set the viewing key of the calling address
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `key` - the viewing key
+fn try_set_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> HandleResult {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    write_viewing_key(&mut deps.storage, &owner, &ViewingKey(key));
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetViewingKey { status: Success })?),
+    })
+}
+
+/// Revoke a query permit of the calling address
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `permit_name` - name of the permit to revoke
+pub(crate) fn try_revoke_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    permit_name: String,
+) -> HandleResult {
+    RevokedPermits::revoke_permit(
+        &mut deps.storage,
+        PREFIX_REVOKED_PERMITS,
+        &env.message.sender,
+        &permit_name,
+    );
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RevokePermit { status: Success })?),
+    })
+}
+
+/// Returns StdResult<Option<Bid>> of the active bid of an address
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `state` - a reference to the auction state
+/// * `bidder` - a reference to the canonical address of the bidder
+fn load_active_bid<S: ReadonlyStorage>(
+    storage: &S,
+    state: &State,
+    bidder: &CanonicalAddr,
+) -> StdResult<Option<Bid>> {
+    if state.bidders.contains(&bidder.as_slice().to_vec()) {
+        load_bid(storage, bidder.as_slice())
+    } else {
+        Ok(None)
+    }
+}
+
+/// View active bid on auction.  Kept for backwards compatibility, the MyBid query displays the
+/// same bid without the cost of a transaction
 ///
 /// # Arguments
 ///
@@ -153,28 +639,24 @@ fn try_view_bid<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     bidder: &HumanAddr,
 ) -> HandleResult {
-    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let state = load_state(&deps.storage)?;
 
     let bidder_raw_format = &deps.api.canonical_address(bidder)?;
     let mut amount_bid: Option<Uint128> = None;
+    let mut bid_score: Option<Uint128> = None;
     let mut message = String::new();
     let status: ResponseStatus;
 
-    if state.bidders.contains(&bidder_raw_format.as_slice().to_vec()) {
-        let bid: Option<Bid> = may_load(&deps.storage, bidder_raw_format.as_slice())?;
-        if let Some(found_bid) = bid {
-            status = Success;
-            amount_bid = Some(Uint128(found_bid.amount));
-            message.push_str(&format!(
-                "Bid placed {} UTC",
-                NaiveDateTime::from_timestamp(found_bid.timestamp as i64, 0)
-                    .format("%Y-%m-%d %H:%M:%S")
-            ));
-        } else {
-            status = Failure;
-            message.push_str(&format!("No active bid for address: {}", bidder));
-        }
-        // no active bid found
+    if let Some(found_bid) = load_active_bid(&deps.storage, &state, bidder_raw_format)? {
+        status = Success;
+        amount_bid = Some(Uint128(found_bid.amount));
+        bid_score = Some(Uint128(score_bid(&state, &found_bid)));
+        message.push_str(&format!(
+            "Bid placed {} UTC",
+            NaiveDateTime::from_timestamp(found_bid.timestamp as i64, 0)
+                .format("%Y-%m-%d %H:%M:%S")
+        ));
+    // no active bid found
     } else {
         status = Failure;
         message.push_str(&format!("No active bid for address: {}", bidder));
@@ -188,6 +670,7 @@ fn try_view_bid<S: Storage, A: Api, Q: Querier>(
             previous_bid: None,
             amount_bid,
             amount_returned: None,
+            bid_score,
         })?),
     })
 }
@@ -195,7 +678,7 @@ fn try_view_bid<S: Storage, A: Api, Q: Querier>(
 /// Returns HandleResult
 ///
 /// process the Receive message sent after either bid or sell token contract sent tokens to
-/// auction escrow
+/// auction escrow.  Returning an error makes the token contract revert the transfer
 ///
 /// # Arguments
 ///
@@ -203,24 +686,83 @@ fn try_view_bid<S: Storage, A: Api, Q: Querier>(
 /// * `env` - Env of contract's environment
 /// * `from` - address of owner of tokens sent to escrow
 /// * `amount` - Uint128 amount sent to escrow
+/// * `msg` - Optional base64 encoded ReceiveMsg stating what the tokens are for
 fn try_receive<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     from: HumanAddr,
-    amount: Uint128
+    amount: Uint128,
+    msg: Option<Binary>,
 ) -> HandleResult {
-    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    let mut state = load_state(&deps.storage)?;
 
-    if env.message.sender == state.sell_contract.address {
-        try_consign(deps, from, amount, &mut state)
-    } else if env.message.sender == state.bid_contract.address {
-        try_bid(deps, env, from, amount, &mut state)
-    } else {
-        let message = format!(
-            "Address: {} is not a token in this auction",
-            env.message.sender
-        );
-        Err(StdError::generic_err(message))
+    let from_sell = env.message.sender == state.sell_contract.address;
+    let from_bid = env.message.sender == state.bid_contract.address;
+    if !from_sell && !from_bid {
+        return Err(ContractError::NotAuctionToken {
+            address: env.message.sender,
+        }
+        .into());
+    }
+
+    // without a msg, the token that was sent determines what the tokens are for
+    let receive_msg = match msg {
+        Some(bin) => from_binary(&bin).map_err(|e| ContractError::InvalidReceiveMsg {
+            reason: e.to_string(),
+        })?,
+        None if from_sell => ReceiveMsg::Consign {},
+        None => ReceiveMsg::Bid {
+            max_term: None,
+            grace_period: None,
+            prepayment_penalty: None,
+            note: None,
+        },
+    };
+
+    // while paused, no new consignments or bids are accepted
+    if state.is_paused {
+        if let ReceiveMsg::Consign {} | ReceiveMsg::Bid { .. } = receive_msg {
+            return Err(ContractError::Paused.into());
+        }
+    }
+
+    match receive_msg {
+        ReceiveMsg::Consign {} if from_sell => try_consign(deps, from, amount, &mut state),
+        ReceiveMsg::Bid {
+            max_term,
+            grace_period,
+            prepayment_penalty,
+            note,
+        } if from_bid => {
+            if matches!(state.kind, AuctionKind::Dutch { .. }) {
+                // a Dutch auction only sells at its current rate, so terms would be dropped
+                if max_term.is_some()
+                    || grace_period.is_some()
+                    || prepayment_penalty.is_some()
+                    || note.is_some()
+                {
+                    return Err(ContractError::DutchBidTerms.into());
+                }
+                return try_accept_rate(deps, env, from, amount, &mut state);
+            }
+            let new_bid = Bid {
+                amount: amount.u128(),
+                timestamp: env.block.time,
+                max_term,
+                grace_period: grace_period.unwrap_or(0),
+                prepayment_penalty: prepayment_penalty.unwrap_or(0),
+                note,
+            };
+            try_bid(deps, from, new_bid, &mut state)
+        }
+        // loans are not serviced by the auction, so their tokens are refused and the Send reverts
+        ReceiveMsg::Repay { .. } | ReceiveMsg::DepositCollateral {} if from_bid => {
+            Err(ContractError::NoLoanServicing.into())
+        }
+        _ => Err(ContractError::WrongToken {
+            address: env.message.sender,
+        }
+        .into()),
     }
 }
 
@@ -239,33 +781,24 @@ fn try_consign<S: Storage, A: Api, Q: Querier>(
 ) -> HandleResult {
     // if not the auction owner, send the tokens back
     if owner != state.seller {
-        return Err(StdError::generic_err(
-            "Only auction creator can consign tokens for sale. Your tokens have been returned",
-        ));
+        return Err(ContractError::NotSellerConsign.into());
     }
     // if auction is over, send the tokens back
     if state.is_completed {
-        return Err(StdError::generic_err(
-            "Auction has ended. Your tokens have been returned",
-        ));
+        return Err(ContractError::ConsignAfterEnd.into());
     }
     // if tokens to be sold have already been consigned, return these tokens
     if state.tokens_consigned {
-        return Err(StdError::generic_err(
-            "Tokens to be sold have already been consigned. Your tokens have been returned",
-        ));
+        return Err(ContractError::AlreadyConsigned.into());
     }
 
     let consign_total = state.currently_consigned + amount.u128();
     let mut log_msg = String::new();
     let mut cos_msg = Vec::new();
     let status: ResponseStatus;
-    let mut excess: Option<Uint128> = None;
-    let mut needed: Option<Uint128> = None;
     // if consignment amount < client score, ask for remaining balance
     if consign_total < state.score {
         state.currently_consigned = consign_total;
-        needed = Some(Uint128(state.score - consign_total));
         status = Failure;
         log_msg.push_str(
             "You have not consigned the full amount to be sold. You need to consign additional \
@@ -279,25 +812,19 @@ fn try_consign<S: Storage, A: Api, Q: Querier>(
         log_msg.push_str("Tokens to be sold have been consigned to the auction");
         // if consigned more than needed, return excess tokens
         if consign_total > state.score {
-            excess = Some(Uint128(consign_total - state.score));
-            cos_msg.push(state.sell_contract.transfer_msg(owner, excess.unwrap())?);
+            let excess = Uint128(consign_total - state.score);
+            cos_msg.push(state.sell_contract.transfer_msg(owner, excess)?);
             log_msg.push_str(". Excess tokens have been returned");
         }
     }
 
-    save(&mut deps.storage, CONFIG_KEY, &state)?;
-
-    let resp = serde_json::to_string(&HandleAnswer::Consign {
-        status,
-        message: log_msg,
-        amount_consigned: Some(Uint128(state.currently_consigned)),
-        amount_needed: needed,
-        amount_returned: excess,
-    }).unwrap();
+    save_state(&mut deps.storage, &state)?;
 
+    let mut logs = event_log(&deps.storage, "consign", state, Some(amount.u128()))?;
+    logs.push(response_log(status, &log_msg));
     Ok(HandleResponse {
         messages: cos_msg,
-        log: vec![log("response", resp)],
+        log: logs,
         data: None,
     })
 }
@@ -307,93 +834,85 @@ fn try_consign<S: Storage, A: Api, Q: Querier>(
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
 /// * `bidder` - address of owner of tokens sent to escrow
-/// * `amount` - Uint128 amount sent to escrow
+/// * `new_bid` - the bid made with the tokens sent to escrow
 /// * `state` - mutable reference to auction state
 fn try_bid<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    env: Env,
     bidder: HumanAddr,
-    amount: Uint128,
+    new_bid: Bid,
     state: &mut State,
 ) -> HandleResult {
+    let amount = Uint128(new_bid.amount);
     // if auction is over, send the tokens back
-    if state.is_completed {
-        return Err(StdError::generic_err(
-            "Auction has ended. Bid tokens have been returned",
-        ));
+    if state.is_completed || state.ends_at.map_or(false, |end| new_bid.timestamp >= end) {
+        return Err(ContractError::BidAfterEnd.into());
     }
     // don't accept a 0 bid
     if amount == Uint128(0) {
-        return Err(StdError::generic_err(
-            "Bid must be greater than 0",
-        ));
+        return Err(ContractError::ZeroBid.into());
     }
-
-    let mut number = amount.u128();
-    let mut len : u32 = 0;
-    while number > 0 {
-        number /= 10;
-        len += 1;
+    if new_bid.max_term == Some(0) {
+        return Err(ContractError::ZeroTerm.into());
+    }
+    if new_bid.prepayment_penalty > MAX_PREPAYMENT_PENALTY {
+        return Err(ContractError::PenaltyTooHigh {
+            max: MAX_PREPAYMENT_PENALTY,
+        }
+        .into());
+    }
+    if new_bid.note.as_ref().map_or(false, |n| n.len() > MAX_NOTE_LEN) {
+        return Err(ContractError::NoteTooLong { max: MAX_NOTE_LEN }.into());
     }
-    let perfect_proposal = 10u128.pow(len - 1);
 
-    // don't accept a 0 bid
+    let perfect_proposal = perfect_proposal(amount.u128());
+
+    // don't accept a bid equal to the perfect proposal
     if amount == Uint128(perfect_proposal) {
-        return Err(StdError::generic_err(
-            "You can't expect getting lesser or equal to what you propose",
-        ));
+        return Err(ContractError::PerfectProposal.into());
+    }
+
+    // if lender is not approved by the registry, send the tokens back
+    if let Some(refusal) = lender_refusal(deps, state, &bidder, new_bid.timestamp) {
+        return refund_bid(&deps.storage, state, bidder, amount, error_log(&refusal));
     }
 
     // if bid is greater than client estimation, send the tokens back
     if amount.u128() > state.average_bid {
-        let message =
-            String::from("Bid was greater than estimation allowed. Bid tokens have been returned");
-
-        let resp = serde_json::to_string(&HandleAnswer::Bid {
-            status: Failure,
-            message,
-            previous_bid: None,
-            amount_bid: None,
-            amount_returned: Some(amount),
-        })
-            .unwrap();
-
-        return Ok(HandleResponse {
-            messages: vec![state.bid_contract.transfer_msg(bidder, amount)?],
-            log: vec![log("response", resp)],
-            data: None,
-        });
+        return refund_bid(
+            &deps.storage,
+            state,
+            bidder,
+            amount,
+            response_log(
+                Failure,
+                "Bid was greater than estimation allowed. Bid tokens have been returned",
+            ),
+        );
     }
 
     let mut return_amount: Option<Uint128> = None;
     let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let new_score = score_bid(state, &new_bid);
 
     // if there is an active bid from this address
     if state.bidders.contains(&bidder_raw.as_slice().to_vec()) {
-        let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
+        let bid = load_bid(&deps.storage, bidder_raw.as_slice())?;
         if let Some(old_bid) = bid {
-            // if new bid is >= the old bid, keep old bid and return this one
-            if amount.u128() >= old_bid.amount {
+            // if new bid does not score better than the old bid, keep old bid and return this one
+            if new_score <= score_bid(state, &old_bid) {
                 let message = String::from(
-                    "New bid greater than or equal to previous bid. Newly bid tokens have been returned",
+                    "New bid does not score better than previous bid. Newly bid tokens have been returned",
                 );
 
-                let resp = serde_json::to_string(&HandleAnswer::Bid {
-                    status: Failure,
-                    message,
-                    previous_bid: Some(Uint128(old_bid.amount)),
-                    amount_bid: Some(amount),
-                    amount_returned: Some(amount),
-                }).unwrap();
-
+                let mut logs = event_log(&deps.storage, "bid", state, None)?;
+                logs.push(response_log(Failure, &message));
                 return Ok(HandleResponse {
                     messages: vec![state.bid_contract.transfer_msg(bidder, amount)?],
-                    log: vec![log("response", resp)],
+                    log: logs,
                     data: None,
                 });
-                // new bid is less, save the new bid, and return the old one, so mark for return
+                // new bid scores better, save the new bid, and return the old one, so mark for return
             } else {
                 return_amount = Some(Uint128(old_bid.amount));
             }
@@ -402,77 +921,453 @@ fn try_bid<S: Storage, A: Api, Q: Querier>(
     } else {
         // insert in list of bidders and save
         state.bidders.insert(bidder_raw.as_slice().to_vec());
-        save(&mut deps.storage, CONFIG_KEY, &state)?;
+        save_state(&mut deps.storage, &state)?;
     }
-    let new_bid = Bid {
-        amount: amount.u128(),
-        timestamp: env.block.time,
-    };
     save(&mut deps.storage, bidder_raw.as_slice(), &new_bid)?;
 
     let mut message = String::from("Bid accepted");
-    let mut cos_msg = Vec::new();
-
-    // if need to return the old bid
-    if let Some(returned) = return_amount {
-        cos_msg.push(state.bid_contract.transfer_msg(bidder, returned)?);
-        message.push_str(". Previously bid tokens have been returned");
-    }
-    let resp = serde_json::to_string(&HandleAnswer::Bid {
-        status: Success,
-        message,
-        previous_bid: None,
-        amount_bid: Some(amount),
-        amount_returned: return_amount,
-    }).unwrap();
 
+    // every bid outcome sends the bidder one transfer, so the messages do not reveal whether the
+    // bid was accepted, rejected, or replaced an earlier one
+    let returned = match return_amount {
+        Some(returned) => {
+            message.push_str(". Previously bid tokens have been returned");
+            returned
+        }
+        None => Uint128(0),
+    };
+    let cos_msg = vec![state.bid_contract.transfer_msg(bidder, returned)?];
+    let mut logs = event_log(&deps.storage, "bid", state, None)?;
+    logs.push(response_log(Success, &message));
     Ok(HandleResponse {
         messages: cos_msg,
-        log: vec![log("response", resp)],
+        log: logs,
         data: None,
     })
 }
 
-/// Returns HandleResult
-///
-/// Closing the auction and sending all the tokens in escrow to where they belong
+/// Accept the current rate of a Dutch auction.  The first lender to send at least the current
+/// rate wins, and the auction is finalized in the same transaction
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `only_if_bids` - true if auction should stay open if there are no bids
-/// * `return_all` - true if being called from the return_all fallback plan
-fn try_finalize<S: Storage, A: Api, Q: Querier>(
+/// * `lender` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `state` - mutable reference to auction state
+fn try_accept_rate<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    only_if_bids: bool,
-    return_all: bool,
+    lender: HumanAddr,
+    amount: Uint128,
+    state: &mut State,
 ) -> HandleResult {
-    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    // if auction is over, send the tokens back
+    if state.is_completed || state.ends_at.map_or(false, |end| env.block.time >= end) {
+        return Err(ContractError::BidAfterEnd.into());
+    }
+    // don't accept a 0 bid
+    if amount == Uint128(0) {
+        return Err(ContractError::ZeroBid.into());
+    }
+    // if lender is not approved by the registry, send the tokens back
+    if let Some(refusal) = lender_refusal(deps, state, &lender, env.block.time) {
+        return refund_bid(&deps.storage, state, lender, amount, error_log(&refusal));
+    }
+    // the winner is paid immediately, so the sale tokens must already be in escrow
+    if !state.tokens_consigned {
+        return Err(ContractError::NotConsigned.into());
+    }
+    let rate = match state
+        .kind
+        .rate_at(state.average_bid, state.started_at, env.block.time)
+    {
+        Some(rate) => rate,
+        None => {
+            return Err(ContractError::NoCurrentRate.into())
+        }
+    };
+    if amount.u128() < rate {
+        return Err(ContractError::BelowCurrentRate { rate }.into());
+    }
+
+    let (mut cos_msg, _) = settlement_msgs(state, &lender, rate)?;
+    let mut message = String::from("Bid accepted at the current rate. Auction has been finalized");
+    // if sent more than the current rate, return excess tokens
+    if amount.u128() > rate {
+        cos_msg.push(
+            state
+                .bid_contract
+                .transfer_msg(lender.clone(), Uint128(amount.u128() - rate))?,
+        );
+        message.push_str(". Excess tokens have been returned");
+    }
+
+    state.currently_consigned = 0;
+    state.winning_bid = rate;
+    state.lender = Some(lender);
+    state.is_completed = true;
+    save_state(&mut deps.storage, &state)?;
+    cos_msg.extend(settle_exposure_msg(&deps.storage, state, true)?);
+
+    let mut logs = event_log(&deps.storage, "bid", state, None)?;
+    logs.push(response_log(Success, &message));
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: logs,
+        data: None,
+    })
+}
 
+/// Returns StdResult<(Vec<CosmosMsg>, u128)> of the messages that swap the winning bid for the
+/// tokens for sale, and the platform fee taken from the swap
+///
+/// # Arguments
+///
+/// * `state` - reference to auction state
+/// * `lender` - reference to the address of the winning lender
+/// * `winning_bid` - amount of the winning bid
+fn settlement_msgs(
+    state: &State,
+    lender: &HumanAddr,
+    winning_bid: u128,
+) -> StdResult<(Vec<CosmosMsg>, u128)> {
+    let mut to_seller = winning_bid;
+    let mut to_lender = state.score;
+    let mut fee_msg: Option<CosmosMsg> = None;
+    let mut fee = 0;
+    if let Some(config) = &state.fee {
+        match config.source {
+            FeeSource::WinningBid => {
+                fee = config.fee_of(winning_bid);
+                to_seller -= fee;
+                if fee > 0 {
+                    fee_msg = Some(
+                        state
+                            .bid_contract
+                            .transfer_msg(config.treasury.clone(), Uint128(fee))?,
+                    );
+                }
+            }
+            FeeSource::Consignment => {
+                fee = config.fee_of(state.score);
+                to_lender -= fee;
+                if fee > 0 {
+                    fee_msg = Some(
+                        state
+                            .sell_contract
+                            .transfer_msg(config.treasury.clone(), Uint128(fee))?,
+                    );
+                }
+            }
+        }
+    }
+    let mut cos_msg = vec![
+        state
+            .bid_contract
+            .transfer_msg(state.seller.clone(), Uint128(to_seller))?,
+        state
+            .sell_contract
+            .transfer_msg(lender.clone(), Uint128(to_lender))?,
+    ];
+    if let Some(fee_msg) = fee_msg {
+        cos_msg.push(fee_msg);
+    }
+    Ok((cos_msg, fee))
+}
+
+/// Returns StdResult<()> which is an error if the calling address is not the admin
+///
+/// # Arguments
+///
+/// * `state` - reference to auction state
+/// * `sender` - reference to the calling address
+fn enforce_admin(state: &State, sender: &HumanAddr) -> StdResult<()> {
+    if state.admin.as_ref() != Some(sender) {
+        return Err(ContractError::NotAdmin.into());
+    }
+    Ok(())
+}
+
+/// Pause or unpause new bids and consignments.  Only the admin can pause the auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `paused` - true to pause the auction, false to unpause it
+fn try_set_paused<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    paused: bool,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+
+    enforce_admin(&state, &env.message.sender)?;
+    if state.is_completed {
+        return Err(ContractError::AdminAfterEnd.into());
+    }
+    state.is_paused = paused;
+    save_state(&mut deps.storage, &state)?;
+
+    let (action, message) = if paused {
+        ("pause", "Auction has been paused")
+    } else {
+        ("unpause", "Auction has been unpaused")
+    };
+    Ok(HandleResponse {
+        messages: vec![],
+        log: event_log(&deps.storage, action, &state, Some(0))?,
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: String::from(message),
+        })?),
+    })
+}
+
+/// Close the auction without a winner and return every bid and the consigned tokens.  Only the
+/// admin can unwind the auction, and not once it has settled to a winner
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_emergency_unwind<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+
+    enforce_admin(&state, &env.message.sender)?;
+    if state.is_completed && state.lender.is_some() {
+        return Err(ContractError::UnwindSettled.into());
+    }
+
+    let mut cos_msg = Vec::new();
+    for bidder in state.bidders.drain() {
+        let bid = load_bid(&deps.storage, bidder.as_slice())?;
+        if let Some(found_bid) = bid {
+            cos_msg.push(state.bid_contract.transfer_msg(
+                deps.api.human_address(&CanonicalAddr::from(bidder.as_slice()))?,
+                Uint128(found_bid.amount),
+            )?);
+        }
+        remove(&mut deps.storage, bidder.as_slice());
+    }
+    let mut amount_returned: Option<Uint128> = None;
+    if state.currently_consigned > 0 {
+        cos_msg.push(
+            state
+                .sell_contract
+                .transfer_msg(state.seller.clone(), Uint128(state.currently_consigned))?,
+        );
+        amount_returned = Some(Uint128(state.currently_consigned));
+        state.currently_consigned = 0;
+    }
+    if !state.is_completed {
+        state.is_completed = true;
+        cos_msg.extend(settle_exposure_msg(&deps.storage, &state, false)?);
+    }
+    state.is_paused = false;
+    state.is_unwound = true;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: event_log(&deps.storage, "emergency_unwind", &state, Some(0))?,
+        data: Some(to_binary(&HandleAnswer::CloseAuction {
+            status: Success,
+            message: String::from("Auction has been unwound.  All outstanding funds have been returned"),
+            winning_bid: None,
+            amount_returned,
+            fee: None,
+        })?),
+    })
+}
+
+/// Set the platform fee.  Only the admin can set the fee, and only before the first bid, so
+/// lenders always bid knowing the fee
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `fee` - Optional new fee configuration, None removes the fee
+fn try_set_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    fee: Option<FeeConfig>,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+
+    enforce_admin(&state, &env.message.sender)?;
+    if state.is_completed {
+        return Err(ContractError::AdminAfterEnd.into());
+    }
+    if !state.bidders.is_empty() {
+        return Err(ContractError::FeeAfterBids.into());
+    }
+    if let Some(config) = &fee {
+        config.validate()?;
+    }
+    state.fee = fee;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: event_log(&deps.storage, "set_fee", &state, Some(0))?,
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: String::from("Fee has been set"),
+        })?),
+    })
+}
+
+/// Amend the auction parameters.  Only allowed before the first bid
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `description` - Optional new description of the auction
+/// * `max_bid` - Optional new average bid that will be accepted
+/// * `ends_at` - Optional new time after which bids are no longer accepted
+fn try_update_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    description: Option<String>,
+    max_bid: Option<Uint128>,
+    ends_at: Option<u64>,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+
+    if env.message.sender != state.seller {
+        return Err(ContractError::NotSellerUpdate.into());
+    }
+    if state.is_completed {
+        return Err(ContractError::UpdateAfterEnd.into());
+    }
+    if !state.bidders.is_empty() {
+        return Err(ContractError::UpdateAfterBids.into());
+    }
+    if let Some(max_bid) = max_bid {
+        if max_bid == Uint128(0) {
+            return Err(ContractError::ZeroAverageBid.into());
+        }
+        state.average_bid = max_bid.u128();
+    }
+    check_dutch_schedule(&state.kind, state.average_bid)?;
+    if let Some(ends_at) = ends_at {
+        if ends_at <= env.block.time {
+            return Err(ContractError::EndInPast.into());
+        }
+        state.ends_at = Some(ends_at);
+    }
+    if description.is_some() {
+        state.description = description;
+    }
+
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: event_log(&deps.storage, "update", &state, Some(0))?,
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: String::from("Auction has been updated"),
+        })?),
+    })
+}
+
+/// Cancel the auction and return the consigned tokens.  Only allowed before the first bid
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_cancel_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+
+    if env.message.sender != state.seller {
+        return Err(ContractError::NotSellerCancel.into());
+    }
+    if state.is_completed {
+        return Err(ContractError::CancelAfterEnd.into());
+    }
+    if !state.bidders.is_empty() {
+        return Err(ContractError::CancelAfterBids.into());
+    }
+
+    let mut cos_msg = Vec::new();
+    let mut amount_returned: Option<Uint128> = None;
+    if state.currently_consigned > 0 {
+        cos_msg.push(
+            state
+                .sell_contract
+                .transfer_msg(state.seller.clone(), Uint128(state.currently_consigned))?,
+        );
+        amount_returned = Some(Uint128(state.currently_consigned));
+        state.currently_consigned = 0;
+    }
+    state.is_completed = true;
+    state.is_cancelled = true;
+    save_state(&mut deps.storage, &state)?;
+    cos_msg.extend(settle_exposure_msg(&deps.storage, &state, false)?);
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: event_log(&deps.storage, "cancel", &state, Some(0))?,
+        data: Some(to_binary(&HandleAnswer::CancelAuction {
+            status: Success,
+            message: String::from("Auction has been cancelled"),
+            amount_returned,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// Closing the auction and sending all the tokens in escrow to where they belong
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `only_if_bids` - true if auction should stay open if there are no bids
+/// * `return_all` - true if being called from the return_all fallback plan
+fn try_finalize<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    only_if_bids: bool,
+    return_all: bool,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+
+    // a paused auction waits for the admin to unpause or unwind it
+    if state.is_paused {
+        return Err(ContractError::FinalizePaused.into());
+    }
     // can only do a return_all if the auction is closed
     if return_all && !state.is_completed {
-        return Err(StdError::generic_err(
-            "return_all can only be executed after the auction has ended",
-        ));
+        return Err(ContractError::ReturnAllBeforeEnd.into());
     }
     // if not the auction owner, can't finalize, but you can return_all
     if !return_all && env.message.sender != state.seller {
-        return Err(StdError::generic_err(
-            "Only auction creator can finalize the sal",
-        ));
+        return Err(ContractError::NotSellerFinalize.into());
     }
     // if there are no active bids, and owner only wants to close if bids
     if !state.is_completed && only_if_bids && state.bidders.is_empty() {
-        return Err(StdError::generic_err(
-            "Did not close because there are no active bids",
-        ));
+        return Err(ContractError::NoActiveBids.into());
     }
     let mut cos_msg = Vec::new();
     let mut update_state = false;
     let mut winning_amount: Option<Uint128> = None;
     let mut amount_returned: Option<Uint128> = None;
+    let mut fee_taken: Option<Uint128> = None;
 
     let no_bids = state.bidders.is_empty();
     // if there were bids
@@ -484,7 +1379,7 @@ fn try_finalize<S: Storage, A: Api, Q: Querier>(
         }
         let mut bid_list: Vec<OwnedBid> = Vec::new();
         for bidder in &state.bidders {
-            let bid: Option<Bid> = may_load(&deps.storage, bidder.as_slice())?;
+            let bid = load_bid(&deps.storage, bidder.as_slice())?;
             if let Some(found_bid) = bid {
                 bid_list.push(OwnedBid {
                     bidder: CanonicalAddr::from(bidder.as_slice()),
@@ -494,23 +1389,22 @@ fn try_finalize<S: Storage, A: Api, Q: Querier>(
         }
         // closing an auction that has been fully consigned
         if state.tokens_consigned && !state.is_completed {
+            // best scoring bid goes last, earliest bid wins a tie
             bid_list.sort_by(|a, b| {
-                b.bid
-                    .amount
-                    .cmp(&a.bid.amount)
+                score_bid(&state, &a.bid)
+                    .cmp(&score_bid(&state, &b.bid))
                     .then(b.bid.timestamp.cmp(&a.bid.timestamp))
             });
             // if there was a winner, swap the tokens
             if let Some(winning_bid) = bid_list.pop() {
-                cos_msg.push(
-                    state
-                        .bid_contract
-                        .transfer_msg(state.seller.clone(), Uint128(winning_bid.bid.amount))?,
-                );
-                cos_msg.push(state.sell_contract.transfer_msg(
-                    deps.api.human_address(&winning_bid.bidder)?,
-                    Uint128(state.score),
-                )?);
+                let lender = deps.api.human_address(&winning_bid.bidder)?;
+                let (mut swap_msgs, fee) =
+                    settlement_msgs(&state, &lender, winning_bid.bid.amount)?;
+                cos_msg.append(&mut swap_msgs);
+                if fee > 0 {
+                    fee_taken = Some(Uint128(fee));
+                }
+                state.lender = Some(lender);
                 state.currently_consigned = 0;
                 update_state = true;
                 winning_amount = Some(Uint128(winning_bid.bid.amount));
@@ -546,13 +1440,14 @@ fn try_finalize<S: Storage, A: Api, Q: Querier>(
         state.currently_consigned = 0;
         update_state = true;
     }
-    // mark that auction had ended
+    // mark that auction had ended, and keep the seller's exposure only if it resulted in a loan
     if !state.is_completed {
         state.is_completed = true;
         update_state = true;
+        cos_msg.extend(settle_exposure_msg(&deps.storage, &state, state.lender.is_some())?);
     }
     if update_state {
-        save(&mut deps.storage, CONFIG_KEY, &state)?;
+        save_state(&mut deps.storage, &state)?;
     }
 
     let log_msg = if winning_amount.is_some() {
@@ -574,14 +1469,18 @@ fn try_finalize<S: Storage, A: Api, Q: Querier>(
     } else {
         "Auction has been closed".to_string()
     };
+    // the winning bid is public once the auction is closed, returned bids stay private
+    let action = if return_all { "return_all" } else { "finalize" };
+    let moved = winning_amount.map_or(0, |amount| amount.u128());
     Ok(HandleResponse {
         messages: cos_msg,
-        log: vec![],
+        log: event_log(&deps.storage, action, &state, Some(moved))?,
         data: Some(to_binary(&HandleAnswer::CloseAuction {
             status: Success,
             message: log_msg,
             winning_bid: winning_amount,
             amount_returned,
+            fee: fee_taken,
         })?),
     })
 }
@@ -595,23 +1494,296 @@ fn try_finalize<S: Storage, A: Api, Q: Querier>(
 /// * `msg` - QueryMsg passed in with the query call
 pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
     let response = match msg {
-        QueryMsg::AuctionInfo { .. } => try_query_info(deps),
+        QueryMsg::AuctionInfo { .. } => try_query_info(&deps.storage, &deps.querier),
+        QueryMsg::BidCount { .. } => try_query_bid_count(&deps.storage),
+        QueryMsg::ListBids {
+            address,
+            key,
+            page,
+            page_size,
+        } => try_list_bids(
+            &deps.storage,
+            &deps.api,
+            address,
+            key,
+            page.unwrap_or(0),
+            page_size,
+        ),
+        QueryMsg::MyBid { address, key } => {
+            try_query_my_bid_with_key(&deps.storage, &deps.api, &address, key)
+        }
+        QueryMsg::BidScore { address, key } => {
+            try_query_bid_score(&deps.storage, &deps.api, &address, key)
+        }
+        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
     };
     pad_query_result(response, BLOCK_SIZE)
 }
 
-/// View auction details
+/// Returns StdResult<bool> which is true if the viewing key is correct for the address
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the address whose key is being checked
+/// * `key` - viewing key
+fn is_key_valid<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    address: &HumanAddr,
+    key: String,
+) -> StdResult<bool> {
+    let owner = api.canonical_address(address)?;
+    let viewing_key = ViewingKey(key);
+    // check against a dummy hash when no key was set, so the timing does not reveal it
+    match read_viewing_key(storage, &owner) {
+        Some(hashed) => Ok(viewing_key.check_viewing_key(&hashed)),
+        None => {
+            viewing_key.check_viewing_key(&[0u8; VIEWING_KEY_SIZE]);
+            Ok(false)
+        }
+    }
+}
+
+/// Returns QueryResult from validating a permit and then running the query it authenticates.
+/// secret_toolkit reads the permit revocations through a Storage, so this is the only query
+/// that needs the full Extern
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
-fn try_query_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
-    let state: State = load(&deps.storage, CONFIG_KEY)?;
+/// * `permit` - permit signed by the querier
+/// * `query` - query to run on behalf of the permit's signer
+fn permit_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> QueryResult {
+    let state = load_state(&deps.storage)?;
+    let account = validate(deps, PREFIX_REVOKED_PERMITS, &permit, state.auction_addr)?;
+
+    try_permit_query(&deps.storage, &deps.api, &permit, &account, query)
+}
+
+/// Returns QueryResult of a query authenticated by an already validated permit
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `permit` - a reference to the validated permit
+/// * `account` - a reference to the address that signed the permit
+/// * `query` - query to run on behalf of the permit's signer
+pub(crate) fn try_permit_query<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    permit: &Permit,
+    account: &HumanAddr,
+    query: QueryWithPermit,
+) -> QueryResult {
+    match query {
+        QueryWithPermit::MyBid {} => {
+            if !permit.check_permission(&Permission::Owner) {
+                return Err(ContractError::NoBidPermission {
+                    permissions: permit.params.permissions.clone(),
+                }
+                .into());
+            }
+            try_query_my_bid(storage, api, account)
+        }
+    }
+}
+
+/// Returns QueryResult of the response sent when a viewing key is wrong or has not been set
+fn viewing_key_error() -> QueryResult {
+    to_binary(&QueryAnswer::ViewingKeyError {
+        msg: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
 
-    let sell_token_info = state.sell_contract.token_info_query(&deps.querier)?;
-    let bid_token_info = state.bid_contract.token_info_query(&deps.querier)?;
+/// Display the active bid of an address authenticated with its viewing key
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `bidder` - a reference to the address of the bidder
+/// * `key` - viewing key of the bidder
+pub(crate) fn try_query_my_bid_with_key<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    bidder: &HumanAddr,
+    key: String,
+) -> QueryResult {
+    if is_key_valid(storage, api, bidder, key)? {
+        try_query_my_bid(storage, api, bidder)
+    } else {
+        viewing_key_error()
+    }
+}
+
+/// Display the active bid of an already authenticated address, scored with its stored terms
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `bidder` - a reference to the address of the bidder
+fn try_query_my_bid<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    bidder: &HumanAddr,
+) -> QueryResult {
+    let state = load_state(storage)?;
+    let bidder_raw = api.canonical_address(bidder)?;
+
+    let bid = load_active_bid(storage, &state, &bidder_raw)?
+        .map(|found_bid| bid_info(&state, Some(bidder.clone()), found_bid));
+
+    to_binary(&QueryAnswer::MyBid { bid })
+}
+
+/// Display the score the active bid of an address authenticated with its viewing key receives
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `bidder` - a reference to the address of the bidder
+/// * `key` - viewing key of the bidder
+pub(crate) fn try_query_bid_score<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    bidder: &HumanAddr,
+    key: String,
+) -> QueryResult {
+    if !is_key_valid(storage, api, bidder, key)? {
+        return viewing_key_error();
+    }
+    let state = load_state(storage)?;
+    let bidder_raw = api.canonical_address(bidder)?;
 
-    let status = if state.is_completed {
+    let bid_score = load_active_bid(storage, &state, &bidder_raw)?
+        .map(|found_bid| Uint128(score_bid(&state, &found_bid)));
+
+    to_binary(&QueryAnswer::BidScore { bid_score })
+}
+
+/// Returns BidInfo displaying a bid
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `bidder` - Optional address of the bidder
+/// * `bid` - the bid to display
+fn bid_info(state: &State, bidder: Option<HumanAddr>, bid: Bid) -> BidInfo {
+    BidInfo {
+        bidder,
+        amount: Uint128(bid.amount),
+        timestamp: bid.timestamp,
+        bid_score: Uint128(score_bid(state, &bid)),
+        max_term: bid.max_term,
+        grace_period: bid.grace_period,
+        prepayment_penalty: bid.prepayment_penalty,
+        note: bid.note,
+    }
+}
+
+/// Display the number of active bids
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+pub(crate) fn try_query_bid_count<S: ReadonlyStorage>(storage: &S) -> QueryResult {
+    let state = load_state(storage)?;
+
+    to_binary(&QueryAnswer::BidCount {
+        count: state.bidders.len() as u32,
+    })
+}
+
+/// List a page of active bids, best scoring first
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `address` - address of the auction creator
+/// * `key` - viewing key of the auction creator
+/// * `page` - page to display, starting at 0
+/// * `page_size` - number of bids per page, capped at MAX_PAGE_SIZE
+pub(crate) fn try_list_bids<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    address: HumanAddr,
+    key: String,
+    page: u32,
+    page_size: u32,
+) -> QueryResult {
+    if !is_key_valid(storage, api, &address, key)? {
+        return viewing_key_error();
+    }
+    let state = load_state(storage)?;
+    if address != state.seller {
+        return Err(ContractError::NotSellerList.into());
+    }
+
+    let mut bid_list: Vec<(&Vec<u8>, Bid)> = Vec::new();
+    for bidder in &state.bidders {
+        let bid = load_bid(storage, bidder.as_slice())?;
+        if let Some(found_bid) = bid {
+            bid_list.push((bidder, found_bid));
+        }
+    }
+    // best scoring bid goes first, earliest bid wins a tie
+    bid_list.sort_by(|a, b| {
+        score_bid(&state, &b.1)
+            .cmp(&score_bid(&state, &a.1))
+            .then(a.1.timestamp.cmp(&b.1.timestamp))
+    });
+    let count = bid_list.len() as u32;
+
+    let mut bids = Vec::new();
+    let page_size = std::cmp::min(page_size, MAX_PAGE_SIZE) as usize;
+    let start = (page as usize).saturating_mul(page_size);
+    for (bidder, bid) in bid_list.into_iter().skip(start).take(page_size) {
+        // keep lenders private until the auction has closed
+        let bidder = if state.is_completed {
+            Some(api.human_address(&CanonicalAddr::from(bidder.as_slice()))?)
+        } else {
+            None
+        };
+        bids.push(bid_info(&state, bidder, bid));
+    }
+
+    to_binary(&QueryAnswer::ListBids {
+        bids,
+        count,
+        lender: state.lender.clone(),
+    })
+}
+
+/// View auction details
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `querier` - a reference to the Querier used to query the token contracts
+pub(crate) fn try_query_info<S: ReadonlyStorage, Q: Querier>(
+    storage: &S,
+    querier: &Q,
+) -> QueryResult {
+    let state = load_state(storage)?;
+
+    let sell_token_info = state.sell_contract.token_info_query(querier)?;
+    let bid_token_info = state.bid_contract.token_info_query(querier)?;
+
+    let status = if state.is_unwound {
+        String::from("Unwound")
+    } else if state.is_cancelled {
+        String::from("Cancelled")
+    } else if state.is_completed {
         let locked = if !state.bidders.is_empty() || state.currently_consigned > 0 {
             ", but found outstanding balances.  Please run either retract_bid to \
                 retrieve your non-winning bid, or return_all to return all outstanding bids/\
@@ -620,11 +1792,27 @@ fn try_query_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> Que
             ""
         };
         format!("Closed{}", locked)
+    } else if state.is_paused {
+        String::from("Paused")
     } else {
         let consign = if !state.tokens_consigned { " NOT" } else { "" };
+        let descending = if let AuctionKind::Dutch { .. } = state.kind {
+            " at a descending rate"
+        } else {
+            ""
+        };
+        let ends = state
+            .ends_at
+            .map(|end| {
+                format!(
+                    ". Bids accepted until {} UTC",
+                    NaiveDateTime::from_timestamp(end as i64, 0).format("%Y-%m-%d %H:%M:%S")
+                )
+            })
+            .unwrap_or_default();
         format!(
-            "Accepting bids: Token(s) to be sold have{} been consigned to the auction",
-            consign
+            "Accepting bids{}: Token(s) to be sold have{} been consigned to the auction{}",
+            descending, consign, ends
         )
     };
 
@@ -649,5 +1837,1036 @@ fn try_query_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> Que
         auction_address: state.auction_addr,
         status,
         winning_bid,
+        preferences: state.preferences,
+        kind: state.kind,
+        started_at: state.started_at,
+        ends_at: state.ends_at,
+        fee: state.fee,
+        oracle_contract: state.oracle_contract,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{auction_init_msg, contract_info, BID_TOKEN, ORACLE, REGISTRY, SELL_TOKEN};
+    use crate::testing::{mock_deps, open_auction_state, transfers, ContractsQuerier, Transfer};
+    use oracle::fixtures::sample_history;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::WasmMsg;
+
+    fn auction_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let state = open_auction_state("auction", "seller");
+        save_state(&mut deps.storage, &state).unwrap();
+        deps
+    }
+
+    fn bid<Q: Querier>(
+        deps: &mut Extern<MockStorage, MockApi, Q>,
+        lender: &str,
+        amount: u128,
+    ) -> HandleResponse {
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr(lender.to_string()),
+            from: HumanAddr(lender.to_string()),
+            amount: Uint128(amount),
+            msg: None,
+        };
+        handle(deps, mock_env("bidtoken", &[]), handle_msg).unwrap()
+    }
+
+    fn init_scenario() -> (InitResult, Extern<MockStorage, MockApi, ContractsQuerier>) {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        (init(&mut deps, mock_env("seller", &[]), auction_init_msg()), deps)
+    }
+
+    fn transfer(token: &str, recipient: &str, amount: u128) -> Transfer {
+        Transfer {
+            token: HumanAddr(token.to_string()),
+            recipient: HumanAddr(recipient.to_string()),
+            amount: Uint128(amount),
+        }
+    }
+
+    fn error_message(result: StdResult<HandleResponse>) -> String {
+        match result {
+            Err(StdError::GenericErr { msg, .. }) => msg,
+            _ => panic!("Expected an error"),
+        }
+    }
+
+    fn message_sizes(response: &HandleResponse) -> Vec<usize> {
+        response
+            .messages
+            .iter()
+            .map(|message| match message {
+                CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => msg.as_slice().len(),
+                _ => panic!("Unexpected message"),
+            })
+            .collect()
+    }
+
+    fn log_size(response: &HandleResponse) -> usize {
+        response
+            .log
+            .iter()
+            .map(|attribute| attribute.key.len() + attribute.value.len())
+            .sum()
+    }
+
+    fn response(response: &HandleResponse) -> &str {
+        let attribute = response.log.iter().find(|a| a.key == "response").unwrap();
+        attribute.value.trim_end()
+    }
+
+    #[test]
+    fn amount_buckets_have_the_same_length() {
+        assert_eq!(amount_bucket(0), "0e00");
+        assert_eq!(amount_bucket(7), "1e00");
+        assert_eq!(amount_bucket(1100000), "1e06");
+        assert_eq!(amount_bucket(u128::MAX), "1e38");
+    }
+
+    #[test]
+    fn events_log_the_action_and_status_but_never_a_bid_amount() {
+        let mut deps = auction_helper();
+        let attributes = |response: &HandleResponse| -> Vec<(String, String)> {
+            response
+                .log
+                .iter()
+                .filter(|attribute| attribute.key != "response")
+                .map(|attribute| (attribute.key.clone(), attribute.value.clone()))
+                .collect()
+        };
+        let expected = |attributes: &[(&str, &str)]| -> Vec<(String, String)> {
+            attributes.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+        };
+
+        let accepted = bid(&mut deps, "lender1", 1100000);
+        let refunded = bid(&mut deps, "lender2", 1250000);
+        for outcome in [&accepted, &refunded].iter() {
+            assert_eq!(
+                attributes(outcome),
+                expected(&[("action", "bid"), ("auction", "auction"), ("status", "open")])
+            );
+        }
+
+        // the winning bid is public once the auction is closed, so finalize logs its magnitude
+        let finalize = HandleMsg::Finalize { only_if_bids: true };
+        let finalized = handle(&mut deps, mock_env("seller", &[]), finalize).unwrap();
+        assert_eq!(
+            attributes(&finalized),
+            expected(&[
+                ("action", "finalize"),
+                ("auction", "auction"),
+                ("amount_bucket", "1e06"),
+                ("status", "closed"),
+            ])
+        );
+    }
+
+    #[test]
+    fn bid_outcomes_have_the_same_shape() {
+        let mut deps = auction_helper();
+
+        let accepted = bid(&mut deps, "lender1", 1100000);
+        assert!(response(&accepted).contains("Bid accepted"));
+        let replaced = bid(&mut deps, "lender1", 1050000);
+        assert!(response(&replaced).contains("Previously bid tokens have been returned"));
+        let rejected = bid(&mut deps, "lender1", 1150000);
+        assert!(response(&rejected).contains("does not score better"));
+        let refunded = bid(&mut deps, "lender2", 1250000);
+        assert!(response(&refunded).contains("greater than estimation"));
+
+        let outcomes = [&accepted, &replaced, &rejected, &refunded];
+        for outcome in outcomes.iter() {
+            assert_eq!(message_sizes(outcome), message_sizes(&accepted));
+            assert_eq!(log_size(outcome), log_size(&accepted));
+            assert_eq!(outcome.data, None);
+            // only the status and message are logged, never the amounts
+            assert!(!response(outcome).contains("amount"), "{}", response(outcome));
+        }
+        assert_eq!(message_sizes(&accepted).len(), 1);
+    }
+
+    #[test]
+    fn init_scores_oracle_history() {
+        let (init_result, deps) = init_scenario();
+        let response = init_result.unwrap();
+        // registers with both tokens and opens the seller's exposure with the oracle
+        assert_eq!(response.messages.len(), 3);
+        let state = load_state(&deps.storage).unwrap();
+        assert_eq!(state.score, 1666);
+        assert_eq!(state.average_bid, 1200000);
+
+        let query_result = query(&deps, QueryMsg::AuctionInfo {}).unwrap();
+        match from_binary(&query_result).unwrap() {
+            QueryAnswer::AuctionInfo {
+                sell_token,
+                bid_token,
+                score,
+                oracle_contract,
+                ..
+            } => {
+                assert_eq!(sell_token.token_info.symbol, "SELL");
+                assert_eq!(bid_token.token_info.symbol, "BID");
+                assert_eq!(score, Uint128(1666));
+                assert_eq!(oracle_contract, Some(contract_info(ORACLE)));
+            }
+            _ => panic!("Unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn outweighing_debts_score_nothing() {
+        let history = crate::msg::History {
+            debts: Some(Uint128(1000000)),
+            credits: vec![crate::msg::Credit {
+                sum: Uint128(1000000),
+                interest_rate: Uint128(10),
+                time: Uint128(12),
+                is_closed: false,
+            }],
+        };
+        // the score is negative, which used to wrap around to 3402823669209384634633746075098
+        assert_eq!(history.score(Uint128(1000000)), None);
+    }
+
+    #[test]
+    fn init_without_history_fails() {
+        let mut deps = mock_deps();
+        let init_result = init(&mut deps, mock_env("seller", &[]), auction_init_msg());
+        match init_result {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":105"#)),
+            _ => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    fn receive_msgs_dispatch_on_their_intent() {
+        let send = |from: &str, amount: u128, msg: Option<Binary>| HandleMsg::Receive {
+            sender: HumanAddr(from.to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg,
+        };
+        let encode = |msg: &ReceiveMsg| Some(to_binary(msg).unwrap());
+        let mut deps = auction_helper();
+        let mut state = load_state(&deps.storage).unwrap();
+        state.currently_consigned = 0;
+        state.tokens_consigned = false;
+        save_state(&mut deps.storage, &state).unwrap();
+
+        // payloads that are not a ReceiveMsg make the token contract revert the transfer
+        for payload in &[&b"bid"[..], &br#"{"borrow":{}}"#[..], &br#"{"repay":{}}"#[..]] {
+            let malformed = Some(Binary::from(payload.to_vec()));
+            let handle_result = handle(&mut deps, mock_env(BID_TOKEN, &[]), send("lender1", 1100000, malformed));
+            assert!(error_message(handle_result).contains(r#""code":201"#));
+        }
+        // an intent the sent token can not serve is refused
+        let consign = || encode(&ReceiveMsg::Consign {});
+        let handle_result = handle(&mut deps, mock_env(BID_TOKEN, &[]), send("seller", 1000, consign()));
+        assert!(error_message(handle_result).contains(r#""code":203"#));
+        assert!(load_state(&deps.storage).unwrap().bidders.is_empty());
+
+        handle(&mut deps, mock_env(SELL_TOKEN, &[]), send("seller", 1000, consign())).unwrap();
+        assert!(load_state(&deps.storage).unwrap().tokens_consigned);
+        let terms = ReceiveMsg::Bid {
+            max_term: Some(12),
+            grace_period: None,
+            prepayment_penalty: None,
+            note: Some("note".to_string()),
+        };
+        handle(&mut deps, mock_env(BID_TOKEN, &[]), send("lender1", 1100000, encode(&terms))).unwrap();
+        let lender = deps.api.canonical_address(&HumanAddr("lender1".to_string())).unwrap();
+        let stored = load_bid(&deps.storage, lender.as_slice()).unwrap().unwrap();
+        assert_eq!(stored.max_term, Some(12));
+        assert_eq!(stored.note, Some("note".to_string()));
+
+        // loans are not serviced by the auction, so repayments and collateral are refused
+        let loan_msgs = [ReceiveMsg::Repay { loan_id: 0 }, ReceiveMsg::DepositCollateral {}];
+        for loan_msg in loan_msgs.iter() {
+            let handle_result = handle(&mut deps, mock_env(BID_TOKEN, &[]), send("seller", 1000, encode(loan_msg)));
+            assert!(error_message(handle_result).contains(r#""code":600"#));
+            let handle_result = handle(&mut deps, mock_env(SELL_TOKEN, &[]), send("seller", 1000, encode(loan_msg)));
+            assert!(error_message(handle_result).contains(r#""code":203"#));
+        }
+        assert_eq!(load_state(&deps.storage).unwrap().bidders.len(), 1);
+    }
+
+    #[test]
+    fn consign_bid_finalize_return_all() {
+        let (init_result, mut deps) = init_scenario();
+        init_result.unwrap();
+
+        let consign_msg = HandleMsg::Receive {
+            sender: HumanAddr("seller".to_string()),
+            from: HumanAddr("seller".to_string()),
+            amount: Uint128(1666),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        handle(&mut deps, mock_env(SELL_TOKEN, &[]), consign_msg).unwrap();
+        assert!(load_state(&deps.storage).unwrap().tokens_consigned);
+
+        bid(&mut deps, "lender1", 1100000);
+        bid(&mut deps, "lender2", 1050000);
+        let query_result = query(&deps, QueryMsg::BidCount {}).unwrap();
+        match from_binary(&query_result).unwrap() {
+            QueryAnswer::BidCount { count } => assert_eq!(count, 2),
+            _ => panic!("Unexpected answer"),
+        }
+
+        let return_all = HandleMsg::ReturnAll {};
+        let handle_result = handle(&mut deps, mock_env("lender1", &[]), return_all);
+        assert!(error_message(handle_result).contains(r#""code":500"#));
+        let finalize = HandleMsg::Finalize { only_if_bids: true };
+        let handle_result = handle(&mut deps, mock_env("lender1", &[]), finalize);
+        assert!(error_message(handle_result).contains(r#""code":501"#));
+
+        let finalize = HandleMsg::Finalize { only_if_bids: true };
+        let response = handle(&mut deps, mock_env("seller", &[]), finalize).unwrap();
+        // the lowest bid wins, the other bid is returned and the seller's exposure is settled
+        assert_eq!(
+            transfers(&response.messages),
+            vec![
+                transfer(BID_TOKEN, "seller", 1050000),
+                transfer(SELL_TOKEN, "lender2", 1666),
+                transfer(BID_TOKEN, "lender1", 1100000),
+            ]
+        );
+        assert_eq!(response.messages.len(), 4);
+        let state = load_state(&deps.storage).unwrap();
+        assert!(state.is_completed);
+        assert!(state.bidders.is_empty());
+        assert_eq!(state.currently_consigned, 0);
+        assert_eq!(state.winning_bid, 1050000);
+
+        // nothing is left to return once the sale has been settled
+        let return_all = HandleMsg::ReturnAll {};
+        let response = handle(&mut deps, mock_env("lender1", &[]), return_all).unwrap();
+        assert!(response.messages.is_empty());
+        match from_binary(&response.data.unwrap()).unwrap() {
+            HandleAnswer::CloseAuction { message, .. } => {
+                assert_eq!(message, "Outstanding funds have been returned")
+            }
+            _ => panic!("Unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn my_bid_with_a_permit_needs_the_owner_permission() {
+        let mut deps = auction_helper();
+        bid(&mut deps, "lender1", 1100000);
+        let permit = |permission: &str| -> Permit {
+            let json = format!(
+                r#"{{"params":{{"allowed_tokens":["auction"],"permit_name":"bids","chain_id":"secret-4","permissions":["{}"]}},"signature":{{"pub_key":{{"type":"tendermint/PubKeySecp256k1","value":"AA=="}},"signature":"AA=="}}}}"#,
+                permission
+            );
+            from_binary(&Binary::from(json.into_bytes())).unwrap()
+        };
+        let lender = HumanAddr("lender1".to_string());
+
+        // the signature is checked before the query runs, which only looks at the permissions
+        let query_result = try_permit_query(&deps.storage, &deps.api, &permit("balance"), &lender, QueryWithPermit::MyBid {});
+        match query_result {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":900"#)),
+            _ => panic!("Expected an error"),
+        }
+        let query_result = try_permit_query(&deps.storage, &deps.api, &permit("owner"), &lender, QueryWithPermit::MyBid {});
+        match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::MyBid { bid: Some(info) } => assert_eq!(info.amount, Uint128(1100000)),
+            _ => panic!("Unexpected answer"),
+        }
+
+        // the ViewBid handle still answers for older clients
+        let viewed = handle(&mut deps, mock_env("lender1", &[]), HandleMsg::ViewBid {}).unwrap();
+        match from_binary(&viewed.data.unwrap()).unwrap() {
+            HandleAnswer::Bid { status, amount_bid, .. } => {
+                assert_eq!(status, Success);
+                assert_eq!(amount_bid, Some(Uint128(1100000)));
+            }
+            _ => panic!("Unexpected answer"),
+        }
+        let viewed = handle(&mut deps, mock_env("lender2", &[]), HandleMsg::ViewBid {}).unwrap();
+        match from_binary(&viewed.data.unwrap()).unwrap() {
+            HandleAnswer::Bid { status, amount_bid, .. } => {
+                assert_eq!(status, Failure);
+                assert_eq!(amount_bid, None);
+            }
+            _ => panic!("Unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn my_bid_scores_stored_terms() {
+        let mut deps = auction_helper();
+        let terms = ReceiveMsg::Bid {
+            max_term: Some(24),
+            grace_period: Some(3),
+            prepayment_penalty: None,
+            note: None,
+        };
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("lender1".to_string()),
+            from: HumanAddr("lender1".to_string()),
+            amount: Uint128(1100000),
+            msg: Some(to_binary(&terms).unwrap()),
+        };
+        handle(&mut deps, mock_env("bidtoken", &[]), handle_msg).unwrap();
+        let set_key = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        };
+        handle(&mut deps, mock_env("lender1", &[]), set_key).unwrap();
+
+        let my_bid = |key: &str| QueryMsg::MyBid {
+            address: HumanAddr("lender1".to_string()),
+            key: key.to_string(),
+        };
+        match from_binary(&query(&deps, my_bid("wrong")).unwrap()).unwrap() {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("Unexpected answer"),
+        }
+        let state = load_state(&deps.storage).unwrap();
+        let stored = Bid {
+            amount: 1100000,
+            timestamp: mock_env("bidtoken", &[]).block.time,
+            max_term: Some(24),
+            grace_period: 3,
+            prepayment_penalty: 0,
+            note: None,
+        };
+        match from_binary(&query(&deps, my_bid("key")).unwrap()).unwrap() {
+            QueryAnswer::MyBid { bid: Some(info) } => {
+                assert_eq!(info.bid_score, Uint128(score_bid(&state, &stored)));
+                assert_eq!(info.max_term, Some(24));
+                assert_eq!(info.grace_period, 3);
+            }
+            _ => panic!("Unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn preferences_rank_bids_on_every_term() {
+        let mut deps = auction_helper();
+        let mut state = load_state(&deps.storage).unwrap();
+        state.preferences = BidPreferences {
+            rate_weight: 1,
+            term_weight: 10000,
+            grace_period_weight: 20000,
+            prepayment_penalty_weight: 0,
+        };
+        save_state(&mut deps.storage, &state).unwrap();
+        let send = |lender: &str, amount: u128, grace_period: Option<u64>| HandleMsg::Receive {
+            sender: HumanAddr(lender.to_string()),
+            from: HumanAddr(lender.to_string()),
+            amount: Uint128(amount),
+            msg: Some(to_binary(&ReceiveMsg::Bid {
+                max_term: Some(12),
+                grace_period,
+                prepayment_penalty: None,
+                note: None,
+            }).unwrap()),
+        };
+        handle(&mut deps, mock_env(BID_TOKEN, &[]), send("lender1", 1050000, None)).unwrap();
+        handle(&mut deps, mock_env(BID_TOKEN, &[]), send("lender2", 1100000, Some(6))).unwrap();
+
+        // each bidder sees the score of their own bid, 150000 + 12 * 10000 and
+        // 100000 + 12 * 10000 + 6 * 20000
+        let bid_score = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, lender: &str| {
+            let set_key = HandleMsg::SetViewingKey { key: lender.to_string(), padding: None };
+            handle(deps, mock_env(lender, &[]), set_key).unwrap();
+            let query_msg = QueryMsg::BidScore {
+                address: HumanAddr(lender.to_string()),
+                key: lender.to_string(),
+            };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::BidScore { bid_score } => bid_score,
+                _ => panic!("Unexpected answer"),
+            }
+        };
+        assert_eq!(bid_score(&mut deps, "lender1"), Some(Uint128(270000)));
+        assert_eq!(bid_score(&mut deps, "lender2"), Some(Uint128(340000)));
+        assert_eq!(bid_score(&mut deps, "lender3"), None);
+        let wrong_key = QueryMsg::BidScore {
+            address: HumanAddr("lender1".to_string()),
+            key: "lender2".to_string(),
+        };
+        match from_binary(&query(&deps, wrong_key).unwrap()).unwrap() {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("Unexpected answer"),
+        }
+
+        // the higher bid wins on its grace period
+        let finalize = HandleMsg::Finalize { only_if_bids: true };
+        let response = handle(&mut deps, mock_env("seller", &[]), finalize).unwrap();
+        assert_eq!(
+            transfers(&response.messages),
+            vec![
+                transfer(BID_TOKEN, "seller", 1100000),
+                transfer(SELL_TOKEN, "lender2", 1000),
+                transfer(BID_TOKEN, "lender1", 1050000),
+            ]
+        );
+        assert_eq!(load_state(&deps.storage).unwrap().winning_bid, 1100000);
+    }
+
+    #[test]
+    fn dutch_rate_steps_down_to_its_floor() {
+        let kind = AuctionKind::Dutch {
+            step: Uint128(50000),
+            interval: 60,
+            floor: Uint128(1000000),
+        };
+        assert_eq!(kind.rate_at(1200000, 1000, 1000), Some(1200000));
+        assert_eq!(kind.rate_at(1200000, 1000, 1059), Some(1200000));
+        assert_eq!(kind.rate_at(1200000, 1000, 1060), Some(1150000));
+        assert_eq!(kind.rate_at(1200000, 1000, 1000 + 3 * 60), Some(1050000));
+        assert_eq!(kind.rate_at(1200000, 1000, 1000 + 4 * 60), Some(1000000));
+        assert_eq!(kind.rate_at(1200000, 1000, 1000 + 5 * 60), Some(1000000));
+        assert_eq!(kind.rate_at(1200000, 1000, u64::MAX), Some(1000000));
+        // a time before the start offers the starting rate
+        assert_eq!(kind.rate_at(1200000, 1000, 0), Some(1200000));
+        assert_eq!(AuctionKind::Reverse {}.rate_at(1200000, 1000, 2000), None);
+    }
+
+    #[test]
+    fn first_lender_to_accept_the_dutch_rate_wins() {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let mut init_msg = auction_init_msg();
+        init_msg.kind = Some(AuctionKind::Dutch {
+            step: Uint128(50000),
+            interval: 60,
+            floor: Uint128(1000000),
+        });
+        init(&mut deps, mock_env("seller", &[]), init_msg).unwrap();
+        let started_at = load_state(&deps.storage).unwrap().started_at;
+        let send = |from: &str, amount: u128| HandleMsg::Receive {
+            sender: HumanAddr(from.to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg: None,
+        };
+        let at = |sender: &str, time: u64| {
+            let mut env = mock_env(sender, &[]);
+            env.block.time = time;
+            env
+        };
+
+        // the rate is only accepted once the sale tokens are in escrow
+        let handle_result = handle(&mut deps, at(BID_TOKEN, started_at), send("lender1", 1200000));
+        assert!(error_message(handle_result).contains(r#""code":406"#));
+        handle(&mut deps, mock_env(SELL_TOKEN, &[]), send("seller", 1666)).unwrap();
+
+        // two intervals in, the rate has stepped down to 1100000
+        let now = started_at + 2 * 60;
+        let handle_result = handle(&mut deps, at(BID_TOKEN, now), send("lender1", 1050000));
+        assert!(error_message(handle_result).contains(r#""code":408"#));
+        let accepted = handle(&mut deps, at(BID_TOKEN, now), send("lender1", 1150000)).unwrap();
+        assert_eq!(
+            transfers(&accepted.messages),
+            vec![
+                transfer(BID_TOKEN, "seller", 1100000),
+                transfer(SELL_TOKEN, "lender1", 1666),
+                transfer(BID_TOKEN, "lender1", 50000),
+            ]
+        );
+        assert!(response(&accepted).contains("Excess tokens have been returned"));
+        let state = load_state(&deps.storage).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.winning_bid, 1100000);
+        assert_eq!(state.lender, Some(HumanAddr("lender1".to_string())));
+        assert_eq!(state.currently_consigned, 0);
+
+        // the auction finalized with the first acceptance, so later lenders are refused
+        let handle_result = handle(&mut deps, at(BID_TOKEN, now), send("lender2", 1200000));
+        assert!(error_message(handle_result).contains(r#""code":400"#));
+    }
+
+    #[test]
+    fn dutch_auction_rejects_zero_floor_terms_and_zero_bids() {
+        let dutch = |floor: u128| {
+            let mut init_msg = auction_init_msg();
+            init_msg.kind = Some(AuctionKind::Dutch {
+                step: Uint128(1000),
+                interval: 60,
+                floor: Uint128(floor),
+            });
+            init_msg
+        };
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        match init(&mut deps, mock_env("seller", &[]), dutch(0)) {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":103"#)),
+            _ => panic!("Expected an error"),
+        }
+        init(&mut deps, mock_env("seller", &[]), dutch(1000000)).unwrap();
+        let consign_msg = HandleMsg::Receive {
+            sender: HumanAddr("seller".to_string()),
+            from: HumanAddr("seller".to_string()),
+            amount: Uint128(1666),
+            msg: None,
+        };
+        handle(&mut deps, mock_env(SELL_TOKEN, &[]), consign_msg).unwrap();
+
+        let terms = ReceiveMsg::Bid {
+            max_term: Some(24),
+            grace_period: None,
+            prepayment_penalty: None,
+            note: None,
+        };
+        let bid_msg = |amount: u128, msg: Option<Binary>| HandleMsg::Receive {
+            sender: HumanAddr("lender1".to_string()),
+            from: HumanAddr("lender1".to_string()),
+            amount: Uint128(amount),
+            msg,
+        };
+        let with_terms = bid_msg(1200000, Some(to_binary(&terms).unwrap()));
+        let handle_result = handle(&mut deps, mock_env(BID_TOKEN, &[]), with_terms);
+        assert!(error_message(handle_result).contains(r#""code":409"#));
+        let zero = bid_msg(0, None);
+        let handle_result = handle(&mut deps, mock_env(BID_TOKEN, &[]), zero);
+        assert!(error_message(handle_result).contains(r#""code":401"#));
+        assert!(!load_state(&deps.storage).unwrap().is_completed);
+    }
+
+    #[test]
+    fn seller_lists_bids_best_first_without_lenders() {
+        let mut deps = auction_helper();
+        bid(&mut deps, "lender1", 1100000);
+        bid(&mut deps, "lender2", 1050000);
+        for address in &["seller", "lender1"] {
+            let set_key = HandleMsg::SetViewingKey {
+                key: format!("{} key", address),
+                padding: None,
+            };
+            handle(&mut deps, mock_env(*address, &[]), set_key).unwrap();
+        }
+        let list_bids = |address: &str, key: &str, page: u32| QueryMsg::ListBids {
+            address: HumanAddr(address.to_string()),
+            key: key.to_string(),
+            page: Some(page),
+            page_size: 1,
+        };
+
+        match from_binary(&query(&deps, list_bids("seller", "lender1 key", 0)).unwrap()).unwrap() {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("Unexpected answer"),
+        }
+        match query(&deps, list_bids("lender1", "lender1 key", 0)) {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":901"#)),
+            _ => panic!("Expected an error"),
+        }
+
+        // the lowest bid scores best, and neither lender is shown while the auction is open
+        let expected = [(0, 1050000), (1, 1100000)];
+        for (page, amount) in expected.iter() {
+            match from_binary(&query(&deps, list_bids("seller", "seller key", *page)).unwrap()).unwrap() {
+                QueryAnswer::ListBids { bids, count, lender } => {
+                    assert_eq!(count, 2);
+                    assert_eq!(bids.len(), 1);
+                    assert_eq!(bids[0].amount, Uint128(*amount));
+                    assert_eq!(bids[0].timestamp, mock_env("bidtoken", &[]).block.time);
+                    assert_eq!(bids[0].bidder, None);
+                    assert_eq!(lender, None);
+                }
+                _ => panic!("Unexpected answer"),
+            }
+        }
+
+        // once finalized, the winning lender is shown
+        let finalize = HandleMsg::Finalize { only_if_bids: true };
+        handle(&mut deps, mock_env("seller", &[]), finalize).unwrap();
+        match from_binary(&query(&deps, list_bids("seller", "seller key", 0)).unwrap()).unwrap() {
+            QueryAnswer::ListBids { count, lender, .. } => {
+                assert_eq!(count, 0);
+                assert_eq!(lender, Some(HumanAddr("lender2".to_string())));
+            }
+            _ => panic!("Unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn list_bids_caps_pages() {
+        let mut deps = auction_helper();
+        bid(&mut deps, "lender1", 1100000);
+        bid(&mut deps, "lender2", 1050000);
+        let set_key = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        };
+        handle(&mut deps, mock_env("seller", &[]), set_key).unwrap();
+
+        let list_bids = |page: u32| QueryMsg::ListBids {
+            address: HumanAddr("seller".to_string()),
+            key: "key".to_string(),
+            page: Some(page),
+            page_size: u32::MAX,
+        };
+        for (page, listed) in [(0, 2), (u32::MAX, 0)].iter() {
+            match from_binary(&query(&deps, list_bids(*page)).unwrap()).unwrap() {
+                QueryAnswer::ListBids { bids, count, .. } => {
+                    assert_eq!(bids.len(), *listed);
+                    assert_eq!(count, 2);
+                }
+                _ => panic!("Unexpected answer"),
+            }
+        }
+    }
+
+    #[test]
+    fn fees_are_taken_from_their_source_at_settlement() {
+        let fee = |source: FeeSource| FeeConfig {
+            bps: 250,
+            treasury: HumanAddr("treasury".to_string()),
+            source,
+        };
+        let settlements = vec![
+            (
+                FeeSource::WinningBid,
+                27500,
+                vec![
+                    transfer(BID_TOKEN, "seller", 1072500),
+                    transfer(SELL_TOKEN, "lender1", 1666),
+                    transfer(BID_TOKEN, "treasury", 27500),
+                ],
+            ),
+            (
+                FeeSource::Consignment,
+                41,
+                vec![
+                    transfer(BID_TOKEN, "seller", 1100000),
+                    transfer(SELL_TOKEN, "lender1", 1625),
+                    transfer(SELL_TOKEN, "treasury", 41),
+                ],
+            ),
+        ];
+        for (source, taken, expected) in settlements {
+            let mut deps = mock_deps();
+            deps.querier.add_history("seller", sample_history());
+            let init_msg = InitMsg {
+                fee: Some(fee(source)),
+                ..auction_init_msg()
+            };
+            init(&mut deps, mock_env("seller", &[]), init_msg).unwrap();
+            match from_binary(&query(&deps, QueryMsg::AuctionInfo {}).unwrap()).unwrap() {
+                QueryAnswer::AuctionInfo { fee: shown, .. } => assert_eq!(shown, Some(fee(source))),
+                _ => panic!("Unexpected answer"),
+            }
+            let consign_msg = HandleMsg::Receive {
+                sender: HumanAddr("seller".to_string()),
+                from: HumanAddr("seller".to_string()),
+                amount: Uint128(1666),
+                msg: None,
+            };
+            handle(&mut deps, mock_env(SELL_TOKEN, &[]), consign_msg).unwrap();
+            bid(&mut deps, "lender1", 1100000);
+
+            let finalize = HandleMsg::Finalize { only_if_bids: true };
+            let settled = handle(&mut deps, mock_env("seller", &[]), finalize).unwrap();
+            assert_eq!(transfers(&settled.messages), expected);
+            match from_binary(&settled.data.unwrap()).unwrap() {
+                HandleAnswer::CloseAuction { winning_bid, fee, .. } => {
+                    assert_eq!(winning_bid, Some(Uint128(1100000)));
+                    assert_eq!(fee, Some(Uint128(taken)));
+                }
+                _ => panic!("Unexpected answer"),
+            }
+        }
+    }
+
+    #[test]
+    fn set_fee_only_before_bids() {
+        let mut deps = auction_helper();
+        let mut state = load_state(&deps.storage).unwrap();
+        state.admin = Some(HumanAddr("admin".to_string()));
+        save_state(&mut deps.storage, &state).unwrap();
+        let set_fee = |bps: u16| HandleMsg::SetFee {
+            fee: Some(FeeConfig {
+                bps,
+                treasury: HumanAddr("treasury".to_string()),
+                source: FeeSource::WinningBid,
+            }),
+        };
+
+        let handle_result = handle(&mut deps, mock_env("seller", &[]), set_fee(100));
+        assert!(error_message(handle_result).contains(r#""code":700"#));
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), set_fee(10_001));
+        assert!(error_message(handle_result).contains(r#""code":107"#));
+        handle(&mut deps, mock_env("admin", &[]), set_fee(100)).unwrap();
+        bid(&mut deps, "lender1", 1100000);
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), set_fee(100));
+        assert!(error_message(handle_result).contains(r#""code":702"#));
+    }
+
+    #[test]
+    fn seller_amends_or_cancels_before_the_first_bid() {
+        let (init_result, mut deps) = init_scenario();
+        init_result.unwrap();
+        let send = |token: &str, from: &str, amount: u128, msg: &ReceiveMsg| {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr(from.to_string()),
+                from: HumanAddr(from.to_string()),
+                amount: Uint128(amount),
+                msg: Some(to_binary(msg).unwrap()),
+            };
+            (mock_env(token, &[]), handle_msg)
+        };
+        let (env, consign) = send(SELL_TOKEN, "seller", 1000, &ReceiveMsg::Consign {});
+        handle(&mut deps, env, consign).unwrap();
+
+        let ends_at = mock_env("seller", &[]).block.time + 1000;
+        let update = HandleMsg::UpdateAuction {
+            description: Some("amended".to_string()),
+            max_bid: Some(Uint128(1100000)),
+            ends_at: Some(ends_at),
+        };
+        handle(&mut deps, mock_env("seller", &[]), update).unwrap();
+        let state = load_state(&deps.storage).unwrap();
+        assert_eq!(state.description, Some("amended".to_string()));
+        assert_eq!(state.average_bid, 1100000);
+        assert_eq!(state.ends_at, Some(ends_at));
+
+        // bids are held to the amended parameters, and refused bids do not count as bids
+        let refunded = bid(&mut deps, "lender1", 1150000);
+        assert!(response(&refunded).contains("greater than estimation"));
+        let (mut env, late_bid) = send(BID_TOKEN, "lender1", 1050000, &ReceiveMsg::Bid {
+            max_term: None,
+            grace_period: None,
+            prepayment_penalty: None,
+            note: None,
+        });
+        env.block.time = ends_at;
+        let handle_result = handle(&mut deps, env, late_bid);
+        assert!(error_message(handle_result).contains(r#""code":400"#));
+
+        // cancelling returns the consignment and settles the exposure
+        let cancelled = handle(&mut deps, mock_env("seller", &[]), HandleMsg::CancelAuction {}).unwrap();
+        assert_eq!(transfers(&cancelled.messages), vec![transfer(SELL_TOKEN, "seller", 1000)]);
+        assert_eq!(cancelled.messages.len(), 2);
+        match from_binary(&cancelled.data.unwrap()).unwrap() {
+            HandleAnswer::CancelAuction { amount_returned, .. } => {
+                assert_eq!(amount_returned, Some(Uint128(1000)));
+            }
+            _ => panic!("Unexpected answer"),
+        }
+        match from_binary(&query(&deps, QueryMsg::AuctionInfo {}).unwrap()).unwrap() {
+            QueryAnswer::AuctionInfo { status, description, .. } => {
+                assert_eq!(status, "Cancelled");
+                assert_eq!(description, Some("amended".to_string()));
+            }
+            _ => panic!("Unexpected answer"),
+        }
+        let handle_result = handle(&mut deps, mock_env("seller", &[]), HandleMsg::CancelAuction {});
+        assert!(error_message(handle_result).contains(r#""code":806"#));
+    }
+
+    #[test]
+    fn update_and_cancel_refusals_are_coded() {
+        let (init_result, mut deps) = init_scenario();
+        init_result.unwrap();
+        let update = |max_bid: u128, ends_at: Option<u64>| HandleMsg::UpdateAuction {
+            description: None,
+            max_bid: Some(Uint128(max_bid)),
+            ends_at,
+        };
+        let refusals = vec![
+            ("lender1", update(1200000, None), 800),
+            ("seller", update(0, None), 803),
+            ("seller", update(1200000, Some(0)), 804),
+            ("lender1", HandleMsg::CancelAuction {}, 805),
+        ];
+        for (sender, handle_msg, code) in refusals {
+            let handle_result = handle(&mut deps, mock_env(sender, &[]), handle_msg);
+            assert!(error_message(handle_result).contains(&format!(r#""code":{}"#, code)));
+        }
+
+        bid(&mut deps, "lender1", 1100000);
+        let handle_result = handle(&mut deps, mock_env("seller", &[]), update(1200000, None));
+        assert!(error_message(handle_result).contains(r#""code":802"#));
+        let handle_result = handle(&mut deps, mock_env("seller", &[]), HandleMsg::CancelAuction {});
+        assert!(error_message(handle_result).contains(r#""code":807"#));
+    }
+
+    #[test]
+    fn standalone_init_takes_fee() {
+        let fee = |bps: u16| FeeConfig {
+            bps,
+            treasury: HumanAddr("treasury".to_string()),
+            source: FeeSource::WinningBid,
+        };
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let init_msg = InitMsg {
+            fee: Some(fee(10_001)),
+            ..auction_init_msg()
+        };
+        match init(&mut deps, mock_env("seller", &[]), init_msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":107"#)),
+            _ => panic!("Expected an invalid fee error"),
+        }
+        let init_msg = InitMsg {
+            fee: Some(fee(100)),
+            ..auction_init_msg()
+        };
+        init(&mut deps, mock_env("seller", &[]), init_msg).unwrap();
+        assert_eq!(load_state(&deps.storage).unwrap().fee, Some(fee(100)));
+    }
+
+    #[test]
+    fn init_sets_registry_key() {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let mut init_msg = auction_init_msg();
+        init_msg.lender_registry = Some(contract_info(REGISTRY));
+        let response = init(&mut deps, mock_env("seller", &[]), init_msg).unwrap();
+
+        // the auction sets the key it checks approvals with at the registry
+        let key: String = load(&deps.storage, REGISTRY_KEY_KEY).unwrap();
+        assert_eq!(response.messages.len(), 4);
+        match &response.messages[3] {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr, &HumanAddr(REGISTRY.to_string()));
+                match from_binary(msg).unwrap() {
+                    RegistryHandleMsg::SetViewingKey { key: set, .. } => assert_eq!(set, key),
+                }
+            }
+            _ => panic!("Unexpected message"),
+        }
+    }
+
+    #[test]
+    fn bids_are_refunded_with_a_code_until_the_registry_approves() {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let init_msg = InitMsg {
+            lender_registry: Some(contract_info(REGISTRY)),
+            ..auction_init_msg()
+        };
+        init(&mut deps, mock_env("seller", &[]), init_msg).unwrap();
+        let auction = load_state(&deps.storage).unwrap().auction_addr;
+        let key: String = load(&deps.storage, REGISTRY_KEY_KEY).unwrap();
+        let set_key = registry::msg::HandleMsg::SetViewingKey { key, padding: None };
+        deps.querier.handle_registry(auction.as_str(), set_key);
+        let approve = registry::msg::HandleMsg::Approve {
+            lender: HumanAddr("lender1".to_string()),
+            expires_at: None,
+        };
+        deps.querier.handle_registry("admin", approve);
+
+        // the registry refuses the auction until its admin adds it as a viewer
+        let refunded = bid(&mut deps, "lender1", 1100000);
+        assert_eq!(transfers(&refunded.messages), vec![transfer(BID_TOKEN, "lender1", 1100000)]);
+        assert!(response(&refunded).contains(r#""code":411"#));
+        let add_viewers = registry::msg::HandleMsg::AddViewers {
+            viewers: vec![auction],
+        };
+        deps.querier.handle_registry("admin", add_viewers);
+
+        let refunded = bid(&mut deps, "lender2", 1100000);
+        assert_eq!(transfers(&refunded.messages), vec![transfer(BID_TOKEN, "lender2", 1100000)]);
+        assert!(response(&refunded).contains(r#""code":410"#));
+        let placed = bid(&mut deps, "lender1", 1100000);
+        // an accepted bid still sends the bidder a transfer, of nothing
+        assert_eq!(transfers(&placed.messages), vec![transfer(BID_TOKEN, "lender1", 0)]);
+        assert!(response(&placed).contains(r#""status":"Success""#));
+        assert_eq!(load_state(&deps.storage).unwrap().bidders.len(), 1);
+    }
+
+    #[test]
+    fn standalone_admin_can_pause_and_unwind() {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let init_msg = |admin: &str| InitMsg {
+            admin: Some(HumanAddr(admin.to_string())),
+            ..auction_init_msg()
+        };
+        match init(&mut deps, mock_env("seller", &[]), init_msg("seller")) {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":108"#)),
+            _ => panic!("Expected the seller to be refused as admin"),
+        }
+        // any other address can be the admin, which grants it nothing at the oracle
+        init(&mut deps, mock_env("seller", &[]), init_msg("admin")).unwrap();
+        let consign = HandleMsg::Receive {
+            sender: HumanAddr("seller".to_string()),
+            from: HumanAddr("seller".to_string()),
+            amount: Uint128(1000),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        handle(&mut deps, mock_env(SELL_TOKEN, &[]), consign).unwrap();
+        bid(&mut deps, "lender1", 1100000);
+
+        // the admin pauses the auction, which refuses bids and finalizing until it is unwound
+        let handle_result = handle(&mut deps, mock_env("seller", &[]), HandleMsg::Pause {});
+        assert!(error_message(handle_result).contains(r#""code":700"#));
+        handle(&mut deps, mock_env("admin", &[]), HandleMsg::Pause {}).unwrap();
+        let paused_bid = HandleMsg::Receive {
+            sender: HumanAddr("lender2".to_string()),
+            from: HumanAddr("lender2".to_string()),
+            amount: Uint128(1050000),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env(BID_TOKEN, &[]), paused_bid);
+        assert!(error_message(handle_result).contains(r#""code":202"#));
+        let finalize = HandleMsg::Finalize { only_if_bids: false };
+        let handle_result = handle(&mut deps, mock_env("seller", &[]), finalize);
+        assert!(error_message(handle_result).contains(r#""code":503"#));
+        let status = |deps: &Extern<MockStorage, MockApi, ContractsQuerier>| {
+            match from_binary(&query(deps, QueryMsg::AuctionInfo {}).unwrap()).unwrap() {
+                QueryAnswer::AuctionInfo { status, .. } => status,
+                _ => panic!("Unexpected answer"),
+            }
+        };
+        assert_eq!(status(&deps), "Paused");
+
+        let unwound = handle(&mut deps, mock_env("admin", &[]), HandleMsg::EmergencyUnwind {});
+        let unwound = unwound.unwrap();
+        assert_eq!(
+            transfers(&unwound.messages),
+            vec![
+                transfer(BID_TOKEN, "lender1", 1100000),
+                transfer(SELL_TOKEN, "seller", 1000),
+            ]
+        );
+        let state = load_state(&deps.storage).unwrap();
+        assert!(state.is_unwound && state.is_completed && !state.is_paused);
+        assert_eq!(status(&deps), "Unwound");
+    }
+
+    #[test]
+    fn admin_can_not_unwind_a_settled_loan() {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let init_msg = InitMsg {
+            admin: Some(HumanAddr("admin".to_string())),
+            ..auction_init_msg()
+        };
+        init(&mut deps, mock_env("seller", &[]), init_msg).unwrap();
+        let consign = HandleMsg::Receive {
+            sender: HumanAddr("seller".to_string()),
+            from: HumanAddr("seller".to_string()),
+            amount: Uint128(1000),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        handle(&mut deps, mock_env(SELL_TOKEN, &[]), consign).unwrap();
+        bid(&mut deps, "lender1", 1100000);
+        let finalize = HandleMsg::Finalize { only_if_bids: true };
+        handle(&mut deps, mock_env("seller", &[]), finalize).unwrap();
+
+        // the lender's loan stays with the auction, which reports it closed in both places
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), HandleMsg::EmergencyUnwind {});
+        assert!(error_message(handle_result).contains(r#""code":703"#));
+        let state = load_state(&deps.storage).unwrap();
+        assert!(state.is_completed && !state.is_unwound);
+        assert_eq!(event_status(&state), "closed");
+        match from_binary(&query(&deps, QueryMsg::AuctionInfo {}).unwrap()).unwrap() {
+            QueryAnswer::AuctionInfo { status, .. } => assert!(status.starts_with("Closed")),
+            _ => panic!("Unexpected answer"),
+        }
+    }
+}
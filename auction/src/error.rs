@@ -0,0 +1,281 @@
+use serde::Serialize;
+use snafu::Snafu;
+
+use cosmwasm_std::{HumanAddr, StdError};
+use secret_toolkit::permit::Permission;
+
+/// errors of the auction contract.  Each error has a stable code, so clients can branch on the
+/// code instead of the message
+#[derive(Snafu, Debug)]
+#[snafu(visibility = "pub")]
+pub enum ContractError {
+    /// init: the same token was given for sale and for bids
+    #[snafu(display("Sell contract and bid contract must be different"))]
+    SameTokens,
+    /// init: the payment does not cover the credit
+    #[snafu(display("You can't expect to pay less than sum of credit"))]
+    PaymentTooLow,
+    /// init: every bid preference weight is 0
+    #[snafu(display("At least one bid preference weight must be greater than 0"))]
+    NoPreferences,
+    /// init: the Dutch auction rate schedule never descends, or descends to 0
+    #[snafu(display("Dutch auction rate step, interval and floor must be greater than 0"))]
+    InvalidDutchSchedule,
+    /// init: the Dutch auction rate floor is above the starting rate
+    #[snafu(display("Dutch auction rate floor can not be greater than the average bid"))]
+    DutchFloorTooHigh,
+    /// init: the oracle has no credit history for the seller
+    #[snafu(display("You have no credit history to calculate score"))]
+    NoCreditHistory,
+    /// init: the seller's credit history scores 0
+    #[snafu(display("You have too bad score"))]
+    ScoreTooLow,
+    /// init: the platform fee is greater than the maximum.  Also returned when the admin sets it
+    #[snafu(display("Fee can not be greater than {} basis points", max))]
+    InvalidFee { max: u16 },
+    /// init: the seller named themselves the admin of their own auction
+    #[snafu(display("The seller can not be the admin of the auction"))]
+    SellerAdmin,
+    /// receive: the tokens were not sent by a token contract of the auction
+    #[snafu(display("Address: {} is not a token in this auction", address))]
+    NotAuctionToken { address: HumanAddr },
+    /// receive: the msg sent with the tokens is not a ReceiveMsg
+    #[snafu(display("Invalid receive msg: {}", reason))]
+    InvalidReceiveMsg { reason: String },
+    /// receive: the auction is paused
+    #[snafu(display("Auction is paused. Your tokens have been returned"))]
+    Paused,
+    /// receive: the token sent can not be used for the action the msg states
+    #[snafu(display("Tokens of contract: {} can not be used for this action", address))]
+    WrongToken { address: HumanAddr },
+    /// consign: the tokens were not sent by the seller
+    #[snafu(display(
+        "Only auction creator can consign tokens for sale. Your tokens have been returned"
+    ))]
+    NotSellerConsign,
+    /// consign: the auction is closed
+    #[snafu(display("Auction has ended. Your tokens have been returned"))]
+    ConsignAfterEnd,
+    /// consign: the full sale amount is already in escrow
+    #[snafu(display(
+        "Tokens to be sold have already been consigned. Your tokens have been returned"
+    ))]
+    AlreadyConsigned,
+    /// bid: the auction is closed or past its end time
+    #[snafu(display("Auction has ended. Bid tokens have been returned"))]
+    BidAfterEnd,
+    /// bid: no tokens were bid
+    #[snafu(display("Bid must be greater than 0"))]
+    ZeroBid,
+    /// bid: the maximum loan term offered is 0
+    #[snafu(display("Maximum loan term must be greater than 0"))]
+    ZeroTerm,
+    /// bid: the prepayment penalty asked is above the maximum
+    #[snafu(display("Prepayment penalty can not be greater than {} basis points", max))]
+    PenaltyTooHigh { max: u64 },
+    /// bid: the note is longer than the maximum
+    #[snafu(display("Bid note can not be longer than {} bytes", max))]
+    NoteTooLong { max: usize },
+    /// bid: the bid is a round power of ten
+    #[snafu(display("You can't expect getting lesser or equal to what you propose"))]
+    PerfectProposal,
+    /// bid: a Dutch auction rate was accepted before the sale tokens were consigned
+    #[snafu(display(
+        "Tokens to be sold have not been consigned yet. Bid tokens have been returned"
+    ))]
+    NotConsigned,
+    /// bid: the Dutch auction has no rate at this time
+    #[snafu(display("Auction does not offer a current rate. Bid tokens have been returned"))]
+    NoCurrentRate,
+    /// bid: less than the current Dutch auction rate was sent
+    #[snafu(display(
+        "Bid must be at least the current rate of {}. Bid tokens have been returned",
+        rate
+    ))]
+    BelowCurrentRate { rate: u128 },
+    /// bid: loan terms were sent to a Dutch auction, which only accepts its current rate
+    #[snafu(display(
+        "Dutch auctions only accept the current rate without loan terms. Bid tokens have been \
+         returned"
+    ))]
+    DutchBidTerms,
+    /// bid: the lender registry does not approve the lender.  The bid is refunded
+    #[snafu(display("Lender is not approved to bid in this auction. Bid tokens have been returned"))]
+    NotApprovedLender,
+    /// bid: the lender registry did not let the auction check approvals, because the auction
+    /// has not been added as a viewer.  The bid is refunded
+    #[snafu(display(
+        "Lender registry did not let the auction check approvals: {}. Bid tokens have been \
+         returned",
+        reason
+    ))]
+    RegistryRefused { reason: String },
+    /// bid: the auction has no viewing key to check approvals with.  The bid is refunded
+    #[snafu(display(
+        "Auction has no viewing key with the lender registry. Bid tokens have been returned"
+    ))]
+    NoRegistryKey,
+    /// bid: the lender registry could not be queried.  The bid is refunded
+    #[snafu(display(
+        "Lender registry could not be queried: {}. Bid tokens have been returned",
+        reason
+    ))]
+    RegistryUnavailable { reason: String },
+    /// finalize: return_all was called on an open auction
+    #[snafu(display("return_all can only be executed after the auction has ended"))]
+    ReturnAllBeforeEnd,
+    /// finalize: the auction was not finalized by the seller
+    #[snafu(display("Only auction creator can finalize the sale"))]
+    NotSellerFinalize,
+    /// finalize: the seller only wanted to close the auction if there are bids
+    #[snafu(display("Did not close because there are no active bids"))]
+    NoActiveBids,
+    /// finalize: the auction is paused until the admin unpauses or unwinds it
+    #[snafu(display("Auction is paused. It can not be finalized until it is unpaused"))]
+    FinalizePaused,
+    /// receive: the tokens were sent to repay a loan or secure it, which the auction does not
+    /// service
+    #[snafu(display(
+        "Loans are not serviced by this auction. Your tokens have been returned"
+    ))]
+    NoLoanServicing,
+    /// admin: the admin command was not sent by the admin
+    #[snafu(display(
+        "This is an admin command. Admin commands can only be run from admin address"
+    ))]
+    NotAdmin,
+    /// admin: the auction is closed, so it can not be paused, unpaused or have its fee set
+    #[snafu(display("Auction has ended"))]
+    AdminAfterEnd,
+    /// admin: the fee was set after the first bid
+    #[snafu(display("Fee can not be set after bids have been placed"))]
+    FeeAfterBids,
+    /// admin: the auction settled to a winning lender, so it can not be unwound
+    #[snafu(display("Auction has settled to a winning lender and can not be unwound"))]
+    UnwindSettled,
+    /// update: the auction was not updated by the seller
+    #[snafu(display("Only auction creator can update the auction"))]
+    NotSellerUpdate,
+    /// update: the auction is closed
+    #[snafu(display("Auction has ended"))]
+    UpdateAfterEnd,
+    /// update: the auction was updated after the first bid
+    #[snafu(display("Auction can not be updated after bids have been placed"))]
+    UpdateAfterBids,
+    /// update: the new average bid is 0
+    #[snafu(display("Average bid must be greater than 0"))]
+    ZeroAverageBid,
+    /// update: the new end time is not in the future
+    #[snafu(display("Auction end must be in the future"))]
+    EndInPast,
+    /// cancel: the auction was not cancelled by the seller
+    #[snafu(display("Only auction creator can cancel the auction"))]
+    NotSellerCancel,
+    /// cancel: the auction is closed
+    #[snafu(display("Auction has ended"))]
+    CancelAfterEnd,
+    /// cancel: the auction was cancelled after the first bid
+    #[snafu(display("Auction can not be cancelled after bids have been placed"))]
+    CancelAfterBids,
+    /// query: the permit does not grant the owner permission needed to view a bid
+    #[snafu(display("No permission to query bid, got permissions {:?}", permissions))]
+    NoBidPermission { permissions: Vec<Permission> },
+    /// query: the bids were not listed by the seller
+    #[snafu(display("Only auction creator can list the bids"))]
+    NotSellerList,
+}
+
+impl ContractError {
+    /// Returns the stable code of the error
+    pub fn code(&self) -> u16 {
+        match self {
+            ContractError::SameTokens => 100,
+            ContractError::PaymentTooLow => 101,
+            ContractError::NoPreferences => 102,
+            ContractError::InvalidDutchSchedule => 103,
+            ContractError::DutchFloorTooHigh => 104,
+            ContractError::NoCreditHistory => 105,
+            ContractError::ScoreTooLow => 106,
+            ContractError::InvalidFee { .. } => 107,
+            ContractError::SellerAdmin => 108,
+            ContractError::NotAuctionToken { .. } => 200,
+            ContractError::InvalidReceiveMsg { .. } => 201,
+            ContractError::Paused => 202,
+            ContractError::WrongToken { .. } => 203,
+            ContractError::NotSellerConsign => 300,
+            ContractError::ConsignAfterEnd => 301,
+            ContractError::AlreadyConsigned => 302,
+            ContractError::BidAfterEnd => 400,
+            ContractError::ZeroBid => 401,
+            ContractError::ZeroTerm => 402,
+            ContractError::PenaltyTooHigh { .. } => 403,
+            ContractError::NoteTooLong { .. } => 404,
+            ContractError::PerfectProposal => 405,
+            ContractError::NotConsigned => 406,
+            ContractError::NoCurrentRate => 407,
+            ContractError::BelowCurrentRate { .. } => 408,
+            ContractError::DutchBidTerms => 409,
+            ContractError::NotApprovedLender => 410,
+            ContractError::RegistryRefused { .. } => 411,
+            ContractError::NoRegistryKey => 412,
+            ContractError::RegistryUnavailable { .. } => 413,
+            ContractError::ReturnAllBeforeEnd => 500,
+            ContractError::NotSellerFinalize => 501,
+            ContractError::NoActiveBids => 502,
+            ContractError::FinalizePaused => 503,
+            ContractError::NoLoanServicing => 600,
+            ContractError::NotAdmin => 700,
+            ContractError::AdminAfterEnd => 701,
+            ContractError::FeeAfterBids => 702,
+            ContractError::UnwindSettled => 703,
+            ContractError::NotSellerUpdate => 800,
+            ContractError::UpdateAfterEnd => 801,
+            ContractError::UpdateAfterBids => 802,
+            ContractError::ZeroAverageBid => 803,
+            ContractError::EndInPast => 804,
+            ContractError::NotSellerCancel => 805,
+            ContractError::CancelAfterEnd => 806,
+            ContractError::CancelAfterBids => 807,
+            ContractError::NoBidPermission { .. } => 900,
+            ContractError::NotSellerList => 901,
+        }
+    }
+}
+
+/// machine-readable form of a contract error
+#[derive(Serialize)]
+struct ErrorResponse {
+    /// stable code of the error
+    code: u16,
+    /// description of the error
+    message: String,
+}
+
+impl From<ContractError> for StdError {
+    /// Returns a generic error whose message is the JSON `{"code": .., "message": ..}` of the
+    /// contract error
+    fn from(err: ContractError) -> Self {
+        let response = ErrorResponse {
+            code: err.code(),
+            message: err.to_string(),
+        };
+        StdError::generic_err(serde_json::to_string(&response).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_code_and_message() {
+        let err: StdError = ContractError::PenaltyTooHigh { max: 10000 }.into();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(
+                msg,
+                r#"{"code":403,"message":"Prepayment penalty can not be greater than 10000 basis points"}"#
+            ),
+            _ => panic!("Unexpected error"),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Fixtures shared by the tests of the auction and the simulator.  The credit history they
+//! score is `oracle::fixtures::sample_history`
+
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
+
+use crate::msg::{ContractInfo, InitMsg};
+
+/// address of the oracle stand-in
+pub const ORACLE: &str = "oracle";
+/// address of the sale token stand-in
+pub const SELL_TOKEN: &str = "selltoken";
+/// address of the bid token stand-in
+pub const BID_TOKEN: &str = "bidtoken";
+/// address of the lender registry stand-in
+pub const REGISTRY: &str = "registry";
+
+/// Returns the code hash and address of a stand-in contract, whose code hash is its address
+/// followed by "hash"
+///
+/// # Arguments
+///
+/// * `address` - address of the contract
+pub fn contract_info(address: &str) -> ContractInfo {
+    ContractInfo {
+        code_hash: format!("{}hash", address),
+        address: HumanAddr(address.to_string()),
+    }
+}
+
+/// Returns the InitMsg of an auction of the stand-in tokens expecting 1000000 for a payment of
+/// 1200000, for which the sample history scores 1666
+pub fn auction_init_msg() -> InitMsg {
+    InitMsg {
+        sell_contract: contract_info(SELL_TOKEN),
+        bid_contract: contract_info(BID_TOKEN),
+        expected: Uint128(1000000),
+        payment: Uint128(1200000),
+        oracle_contract: contract_info(ORACLE),
+        description: None,
+        preferences: None,
+        kind: None,
+        prng_seed: Binary::from(b"seed".to_vec()),
+        admin: None,
+        fee: None,
+        lender_registry: None,
+    }
+}
@@ -1,8 +1,15 @@
 pub mod contract;
+pub mod error;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod marketplace;
 pub mod msg;
 pub mod state;
+#[cfg(test)]
+mod testing;
+pub mod viewing_key;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", not(feature = "marketplace")))]
 mod wasm {
     use super::contract;
     use cosmwasm_std::{
@@ -38,3 +45,37 @@ mod wasm {
     // Other C externs like cosmwasm_vm_version_1, allocate, deallocate are available
     // automatically because we `use cosmwasm_std`.
 }
+
+#[cfg(all(target_arch = "wasm32", feature = "marketplace"))]
+mod wasm {
+    use super::marketplace;
+    use cosmwasm_std::{
+        do_handle, do_init, do_query, ExternalApi, ExternalQuerier, ExternalStorage,
+    };
+
+    #[no_mangle]
+    extern "C" fn init(env_ptr: u32, msg_ptr: u32) -> u32 {
+        do_init(
+            &marketplace::init::<ExternalStorage, ExternalApi, ExternalQuerier>,
+            env_ptr,
+            msg_ptr,
+        )
+    }
+
+    #[no_mangle]
+    extern "C" fn handle(env_ptr: u32, msg_ptr: u32) -> u32 {
+        do_handle(
+            &marketplace::handle::<ExternalStorage, ExternalApi, ExternalQuerier>,
+            env_ptr,
+            msg_ptr,
+        )
+    }
+
+    #[no_mangle]
+    extern "C" fn query(msg_ptr: u32) -> u32 {
+        do_query(
+            &marketplace::query::<ExternalStorage, ExternalApi, ExternalQuerier>,
+            msg_ptr,
+        )
+    }
+}
@@ -0,0 +1,685 @@
+use cosmwasm_std::{
+    from_binary, to_binary, Api, Binary, Env, Extern, HandleResponse, HandleResult, HumanAddr,
+    InitResponse, InitResult, Querier, QuerierResult, QueryResult, ReadonlyStorage, StdError,
+    StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::permit::{validate, Permit};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result};
+
+use crate::contract::{self, create_auction, registry_key_msg, HostConfig};
+use crate::error::ContractError;
+use crate::msg::{
+    AuctionKind, BidPreferences, HandleMsg, InitMsg, MarketHandleAnswer, MarketHandleMsg,
+    MarketInitMsg, MarketQueryAnswer, MarketQueryMsg, MarketReceiveMsg, QueryWithPermit,
+    BLOCK_SIZE, MARKET_KEY, PREFIX_AUCTIONS, PREFIX_REVOKED_PERMITS, PRNG_SEED_KEY,
+    REGISTRY_KEY_KEY,
+};
+use crate::state::{load, load_state, may_load, save, MarketState};
+use crate::viewing_key::{sha_256, ViewingKey};
+
+/// querier handed to the auctions hosted by the marketplace
+pub struct QuerierRef<'a, Q: Querier>(&'a Q);
+
+impl<'a, Q: Querier> Querier for QuerierRef<'a, Q> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        self.0.raw_query(bin_request)
+    }
+}
+
+/// Returns the storage namespace of a hosted auction
+///
+/// # Arguments
+///
+/// * `auction_id` - id of the auction
+fn auction_namespace(auction_id: u64) -> Vec<u8> {
+    [PREFIX_AUCTIONS, &auction_id.to_be_bytes()].concat()
+}
+
+/// Returns the dependencies a hosted auction runs with.  The auction handlers see only the
+/// auction's own storage, so they work unchanged
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `auction_id` - id of the auction
+fn auction_deps<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    auction_id: u64,
+) -> Extern<PrefixedStorage<S>, A, QuerierRef<Q>> {
+    Extern {
+        storage: PrefixedStorage::new(&auction_namespace(auction_id), &mut deps.storage),
+        api: deps.api,
+        querier: QuerierRef(&deps.querier),
+    }
+}
+
+/// Returns StdResult<ReadonlyPrefixedStorage> of the storage a hosted auction answers queries
+/// with, which is an error if the marketplace has no auction with the given id
+///
+/// # Arguments
+///
+/// * `storage` - reference to the marketplace's storage
+/// * `auction_id` - id of the auction
+fn auction_view<S: ReadonlyStorage>(
+    storage: &S,
+    auction_id: u64,
+) -> StdResult<ReadonlyPrefixedStorage<S>> {
+    check_auction_id(storage, auction_id)?;
+    Ok(ReadonlyPrefixedStorage::new(
+        &auction_namespace(auction_id),
+        storage,
+    ))
+}
+
+/// Returns StdResult<()> which is an error if the marketplace has no auction with the given id
+///
+/// # Arguments
+///
+/// * `storage` - reference to the marketplace's storage
+/// * `auction_id` - id of the auction
+fn check_auction_id<S: ReadonlyStorage>(storage: &S, auction_id: u64) -> StdResult<()> {
+    let market: MarketState = load(storage, MARKET_KEY)?;
+    if auction_id >= market.auction_count {
+        return Err(StdError::generic_err(format!(
+            "There is no auction with id {}",
+            auction_id
+        )));
+    }
+    Ok(())
+}
+
+////////////////////////////////////// Init ///////////////////////////////////////
+/// Initializes the marketplace and registers Receive function with sell and bid token
+/// contracts, once for all the auctions it will host
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - MarketInitMsg passed in with the instantiation message
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: MarketInitMsg,
+) -> InitResult {
+    if msg.sell_contract.address == msg.bid_contract.address {
+        return Err(ContractError::SameTokens.into());
+    }
+    if let Some(fee) = &msg.fee {
+        fee.validate()?;
+    }
+
+    let market = MarketState {
+        sell_contract: msg.sell_contract,
+        bid_contract: msg.bid_contract,
+        oracle_contract: msg.oracle_contract,
+        admin: msg.admin,
+        fee: msg.fee,
+        lender_registry: msg.lender_registry,
+        auction_count: 0,
+    };
+    save(&mut deps.storage, MARKET_KEY, &market)?;
+    let prng_seed = sha_256(&msg.prng_seed.0);
+    save(&mut deps.storage, PRNG_SEED_KEY, &prng_seed.to_vec())?;
+
+    let mut messages = vec![
+        market
+            .sell_contract
+            .register_receive_msg(env.contract_code_hash.clone())?,
+        market
+            .bid_contract
+            .register_receive_msg(env.contract_code_hash.clone())?,
+    ];
+    // every hosted auction checks approvals with the marketplace's viewing key
+    if let Some(registry) = &market.lender_registry {
+        let key = ViewingKey::new(&env, &prng_seed, b"registry").0;
+        messages.push(registry_key_msg(registry, key.clone())?);
+        save(&mut deps.storage, REGISTRY_KEY_KEY, &key)?;
+    }
+
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
+}
+
+///////////////////////////////////// Handle //////////////////////////////////////
+/// Returns HandleResult
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - MarketHandleMsg passed in with the execute message
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: MarketHandleMsg,
+) -> HandleResult {
+    let response = match msg {
+        MarketHandleMsg::CreateAuction {
+            expected,
+            payment,
+            description,
+            preferences,
+            kind,
+            entropy,
+        } => try_create_auction(
+            deps,
+            env,
+            expected,
+            payment,
+            description,
+            preferences,
+            kind,
+            entropy,
+        ),
+        MarketHandleMsg::Receive {
+            sender,
+            from,
+            amount,
+            msg,
+        } => try_receive(deps, env, sender, from, amount, msg),
+        MarketHandleMsg::Finalize {
+            auction_id,
+            only_if_bids,
+        } => try_handle_auction(deps, env, auction_id, HandleMsg::Finalize { only_if_bids }),
+        MarketHandleMsg::ReturnAll { auction_id } => {
+            try_handle_auction(deps, env, auction_id, HandleMsg::ReturnAll {})
+        }
+        MarketHandleMsg::CancelAuction { auction_id } => {
+            try_handle_auction(deps, env, auction_id, HandleMsg::CancelAuction {})
+        }
+        MarketHandleMsg::UpdateAuction {
+            auction_id,
+            description,
+            max_bid,
+            ends_at,
+        } => try_handle_auction(
+            deps,
+            env,
+            auction_id,
+            HandleMsg::UpdateAuction {
+                description,
+                max_bid,
+                ends_at,
+            },
+        ),
+        MarketHandleMsg::SetFee { auction_id, fee } => {
+            try_handle_auction(deps, env, auction_id, HandleMsg::SetFee { fee })
+        }
+        MarketHandleMsg::Pause { auction_id } => {
+            try_handle_auction(deps, env, auction_id, HandleMsg::Pause {})
+        }
+        MarketHandleMsg::Unpause { auction_id } => {
+            try_handle_auction(deps, env, auction_id, HandleMsg::Unpause {})
+        }
+        MarketHandleMsg::EmergencyUnwind { auction_id } => {
+            try_handle_auction(deps, env, auction_id, HandleMsg::EmergencyUnwind {})
+        }
+        MarketHandleMsg::CreateViewingKey {
+            auction_id,
+            entropy,
+            padding,
+        } => try_handle_auction(
+            deps,
+            env,
+            auction_id,
+            HandleMsg::CreateViewingKey { entropy, padding },
+        ),
+        MarketHandleMsg::SetViewingKey {
+            auction_id,
+            key,
+            padding,
+        } => try_handle_auction(
+            deps,
+            env,
+            auction_id,
+            HandleMsg::SetViewingKey { key, padding },
+        ),
+        // revocations are kept with the marketplace, where permit queries look for them
+        MarketHandleMsg::RevokePermit { permit_name, .. } => {
+            contract::try_revoke_permit(deps, env, permit_name)
+        }
+    };
+    pad_handle_result(response, BLOCK_SIZE)
+}
+
+/// Create an auction selling the calling address' credit score
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `expected` - expected sum of credit
+/// * `payment` - alleged sum to pay
+/// * `description` - Optional description of the auction
+/// * `preferences` - Optional weights used to rank bids
+/// * `kind` - Optional kind of auction
+/// * `entropy` - entropy mixed into the seed the auction generates viewing keys with
+#[allow(clippy::too_many_arguments)]
+fn try_create_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    expected: Uint128,
+    payment: Uint128,
+    description: Option<String>,
+    preferences: Option<BidPreferences>,
+    kind: Option<AuctionKind>,
+    entropy: String,
+) -> HandleResult {
+    let mut market: MarketState = load(&deps.storage, MARKET_KEY)?;
+    let auction_id = market.auction_count;
+    // every hosted auction gets its own seed, which only the marketplace's secret seed makes
+    // unpredictable
+    let market_seed: Vec<u8> = load(&deps.storage, PRNG_SEED_KEY)?;
+    let prng_seed = [
+        market_seed.as_slice(),
+        &auction_id.to_be_bytes(),
+        env.message.sender.0.as_bytes(),
+        entropy.as_bytes(),
+    ]
+    .concat();
+
+    let init_msg = InitMsg {
+        sell_contract: market.sell_contract.clone(),
+        bid_contract: market.bid_contract.clone(),
+        expected,
+        payment,
+        oracle_contract: market.oracle_contract.clone(),
+        description,
+        preferences,
+        kind,
+        prng_seed: Binary(prng_seed),
+        admin: None,
+        fee: None,
+        lender_registry: market.lender_registry.clone(),
+    };
+    let host = HostConfig {
+        auction_id,
+        admin: market.admin.clone(),
+        fee: market.fee.clone(),
+        registry_key: may_load(&deps.storage, REGISTRY_KEY_KEY)?,
+    };
+    let response = create_auction(
+        &mut auction_deps(deps, auction_id),
+        env,
+        init_msg,
+        Some(host),
+    )?;
+
+    market.auction_count += 1;
+    save(&mut deps.storage, MARKET_KEY, &market)?;
+
+    Ok(HandleResponse {
+        messages: response.messages,
+        log: response.log,
+        data: Some(to_binary(&MarketHandleAnswer::CreateAuction { auction_id })?),
+    })
+}
+
+/// Route tokens sent to the marketplace to the auction named in the Send's msg
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `sender` - address of person or contract that sent the tokens
+/// * `from` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `msg` - base64 encoded MarketReceiveMsg naming the auction
+fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    sender: HumanAddr,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Binary,
+) -> HandleResult {
+    let receive_msg: MarketReceiveMsg =
+        from_binary(&msg).map_err(|e| ContractError::InvalidReceiveMsg {
+            reason: e.to_string(),
+        })?;
+    let msg = receive_msg
+        .action
+        .map(|action| to_binary(&action))
+        .transpose()?;
+
+    try_handle_auction(
+        deps,
+        env,
+        receive_msg.auction_id,
+        HandleMsg::Receive {
+            sender,
+            from,
+            amount,
+            msg,
+        },
+    )
+}
+
+/// Handle a message as the hosted auction with the given id
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `auction_id` - id of the auction
+/// * `msg` - HandleMsg for the auction
+fn try_handle_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    auction_id: u64,
+    msg: HandleMsg,
+) -> HandleResult {
+    check_auction_id(&deps.storage, auction_id)?;
+    contract::handle(&mut auction_deps(deps, auction_id), env, msg)
+}
+
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns QueryResult
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `msg` - MarketQueryMsg passed in with the query call
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: MarketQueryMsg,
+) -> QueryResult {
+    let response = match msg {
+        MarketQueryMsg::AuctionInfo { auction_id } => auction_view(&deps.storage, auction_id)
+            .and_then(|view| contract::try_query_info(&view, &deps.querier)),
+        MarketQueryMsg::BidCount { auction_id } => auction_view(&deps.storage, auction_id)
+            .and_then(|view| contract::try_query_bid_count(&view)),
+        MarketQueryMsg::ListBids {
+            auction_id,
+            address,
+            key,
+            page,
+            page_size,
+        } => auction_view(&deps.storage, auction_id).and_then(|view| {
+            contract::try_list_bids(
+                &view,
+                &deps.api,
+                address,
+                key,
+                page.unwrap_or(0),
+                page_size,
+            )
+        }),
+        MarketQueryMsg::MyBid {
+            auction_id,
+            address,
+            key,
+        } => auction_view(&deps.storage, auction_id).and_then(|view| {
+            contract::try_query_my_bid_with_key(&view, &deps.api, &address, key)
+        }),
+        MarketQueryMsg::BidScore {
+            auction_id,
+            address,
+            key,
+        } => auction_view(&deps.storage, auction_id).and_then(|view| {
+            contract::try_query_bid_score(&view, &deps.api, &address, key)
+        }),
+        MarketQueryMsg::WithPermit {
+            auction_id,
+            permit,
+            query,
+        } => try_permit_query(deps, auction_id, permit, query),
+        MarketQueryMsg::AuctionCount {} => try_query_auction_count(deps),
+    };
+    pad_query_result(response, BLOCK_SIZE)
+}
+
+/// Returns QueryResult from validating a permit for the marketplace and then running the query
+/// it authenticates on the hosted auction with the given id
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `auction_id` - id of the auction
+/// * `permit` - permit signed by the querier
+/// * `query` - query to run on behalf of the permit's signer
+fn try_permit_query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    auction_id: u64,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> QueryResult {
+    let view = auction_view(&deps.storage, auction_id)?;
+    let state = load_state(&view)?;
+    // hosted auctions live at the marketplace's address, and RevokePermit keeps the
+    // revocations in the marketplace's own storage
+    let account = validate(deps, PREFIX_REVOKED_PERMITS, &permit, state.auction_addr)?;
+
+    contract::try_permit_query(&view, &deps.api, &permit, &account, query)
+}
+
+/// Returns QueryResult displaying the number of auctions the marketplace has created
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn try_query_auction_count<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> QueryResult {
+    let market: MarketState = load(&deps.storage, MARKET_KEY)?;
+
+    to_binary(&MarketQueryAnswer::AuctionCount {
+        count: market.auction_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{FeeConfig, FeeSource, QueryAnswer, ReceiveMsg};
+    use crate::state::{load_state, save_state, State};
+    use crate::fixtures::{contract_info, BID_TOKEN, ORACLE, SELL_TOKEN};
+    use crate::testing::{mock_deps, open_auction_state};
+    use oracle::fixtures::sample_history;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+
+    /// marketplace hosting two open auctions, bypassing the oracle that creating them queries
+    fn market_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = MarketInitMsg {
+            sell_contract: contract_info(SELL_TOKEN),
+            bid_contract: contract_info(BID_TOKEN),
+            oracle_contract: contract_info(ORACLE),
+            prng_seed: Binary::from(b"seed".to_vec()),
+            admin: None,
+            fee: None,
+            lender_registry: None,
+        };
+        init(&mut deps, mock_env("market", &[]), init_msg).unwrap();
+
+        for auction_id in 0..2 {
+            let state = State {
+                admin: Some(HumanAddr("admin".to_string())),
+                ..open_auction_state("market", &format!("seller{}", auction_id))
+            };
+            save_state(&mut auction_deps(&mut deps, auction_id).storage, &state).unwrap();
+        }
+        let mut market: MarketState = load(&deps.storage, MARKET_KEY).unwrap();
+        market.auction_count = 2;
+        save(&mut deps.storage, MARKET_KEY, &market).unwrap();
+        deps
+    }
+
+    fn bid_count(deps: &Extern<MockStorage, MockApi, MockQuerier>, auction_id: u64) -> u32 {
+        let query_result = query(deps, MarketQueryMsg::BidCount { auction_id }).unwrap();
+        let answer: QueryAnswer = from_binary(&query_result).unwrap();
+        match answer {
+            QueryAnswer::BidCount { count } => count,
+            _ => panic!("Unexpected query answer"),
+        }
+    }
+
+    #[test]
+    fn send_payload_routes_to_auction() {
+        let mut deps = market_helper();
+
+        let receive_msg = MarketReceiveMsg {
+            auction_id: 1,
+            action: Some(ReceiveMsg::Bid {
+                max_term: None,
+                grace_period: None,
+                prepayment_penalty: None,
+                note: None,
+            }),
+        };
+        let handle_msg = MarketHandleMsg::Receive {
+            sender: HumanAddr("lender".to_string()),
+            from: HumanAddr("lender".to_string()),
+            amount: Uint128(1100000),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env("bidtoken", &[]), handle_msg).unwrap();
+        assert_eq!(bid_count(&deps, 0), 0);
+        assert_eq!(bid_count(&deps, 1), 1);
+
+        // an auction the marketplace has not created can not receive tokens
+        let receive_msg = MarketReceiveMsg {
+            auction_id: 2,
+            action: None,
+        };
+        let handle_msg = MarketHandleMsg::Receive {
+            sender: HumanAddr("lender".to_string()),
+            from: HumanAddr("lender".to_string()),
+            amount: Uint128(1100000),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        assert!(handle(&mut deps, mock_env("bidtoken", &[]), handle_msg).is_err());
+        assert!(query(&deps, MarketQueryMsg::AuctionInfo { auction_id: 2 }).is_err());
+
+        let query_result = query(&deps, MarketQueryMsg::AuctionCount {}).unwrap();
+        let answer: MarketQueryAnswer = from_binary(&query_result).unwrap();
+        let MarketQueryAnswer::AuctionCount { count } = answer;
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn hosted_auctions_offer_keys_and_admin_handles() {
+        let mut deps = market_helper();
+
+        let receive_msg = MarketReceiveMsg {
+            auction_id: 1,
+            action: None,
+        };
+        let handle_msg = MarketHandleMsg::Receive {
+            sender: HumanAddr("lender".to_string()),
+            from: HumanAddr("lender".to_string()),
+            amount: Uint128(1100000),
+            msg: to_binary(&receive_msg).unwrap(),
+        };
+        handle(&mut deps, mock_env("bidtoken", &[]), handle_msg).unwrap();
+
+        for (who, auction_id) in &[("lender", 1), ("seller1", 1)] {
+            let handle_msg = MarketHandleMsg::SetViewingKey {
+                auction_id: *auction_id,
+                key: "key".to_string(),
+                padding: None,
+            };
+            handle(&mut deps, mock_env(*who, &[]), handle_msg).unwrap();
+        }
+        let my_bid = |deps: &Extern<MockStorage, MockApi, MockQuerier>, auction_id: u64| {
+            let query_msg = MarketQueryMsg::MyBid {
+                auction_id,
+                address: HumanAddr("lender".to_string()),
+                key: "key".to_string(),
+            };
+            from_binary::<QueryAnswer>(&query(deps, query_msg).unwrap()).unwrap()
+        };
+        match my_bid(&deps, 1) {
+            QueryAnswer::MyBid { bid: Some(bid) } => assert_eq!(bid.amount, Uint128(1100000)),
+            _ => panic!("Expected the lender's bid"),
+        }
+        // every auction keeps its own viewing keys
+        match my_bid(&deps, 0) {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("Expected a viewing key error"),
+        }
+        let query_msg = MarketQueryMsg::ListBids {
+            auction_id: 1,
+            address: HumanAddr("seller1".to_string()),
+            key: "key".to_string(),
+            page: None,
+            page_size: 10,
+        };
+        match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::ListBids { bids, count, .. } => {
+                assert_eq!(count, 1);
+                assert_eq!(bids[0].bidder, None);
+            }
+            _ => panic!("Expected a list of bids"),
+        }
+
+        // only the marketplace admin can pause, and only the named auction pauses
+        let pause = MarketHandleMsg::Pause { auction_id: 0 };
+        assert!(handle(&mut deps, mock_env("lender", &[]), pause).is_err());
+        let pause = MarketHandleMsg::Pause { auction_id: 0 };
+        handle(&mut deps, mock_env("admin", &[]), pause).unwrap();
+        assert!(load_state(&auction_deps(&mut deps, 0).storage).unwrap().is_paused);
+        assert!(!load_state(&auction_deps(&mut deps, 1).storage).unwrap().is_paused);
+        let unpause = MarketHandleMsg::Unpause { auction_id: 0 };
+        handle(&mut deps, mock_env("admin", &[]), unpause).unwrap();
+        assert!(!load_state(&auction_deps(&mut deps, 0).storage).unwrap().is_paused);
+
+        let update = MarketHandleMsg::UpdateAuction {
+            auction_id: 0,
+            description: Some("updated".to_string()),
+            max_bid: None,
+            ends_at: None,
+        };
+        handle(&mut deps, mock_env("seller0", &[]), update).unwrap();
+        let state = load_state(&auction_deps(&mut deps, 0).storage).unwrap();
+        assert_eq!(state.description, Some("updated".to_string()));
+    }
+
+    #[test]
+    fn hosted_auctions_take_admin_and_fee_from_market() {
+        let mut deps = mock_deps();
+        deps.querier.add_history("seller", sample_history());
+        let fee = FeeConfig {
+            bps: 100,
+            treasury: HumanAddr("treasury".to_string()),
+            source: FeeSource::WinningBid,
+        };
+        let init_msg = |fee: FeeConfig| MarketInitMsg {
+            sell_contract: contract_info(SELL_TOKEN),
+            bid_contract: contract_info(BID_TOKEN),
+            oracle_contract: contract_info(ORACLE),
+            prng_seed: Binary::from(b"seed".to_vec()),
+            admin: Some(HumanAddr("admin".to_string())),
+            fee: Some(fee),
+            lender_registry: None,
+        };
+        let invalid = FeeConfig { bps: 10_001, ..fee.clone() };
+        match init(&mut deps, mock_env("deployer", &[]), init_msg(invalid)) {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains(r#""code":107"#), "{}", msg),
+            _ => panic!("Expected an invalid fee error"),
+        }
+        init(&mut deps, mock_env("deployer", &[]), init_msg(fee.clone())).unwrap();
+
+        let create = MarketHandleMsg::CreateAuction {
+            expected: Uint128(1000000),
+            payment: Uint128(1200000),
+            description: None,
+            preferences: None,
+            kind: None,
+            entropy: "entropy".to_string(),
+        };
+        let response = handle(&mut deps, mock_env("seller", &[]), create).unwrap();
+        // events of hosted auctions name the auction with its id in the marketplace
+        let auction = response.log.iter().find(|attribute| attribute.key == "auction").unwrap();
+        assert_eq!(auction.value, "cosmos2contract/0");
+        let state = load_state(&auction_deps(&mut deps, 0).storage).unwrap();
+        assert_eq!(state.seller, HumanAddr("seller".to_string()));
+        assert_eq!(state.admin, Some(HumanAddr("admin".to_string())));
+        assert_eq!(state.fee, Some(fee));
+    }
+}
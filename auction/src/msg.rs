@@ -1,17 +1,63 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CosmosMsg, HumanAddr, Querier, StdResult, Uint128};
+use cosmwasm_std::{Binary, CosmosMsg, HumanAddr, Querier, StdResult, Uint128};
 
 use secret_toolkit::snip20::{register_receive_msg, token_info_query, transfer_msg, TokenInfo};
-use secret_toolkit::utils::Query;
+use secret_toolkit::permit::Permit;
+use secret_toolkit::utils::{HandleCallback, Query};
+
+use scoring::ScoredCredit;
+
+use crate::error::ContractError;
+use crate::viewing_key::ViewingKey;
 
 /// storage key for auction state
 pub const CONFIG_KEY: &[u8] = b"config";
 
+/// storage key for the version of the stored auction state layout
+pub const VERSION_KEY: &[u8] = b"version";
+
+/// storage key for the id of an auction hosted by a marketplace
+pub const AUCTION_ID_KEY: &[u8] = b"auctionid";
+
+/// storage key for marketplace state
+pub const MARKET_KEY: &[u8] = b"market";
+
+/// storage prefix for the auctions hosted by a marketplace
+pub const PREFIX_AUCTIONS: &[u8] = b"auctions";
+
+/// storage key for the prng seed
+pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
+
+/// storage key for the viewing key the lender registry is queried with
+pub const REGISTRY_KEY_KEY: &[u8] = b"registrykey";
+
+/// storage prefix for hashed viewing keys
+pub const PREFIX_VIEW_KEY: &[u8] = b"viewingkey";
+
+/// storage prefix for revoked query permits
+pub const PREFIX_REVOKED_PERMITS: &str = "revokedpermits";
+
 /// block size
 pub const BLOCK_SIZE: usize = 256;
 
+/// block size of the responses returned in logs, large enough that every outcome of an action
+/// pads to the same size
+pub const RESPONSE_BLOCK_SIZE: usize = 512;
+
+/// maximum number of bids listed on one page
+pub const MAX_PAGE_SIZE: u32 = 30;
+
+/// maximum length of a note attached to a bid
+pub const MAX_NOTE_LEN: usize = 256;
+
+/// maximum prepayment penalty in basis points
+pub const MAX_PREPAYMENT_PENALTY: u64 = 10_000;
+
+/// maximum platform fee in basis points
+pub const MAX_FEE_BPS: u16 = 10_000;
+
 /// Instantiation message
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InitMsg {
@@ -28,16 +74,38 @@ pub struct InitMsg {
     /// Optional description of the auction
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// Optional weights used to rank bids.  Defaults to ranking by bid amount only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<BidPreferences>,
+    /// Optional kind of auction.  Defaults to a reverse auction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<AuctionKind>,
+    /// secret seed used to generate viewing keys
+    pub prng_seed: Binary,
+    /// Optional address allowed to pause, unwind and set the fee of the auction.  It can not be
+    /// the seller.  Auctions hosted by a marketplace take the marketplace's admin instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin: Option<HumanAddr>,
+    /// Optional platform fee taken at settlement.  Auctions hosted by a marketplace take the
+    /// marketplace's fee instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeConfig>,
+    /// Optional code hash and address of the registry of lenders allowed to bid.  Anyone can bid
+    /// if not given.  The registry's admin must add the auction with AddViewers before it can
+    /// check approvals.  Bids are refunded until then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lender_registry: Option<ContractInfo>,
 }
 
 /// Handle messages
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    /// Receive gets called by the token contracts of the auction.  If it came from the sale token, it
-    /// will consign the sent tokens.  If it came from the bid token, it will place a bid.  If any
-    /// other address tries to call this, it will give an error message that the calling address is
-    /// not a token in the auction.
+    /// Receive gets called by the token contracts of the auction.  If the Send carried a `msg`, it
+    /// is decoded as a ReceiveMsg and dispatched on the intent it states.  Without a `msg`, tokens
+    /// from the sale token will be consigned and tokens from the bid token will be placed as a bid.
+    /// If any other address tries to call this, it will give an error message that the calling
+    /// address is not a token in the auction.
     Receive {
         /// address of person or contract that sent the tokens that triggered this Receive
         sender: HumanAddr,
@@ -45,9 +113,13 @@ pub enum HandleMsg {
         from: HumanAddr,
         /// amount of tokens sent
         amount: Uint128,
+        /// Optional base64 encoded ReceiveMsg
+        #[serde(skip_serializing_if = "Option::is_none")]
+        msg: Option<Binary>,
     },
 
-    /// ViewBid will display the active bid made by the calling address
+    /// ViewBid will display the active bid made by the calling address.  Kept for backwards
+    /// compatibility, the MyBid query displays the same bid without the cost of a transaction
     ViewBid {},
 
     /// Finalize will close the auction
@@ -59,6 +131,97 @@ pub enum HandleMsg {
     /// those funds to their owners.  Should never be needed, but included in case of unforeseen
     /// error
     ReturnAll {},
+    /// UpdateAuction will amend the auction parameters.  Only the auction creator can update the
+    /// auction, and only before the first bid has been placed
+    UpdateAuction {
+        /// Optional new description of the auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Optional new average bid that will be accepted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bid: Option<Uint128>,
+        /// Optional new time after which bids are no longer accepted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ends_at: Option<u64>,
+    },
+    /// CancelAuction will close the auction and return the consigned tokens.  Only the auction
+    /// creator can cancel the auction, and only before the first bid has been placed
+    CancelAuction {},
+    /// SetFee will set the platform fee taken at settlement.  Only the admin can set the fee, and
+    /// only before the first bid has been placed
+    SetFee {
+        /// Optional new fee configuration, None removes the fee
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee: Option<FeeConfig>,
+    },
+    /// Pause will stop the auction from accepting new bids and consignments, and from being
+    /// finalized.  Admin only
+    Pause {},
+    /// Unpause will let the auction accept new bids and consignments again.  Admin only
+    Unpause {},
+    /// EmergencyUnwind will close the auction without a winner and return every bid and the
+    /// consigned tokens.  It is refused once the auction has settled to a winner.  Admin only
+    EmergencyUnwind {},
+    /// Migrate will upgrade state saved by an older version of the contract to the current
+    /// layout.  Any later handle does the same, so this only lets the upgrade be run (and
+    /// paid for) up front
+    Migrate {},
+    /// CreateViewingKey will generate a viewing key for the calling address
+    CreateViewingKey {
+        /// entropy used to generate the key
+        entropy: String,
+        /// Optional padding to hide the length of the entropy
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+    /// SetViewingKey will set the viewing key of the calling address
+    SetViewingKey {
+        /// the viewing key
+        key: String,
+        /// Optional padding to hide the length of the key
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+    /// RevokePermit will revoke a query permit of the calling address
+    RevokePermit {
+        /// name of the permit to revoke
+        permit_name: String,
+        /// Optional padding to hide the length of the name
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+}
+
+/// Messages that can be carried in the `msg` field of a SNIP-20 Send to the auction
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// place a bid with the sent bid tokens
+    Bid {
+        /// Optional longest loan term (in months) the lender is willing to offer
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_term: Option<u64>,
+        /// Optional grace period (in months) before the first repayment is due
+        #[serde(skip_serializing_if = "Option::is_none")]
+        grace_period: Option<u64>,
+        /// Optional penalty (in basis points) for repaying the loan early
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prepayment_penalty: Option<u64>,
+        /// Optional note to the borrower
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    },
+    /// consign the sent sale tokens
+    Consign {},
+    /// repay the loan that resulted from the auction with the sent bid tokens.  Loans are not
+    /// serviced by the auction yet, so the tokens are refused
+    Repay {
+        /// id of the loan being repaid
+        loan_id: u64,
+    },
+    /// deposit the sent bid tokens as collateral for the loan.  Loans are not serviced by the
+    /// auction yet, so the tokens are refused
+    DepositCollateral {},
 }
 
 /// Responses from handle functions
@@ -96,6 +259,9 @@ pub enum HandleAnswer {
         /// Optional amount of tokens returned from escrow
         #[serde(skip_serializing_if = "Option::is_none")]
         amount_returned: Option<Uint128>,
+        /// Optional score of the bid according to the borrower's preferences
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_score: Option<Uint128>,
     },
     /// response from closing the auction
     CloseAuction {
@@ -109,6 +275,34 @@ pub enum HandleAnswer {
         /// Optional amount of tokens returned form escrow
         #[serde(skip_serializing_if = "Option::is_none")]
         amount_returned: Option<Uint128>,
+        /// Optional platform fee taken from the swap
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee: Option<Uint128>,
+    },
+    /// response from cancelling the auction
+    CancelAuction {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// Optional amount of consigned tokens returned from escrow
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+    },
+    /// response from creating a viewing key
+    CreateViewingKey {
+        /// the generated viewing key
+        key: ViewingKey,
+    },
+    /// response from setting a viewing key
+    SetViewingKey {
+        /// success or failure
+        status: ResponseStatus,
+    },
+    /// response from revoking a permit
+    RevokePermit {
+        /// success or failure
+        status: ResponseStatus,
     },
     /// generic status response
     Status {
@@ -125,6 +319,53 @@ pub enum HandleAnswer {
 pub enum QueryMsg {
     /// Displays the auction information
     AuctionInfo {},
+    /// Displays the number of active bids
+    BidCount {},
+    /// Lists the active bids, best scoring first.  Only the auction creator can list bids, and
+    /// bidder addresses are hidden until the auction has closed
+    ListBids {
+        /// address of the auction creator
+        address: HumanAddr,
+        /// viewing key of the auction creator
+        key: String,
+        /// Optional page to display, starting at 0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        page: Option<u32>,
+        /// number of bids per page, at most MAX_PAGE_SIZE
+        page_size: u32,
+    },
+    /// Displays the active bid of an address authenticated with its viewing key, and the score
+    /// its stored terms receive
+    MyBid {
+        /// address of the bidder
+        address: HumanAddr,
+        /// viewing key of the bidder
+        key: String,
+    },
+    /// Displays the score the active bid of an address authenticated with its viewing key
+    /// receives under the borrower's preferences
+    BidScore {
+        /// address of the bidder
+        address: HumanAddr,
+        /// viewing key of the bidder
+        key: String,
+    },
+    /// Runs a query authenticated with a permit
+    WithPermit {
+        /// permit signed by the querier
+        permit: Permit,
+        /// query to run on behalf of the permit's signer
+        query: QueryWithPermit,
+    },
+}
+
+/// Queries that can be authenticated with a permit
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryWithPermit {
+    /// Displays the active bid of the permit's signer and the score its stored terms receive.
+    /// Requires the owner permission
+    MyBid {},
 }
 
 /// responses to queries
@@ -147,12 +388,323 @@ pub enum QueryAnswer {
         /// address of auction contract
         auction_address: HumanAddr,
         /// status of the auction can be "Accepting bids: Tokens to be sold have(not) been
-        /// consigned" or "Closed" (will also state if there are outstanding funds after auction
-        /// closure
+        /// consigned", "Closed" (will also state if there are outstanding funds after auction
+        /// closure), "Cancelled", "Paused" or "Unwound"
         status: String,
         /// If the auction resulted in a swap, this will state the winning bid
         #[serde(skip_serializing_if = "Option::is_none")]
         winning_bid: Option<Uint128>,
+        /// weights used to rank bids
+        preferences: BidPreferences,
+        /// kind of auction
+        kind: AuctionKind,
+        /// time the auction was created, which a Dutch auction's rate schedule starts from
+        started_at: u64,
+        /// Optional time after which bids are no longer accepted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ends_at: Option<u64>,
+        /// Optional platform fee taken at settlement
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee: Option<FeeConfig>,
+        /// oracle the score was taken from and the exposure opened with.  The seller chooses it,
+        /// so bidders should check it is an oracle they trust before relying on the score
+        #[serde(skip_serializing_if = "Option::is_none")]
+        oracle_contract: Option<ContractInfo>,
+    },
+    /// BidCount query response
+    BidCount {
+        /// number of active bids
+        count: u32,
+    },
+    /// ListBids query response
+    ListBids {
+        /// page of active bids
+        bids: Vec<BidInfo>,
+        /// total number of active bids
+        count: u32,
+        /// If the auction resulted in a loan, this will state the winning lender
+        #[serde(skip_serializing_if = "Option::is_none")]
+        lender: Option<HumanAddr>,
+    },
+    /// MyBid query response
+    MyBid {
+        /// Optional active bid, None if the address has no active bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid: Option<BidInfo>,
+    },
+    /// BidScore query response
+    BidScore {
+        /// Optional score of the active bid, None if the address has no active bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_score: Option<Uint128>,
+    },
+    /// response when a viewing key is wrong or has not been set
+    ViewingKeyError {
+        /// error description
+        msg: String,
+    },
+}
+
+/// bid as displayed to the auction creator
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct BidInfo {
+    /// Optional address of the bidder.  Only displayed after the auction has closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bidder: Option<HumanAddr>,
+    /// amount of bid
+    pub amount: Uint128,
+    /// time bid was placed
+    pub timestamp: u64,
+    /// score of the bid according to the borrower's preferences
+    pub bid_score: Uint128,
+    /// Optional longest loan term (in months) offered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_term: Option<u64>,
+    /// grace period (in months) offered
+    pub grace_period: u64,
+    /// prepayment penalty (in basis points) asked
+    pub prepayment_penalty: u64,
+    /// Optional note to the borrower
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Instantiation message of a marketplace.  Every auction it hosts sells and bids the same tokens
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MarketInitMsg {
+    /// sell contract code hash and address
+    pub sell_contract: ContractInfo,
+    /// bid contract code hash and address
+    pub bid_contract: ContractInfo,
+    /// oracle contract code hash and address
+    pub oracle_contract: ContractInfo,
+    /// secret seed the viewing key seeds of the hosted auctions are derived from
+    pub prng_seed: Binary,
+    /// Optional address allowed to administer the hosted auctions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin: Option<HumanAddr>,
+    /// Optional platform fee taken at settlement of every hosted auction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeConfig>,
+    /// Optional code hash and address of the registry of lenders allowed to bid.  The registry's
+    /// admin must add the marketplace with AddViewers before its auctions can check approvals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lender_registry: Option<ContractInfo>,
+}
+
+/// Handle messages of a marketplace.  Hosted auctions do not offer ViewBid, which the MyBid
+/// query replaces, or Migrate, which any other handle of the auction does on the way
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketHandleMsg {
+    /// CreateAuction will start a new auction selling the calling address' credit score
+    CreateAuction {
+        /// expected sum of credit
+        expected: Uint128,
+        /// alleged sum to pay
+        payment: Uint128,
+        /// Optional description of the auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Optional weights used to rank bids
+        #[serde(skip_serializing_if = "Option::is_none")]
+        preferences: Option<BidPreferences>,
+        /// Optional kind of auction.  Defaults to a reverse auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        kind: Option<AuctionKind>,
+        /// entropy mixed into the seed the auction generates viewing keys with
+        entropy: String,
+    },
+    /// Receive gets called by the token contracts of the marketplace.  The Send must carry a
+    /// MarketReceiveMsg naming the auction the tokens are for
+    Receive {
+        /// address of person or contract that sent the tokens that triggered this Receive
+        sender: HumanAddr,
+        /// address of the owner of the tokens sent to the marketplace
+        from: HumanAddr,
+        /// amount of tokens sent
+        amount: Uint128,
+        /// base64 encoded MarketReceiveMsg
+        msg: Binary,
+    },
+    /// Finalize will close an auction, as Finalize does for a single auction
+    Finalize {
+        /// id of the auction
+        auction_id: u64,
+        /// true if auction creator wants to keep the auction open if there are no active bids
+        only_if_bids: bool,
+    },
+    /// ReturnAll will return any funds still held by a closed auction
+    ReturnAll {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// CancelAuction will cancel an auction before its first bid
+    CancelAuction {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// UpdateAuction will amend an auction's parameters, as UpdateAuction does for a single
+    /// auction
+    UpdateAuction {
+        /// id of the auction
+        auction_id: u64,
+        /// Optional new description of the auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Optional new average bid that will be accepted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bid: Option<Uint128>,
+        /// Optional new time after which bids are no longer accepted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ends_at: Option<u64>,
+    },
+    /// SetFee will set the platform fee an auction takes at settlement.  Only the marketplace
+    /// admin can set the fee, and only before the auction's first bid
+    SetFee {
+        /// id of the auction
+        auction_id: u64,
+        /// Optional new fee configuration, None removes the fee
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fee: Option<FeeConfig>,
+    },
+    /// Pause will stop an auction from accepting new bids and consignments.  Admin only
+    Pause {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// Unpause will let an auction accept new bids and consignments again.  Admin only
+    Unpause {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// EmergencyUnwind will close an auction without a winner and return every bid and the
+    /// consigned tokens.  Admin only
+    EmergencyUnwind {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// CreateViewingKey will generate a viewing key for the calling address.  Every auction
+    /// keeps its own viewing keys
+    CreateViewingKey {
+        /// id of the auction
+        auction_id: u64,
+        /// entropy used to generate the key
+        entropy: String,
+        /// Optional padding to hide the length of the entropy
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+    /// SetViewingKey will set the viewing key of the calling address for an auction
+    SetViewingKey {
+        /// id of the auction
+        auction_id: u64,
+        /// the viewing key
+        key: String,
+        /// Optional padding to hide the length of the key
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+    /// RevokePermit will revoke a query permit of the calling address.  Permits name the
+    /// marketplace's address, so the permit is revoked for every hosted auction
+    RevokePermit {
+        /// name of the permit to revoke
+        permit_name: String,
+        /// Optional padding to hide the length of the name
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+}
+
+/// Message carried in the `msg` field of a SNIP-20 Send to a marketplace
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct MarketReceiveMsg {
+    /// id of the auction the tokens are for
+    pub auction_id: u64,
+    /// Optional intent of the tokens.  Without it, the token that was sent determines it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ReceiveMsg>,
+}
+
+/// Responses from marketplace handle functions that do not act on an existing auction
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketHandleAnswer {
+    /// response from creating an auction
+    CreateAuction {
+        /// id of the new auction
+        auction_id: u64,
+    },
+}
+
+/// Queries of a marketplace
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketQueryMsg {
+    /// Displays the information of an auction
+    AuctionInfo {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// Displays the number of active bids of an auction
+    BidCount {
+        /// id of the auction
+        auction_id: u64,
+    },
+    /// Lists the active bids of an auction, as ListBids does for a single auction
+    ListBids {
+        /// id of the auction
+        auction_id: u64,
+        /// address of the auction creator
+        address: HumanAddr,
+        /// viewing key the auction creator set for the auction
+        key: String,
+        /// Optional page to display, starting at 0
+        #[serde(skip_serializing_if = "Option::is_none")]
+        page: Option<u32>,
+        /// number of bids per page, at most MAX_PAGE_SIZE
+        page_size: u32,
+    },
+    /// Displays the active bid an address authenticated with its viewing key has in an auction
+    MyBid {
+        /// id of the auction
+        auction_id: u64,
+        /// address of the bidder
+        address: HumanAddr,
+        /// viewing key the bidder set for the auction
+        key: String,
+    },
+    /// Displays the score the active bid of an address authenticated with its viewing key
+    /// receives in an auction
+    BidScore {
+        /// id of the auction
+        auction_id: u64,
+        /// address of the bidder
+        address: HumanAddr,
+        /// viewing key the bidder set for the auction
+        key: String,
+    },
+    /// Runs a query of an auction authenticated with a permit for the marketplace's address
+    WithPermit {
+        /// id of the auction
+        auction_id: u64,
+        /// permit signed by the querier
+        permit: Permit,
+        /// query to run on behalf of the permit's signer
+        query: QueryWithPermit,
+    },
+    /// Displays the number of auctions the marketplace has created
+    AuctionCount {},
+}
+
+/// responses to marketplace queries that do not display an auction
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketQueryAnswer {
+    /// number of auctions created
+    AuctionCount {
+        /// number of auctions, which are numbered from 0
+        count: u64,
     },
 }
 
@@ -171,6 +723,39 @@ impl Query for OracleQueryMsg {
     const BLOCK_SIZE: usize = 256;
 }
 
+/// Handle messages for oracle contract
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleHandleMsg {
+    /// record an auction opened by the borrower.  The oracle limits the borrower's total
+    /// exposure by the score of their stored history
+    OpenExposure {
+        /// borrower address
+        borrower: HumanAddr,
+        /// amount of credit requested
+        amount: Uint128,
+        /// alleged sum the borrower pays, which the history must score high enough at
+        payment: Uint128,
+        /// Optional id of the auction within a marketplace
+        #[serde(skip_serializing_if = "Option::is_none")]
+        auction_id: Option<u64>,
+    },
+    /// settle the exposure recorded by this auction
+    SettleExposure {
+        /// borrower address
+        borrower: HumanAddr,
+        /// true if the auction resulted in a loan that is still outstanding
+        outstanding: bool,
+        /// Optional id of the auction within a marketplace
+        #[serde(skip_serializing_if = "Option::is_none")]
+        auction_id: Option<u64>,
+    },
+}
+
+impl HandleCallback for OracleHandleMsg {
+    const BLOCK_SIZE: usize = 256;
+}
+
 /// Query response for oracle contract
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -182,6 +767,61 @@ pub struct OracleQueryResponse {
     pub message: String
 }
 
+/// Query for lender registry contract
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryQueryMsg {
+    /// is the lender approved at the given time
+    IsApproved {
+        /// lender address
+        address: HumanAddr,
+        /// time to check the approval at
+        time: u64,
+        /// address of the auction asking
+        viewer: HumanAddr,
+        /// viewing key of the auction asking
+        key: String,
+    },
+}
+
+impl Query for RegistryQueryMsg {
+    const BLOCK_SIZE: usize = 256;
+}
+
+/// Handle messages for lender registry contract
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryHandleMsg {
+    /// set the viewing key the auction checks approvals with
+    SetViewingKey {
+        /// the viewing key
+        key: String,
+        /// Optional padding to hide the length of the key
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+}
+
+impl HandleCallback for RegistryHandleMsg {
+    const BLOCK_SIZE: usize = 256;
+}
+
+/// Query response for lender registry contract
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryQueryAnswer {
+    /// IsApproved query response
+    IsApproved {
+        /// true if the lender is approved
+        approved: bool,
+    },
+    /// response when the auction's viewing key is wrong, or the auction is not a viewer
+    ViewingKeyError {
+        /// error description
+        msg: String,
+    },
+}
+
 /// token's contract address and TokenInfo response
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct Token {
@@ -208,6 +848,29 @@ pub struct History {
     pub credits: Vec<Credit>,
 }
 
+impl History {
+    /// Returns the score of the history in an auction of the payment, None if it is too low to
+    /// borrow.  This is the score the oracle caps the borrower's exposure with
+    ///
+    /// # Arguments
+    ///
+    /// * `payment` - alleged sum to pay
+    pub fn score(&self, payment: Uint128) -> Option<u128> {
+        let credits: Vec<ScoredCredit> = self
+            .credits
+            .iter()
+            .map(|credit| ScoredCredit {
+                sum: credit.sum.u128(),
+                interest_rate: credit.interest_rate.u128(),
+                time: credit.time.u128(),
+                is_closed: credit.is_closed,
+            })
+            .collect();
+        let debts = self.debts.map_or(0, |debts| debts.u128());
+        scoring::score(&credits, debts, payment.u128())
+    }
+}
+
 /// Client credit data
 #[derive(Serialize, Deserialize, Clone, JsonSchema, Debug)]
 pub struct Credit {
@@ -221,6 +884,164 @@ pub struct Credit {
     pub is_closed: bool,
 }
 
+/// platform fee taken at settlement
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct FeeConfig {
+    /// fee in basis points
+    pub bps: u16,
+    /// address the fee is sent to
+    pub treasury: HumanAddr,
+    /// side of the swap the fee is taken from.  Defaults to the winning bid
+    #[serde(default)]
+    pub source: FeeSource,
+}
+
+impl FeeConfig {
+    /// Returns StdResult<()> which is an InvalidFee error if the fee is greater than the maximum
+    pub fn validate(&self) -> StdResult<()> {
+        if self.bps > MAX_FEE_BPS {
+            return Err(ContractError::InvalidFee { max: MAX_FEE_BPS }.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the fee taken from an amount
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - amount the fee is taken from
+    pub fn fee_of(&self, amount: u128) -> u128 {
+        amount.saturating_mul(self.bps as u128) / MAX_FEE_BPS as u128
+    }
+}
+
+/// side of the swap the platform fee is taken from
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSource {
+    /// the fee is taken in bid tokens from the winning bid sent to the seller
+    WinningBid,
+    /// the fee is taken in sale tokens from the consignment sent to the winner
+    Consignment,
+}
+
+impl Default for FeeSource {
+    fn default() -> Self {
+        FeeSource::WinningBid
+    }
+}
+
+/// kind of auction
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionKind {
+    /// lenders underbid one another until the seller finalizes the auction
+    Reverse {},
+    /// the offered rate starts at the average bid and steps down over time.  The first lender
+    /// to accept the current rate wins, and the auction is finalized immediately
+    Dutch {
+        /// amount the rate decreases every interval
+        step: Uint128,
+        /// length of an interval in seconds
+        interval: u64,
+        /// lowest rate the auction will step down to
+        floor: Uint128,
+    },
+}
+
+impl Default for AuctionKind {
+    fn default() -> Self {
+        AuctionKind::Reverse {}
+    }
+}
+
+impl AuctionKind {
+    /// Returns the rate offered at the given time, or None if this is not a Dutch auction
+    ///
+    /// # Arguments
+    ///
+    /// * `max_rate` - rate offered when the auction started
+    /// * `started_at` - time the auction started
+    /// * `time` - time to calculate the rate for
+    pub fn rate_at(&self, max_rate: u128, started_at: u64, time: u64) -> Option<u128> {
+        match self {
+            AuctionKind::Reverse {} => None,
+            AuctionKind::Dutch {
+                step,
+                interval,
+                floor,
+            } => {
+                let steps = time.saturating_sub(started_at) / interval;
+                let decrease = step.u128().saturating_mul(steps as u128);
+                Some(std::cmp::max(max_rate.saturating_sub(decrease), floor.u128()))
+            }
+        }
+    }
+}
+
+/// weights the borrower gives to each term of a bid when ranking bids.  A bid scores the weighted
+/// sum of how far its amount is below the average bid, its term, its grace period, and how far its
+/// prepayment penalty is below the maximum.  Higher scores win
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct BidPreferences {
+    /// weight of every token the bid amount is below the average bid
+    pub rate_weight: u32,
+    /// weight of every month of loan term
+    pub term_weight: u32,
+    /// weight of every month of grace period
+    pub grace_period_weight: u32,
+    /// weight of every basis point the prepayment penalty is below the maximum
+    pub prepayment_penalty_weight: u32,
+}
+
+impl Default for BidPreferences {
+    fn default() -> Self {
+        BidPreferences {
+            rate_weight: 1,
+            term_weight: 0,
+            grace_period_weight: 0,
+            prepayment_penalty_weight: 0,
+        }
+    }
+}
+
+impl BidPreferences {
+    /// Returns true if every weight is 0
+    pub fn is_empty(&self) -> bool {
+        self.rate_weight == 0
+            && self.term_weight == 0
+            && self.grace_period_weight == 0
+            && self.prepayment_penalty_weight == 0
+    }
+
+    /// Returns the score of a bid with the given terms
+    ///
+    /// # Arguments
+    ///
+    /// * `average_bid` - average bid of the auction
+    /// * `amount` - amount of the bid
+    /// * `term` - loan term (in months) offered
+    /// * `grace_period` - grace period (in months) offered
+    /// * `prepayment_penalty` - prepayment penalty (in basis points) asked
+    pub fn score(
+        &self,
+        average_bid: u128,
+        amount: u128,
+        term: u64,
+        grace_period: u64,
+        prepayment_penalty: u64,
+    ) -> u128 {
+        let weighted = |weight: u32, value: u128| (weight as u128).saturating_mul(value);
+        weighted(self.rate_weight, average_bid.saturating_sub(amount))
+            .saturating_add(weighted(self.term_weight, term as u128))
+            .saturating_add(weighted(self.grace_period_weight, grace_period as u128))
+            .saturating_add(weighted(
+                self.prepayment_penalty_weight,
+                MAX_PREPAYMENT_PENALTY.saturating_sub(prepayment_penalty) as u128,
+            ))
+    }
+}
+
 /// code hash and address of a contract
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
 pub struct ContractInfo {
@@ -2,11 +2,16 @@ use std::{any::type_name, collections::HashSet};
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_std::{CanonicalAddr, Env, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 
 use secret_toolkit::serialization::{Bincode2, Serde};
 
-use crate::msg::ContractInfo;
+use crate::msg::{
+    AuctionKind, BidPreferences, ContractInfo, FeeConfig, CONFIG_KEY, PREFIX_VIEW_KEY, PRNG_SEED_KEY,
+    VERSION_KEY,
+};
+use crate::viewing_key::{sha_256, ViewingKey};
 
 /// state of the auction
 #[derive(Serialize, Deserialize)]
@@ -29,12 +34,37 @@ pub struct State {
     pub bidders: HashSet<Vec<u8>>,
     /// true if the auction is closed
     pub is_completed: bool,
+    /// true if the auction was closed by being cancelled
+    pub is_cancelled: bool,
+    /// true if the admin has paused new bids and consignments
+    pub is_paused: bool,
+    /// true if the auction was closed by the admin's emergency unwind
+    pub is_unwound: bool,
+    /// Optional time after which bids are no longer accepted
+    pub ends_at: Option<u64>,
     /// true if all tokens for sale have been consigned to escrow
     pub tokens_consigned: bool,
     /// Optional text description of auction
     pub description: Option<String>,
     /// winning bid
     pub winning_bid: u128,
+    /// weights used to rank bids
+    pub preferences: BidPreferences,
+    /// Optional address allowed to administer the auction
+    pub admin: Option<HumanAddr>,
+    /// Optional platform fee taken at settlement
+    pub fee: Option<FeeConfig>,
+    /// Optional code hash and address of the registry of lenders allowed to bid
+    pub lender_registry: Option<ContractInfo>,
+    /// code hash and address of the oracle contract.  None for auctions created before the
+    /// oracle tracked borrower exposure
+    pub oracle_contract: Option<ContractInfo>,
+    /// kind of auction
+    pub kind: AuctionKind,
+    /// time the auction was created
+    pub started_at: u64,
+    /// address of the lender that won the auction
+    pub lender: Option<HumanAddr>,
 }
 
 /// bid data
@@ -44,6 +74,213 @@ pub struct Bid {
     pub amount: u128,
     /// time bid was placed
     pub timestamp: u64,
+    /// longest loan term (in months) the lender is willing to offer
+    pub max_term: Option<u64>,
+    /// grace period (in months) before the first repayment is due
+    pub grace_period: u64,
+    /// penalty (in basis points) for repaying the loan early
+    pub prepayment_penalty: u64,
+    /// note to the borrower
+    pub note: Option<String>,
+}
+
+/// state of a marketplace hosting many auctions.  Each auction keeps its own State and bids
+/// under the PREFIX_AUCTIONS prefix
+#[derive(Serialize, Deserialize)]
+pub struct MarketState {
+    /// code hash and address of sell token contract
+    pub sell_contract: ContractInfo,
+    /// code hash and address of bid token contract
+    pub bid_contract: ContractInfo,
+    /// code hash and address of the oracle contract
+    pub oracle_contract: ContractInfo,
+    /// Optional address allowed to administer the hosted auctions
+    pub admin: Option<HumanAddr>,
+    /// Optional platform fee taken at settlement of every hosted auction
+    pub fee: Option<FeeConfig>,
+    /// Optional code hash and address of the registry of lenders allowed to bid
+    pub lender_registry: Option<ContractInfo>,
+    /// number of auctions created, which is also the id of the next auction
+    pub auction_count: u64,
+}
+
+/// version of the current state layout
+pub const STATE_VERSION: u16 = 2;
+
+/// state of the auction as saved by the first version of the contract.  Instances saved
+/// with this layout have no version stored
+#[derive(Serialize, Deserialize)]
+pub struct StateV1 {
+    /// address of auction contract
+    pub auction_addr: HumanAddr,
+    /// address of auction owner
+    pub seller: HumanAddr,
+    /// code hash and address of sell token contract
+    pub sell_contract: ContractInfo,
+    /// code hash and address of bid token contract
+    pub bid_contract: ContractInfo,
+    /// amount of tokens for sale
+    pub score: u128,
+    /// average bid that will be accepted
+    pub average_bid: u128,
+    /// amount of tokens currently consigned to auction escrow
+    pub currently_consigned: u128,
+    /// list of addresses of bidders
+    pub bidders: HashSet<Vec<u8>>,
+    /// true if the auction is closed
+    pub is_completed: bool,
+    /// true if all tokens for sale have been consigned to escrow
+    pub tokens_consigned: bool,
+    /// Optional text description of auction
+    pub description: Option<String>,
+    /// winning bid
+    pub winning_bid: u128,
+}
+
+impl StateV1 {
+    /// Returns the state in the current layout.  Everything added since the first version
+    /// gets the value an auction created without it would have had
+    pub fn upgrade(self) -> State {
+        State {
+            auction_addr: self.auction_addr,
+            seller: self.seller,
+            sell_contract: self.sell_contract,
+            bid_contract: self.bid_contract,
+            score: self.score,
+            average_bid: self.average_bid,
+            currently_consigned: self.currently_consigned,
+            bidders: self.bidders,
+            is_completed: self.is_completed,
+            is_cancelled: false,
+            is_paused: false,
+            is_unwound: false,
+            ends_at: None,
+            tokens_consigned: self.tokens_consigned,
+            description: self.description,
+            winning_bid: self.winning_bid,
+            preferences: BidPreferences::default(),
+            admin: None,
+            fee: None,
+            lender_registry: None,
+            oracle_contract: None,
+            kind: AuctionKind::default(),
+            started_at: 0,
+            lender: None,
+        }
+    }
+}
+
+/// bid data as saved by the first version of the contract
+#[derive(Serialize, Deserialize)]
+pub struct BidV1 {
+    /// amount of bid
+    pub amount: u128,
+    /// time bid was placed
+    pub timestamp: u64,
+}
+
+impl BidV1 {
+    /// Returns the bid in the current layout, with the terms a bid without terms stands for
+    pub fn upgrade(self) -> Bid {
+        Bid {
+            amount: self.amount,
+            timestamp: self.timestamp,
+            max_term: None,
+            grace_period: 0,
+            prepayment_penalty: 0,
+            note: None,
+        }
+    }
+}
+
+/// Returns StdResult<u16> of the version of the stored state layout.  Instances without a
+/// stored version were saved by the first version of the contract
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn stored_version<S: ReadonlyStorage>(storage: &S) -> StdResult<u16> {
+    let version = may_load::<u16, _>(storage, VERSION_KEY)?.unwrap_or(1);
+    if version > STATE_VERSION {
+        return Err(StdError::generic_err(format!(
+            "Stored state version {} is newer than this contract's version {}",
+            version, STATE_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+/// Returns StdResult<State> from loading the auction state, upgrading it in memory if it was
+/// saved with an older layout
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn load_state<S: ReadonlyStorage>(storage: &S) -> StdResult<State> {
+    match stored_version(storage)? {
+        1 => load::<StateV1, _>(storage, CONFIG_KEY).map(StateV1::upgrade),
+        _ => load(storage, CONFIG_KEY),
+    }
+}
+
+/// Returns StdResult<()> resulting from saving the auction state and the version of its
+/// layout
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `state` - a reference to the auction state
+pub fn save_state<S: Storage>(storage: &mut S, state: &State) -> StdResult<()> {
+    save(storage, CONFIG_KEY, state)?;
+    save(storage, VERSION_KEY, &STATE_VERSION)
+}
+
+/// Returns StdResult<Option<Bid>> from loading a bid, upgrading it in memory if it was saved
+/// with an older layout.  Returns Ok(None) if the address has no bid
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `bidder` - a byte slice of the bidder's canonical address
+pub fn load_bid<S: ReadonlyStorage>(storage: &S, bidder: &[u8]) -> StdResult<Option<Bid>> {
+    match stored_version(storage)? {
+        1 => Ok(may_load::<BidV1, _>(storage, bidder)?.map(BidV1::upgrade)),
+        _ => may_load(storage, bidder),
+    }
+}
+
+/// Returns StdResult<Option<u16>> of the version the stored state was upgraded from, after
+/// rewriting the state and every bid in the current layout.  Returns Ok(None) if the state
+/// was already current.  Auctions saved before viewing keys get a prng seed derived from their
+/// stored state and bids, which only the contract can read, and the migrating message
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `env` - a reference to the Env of the message the migration runs in
+pub fn migrate_state<S: Storage>(storage: &mut S, env: &Env) -> StdResult<Option<u16>> {
+    let version = stored_version(storage)?;
+    if version == STATE_VERSION {
+        return Ok(None);
+    }
+    let state = load_state(storage)?;
+    let mut entropy = storage.get(CONFIG_KEY).unwrap_or_default();
+    for bidder in state.bidders.iter() {
+        if let Some(stored) = storage.get(bidder.as_slice()) {
+            entropy.extend_from_slice(&stored);
+        }
+        if let Some(bid) = load_bid(storage, bidder.as_slice())? {
+            save(storage, bidder.as_slice(), &bid)?;
+        }
+    }
+    if storage.get(PRNG_SEED_KEY).is_none() {
+        entropy.extend_from_slice(&env.block.height.to_be_bytes());
+        entropy.extend_from_slice(&env.block.time.to_be_bytes());
+        entropy.extend_from_slice(env.message.sender.0.as_bytes());
+        save(storage, PRNG_SEED_KEY, &sha_256(&entropy).to_vec())?;
+    }
+    save_state(storage, &state)?;
+    Ok(Some(version))
 }
 
 /// Returns StdResult<()> resulting from saving an item to storage
@@ -99,3 +336,136 @@ pub fn may_load<T: DeserializeOwned, S: ReadonlyStorage>(
         None => Ok(None),
     }
 }
+
+/// Saves the hash of a viewing key for an address
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `owner` - a reference to the canonical address of the key's owner
+/// * `key` - a reference to the viewing key
+pub fn write_viewing_key<S: Storage>(storage: &mut S, owner: &CanonicalAddr, key: &ViewingKey) {
+    let mut key_store = PrefixedStorage::new(PREFIX_VIEW_KEY, storage);
+    key_store.set(owner.as_slice(), &key.to_hashed());
+}
+
+/// Returns the hash of the viewing key of an address, or None if it never set one
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `owner` - a reference to the canonical address of the key's owner
+pub fn read_viewing_key<S: ReadonlyStorage>(storage: &S, owner: &CanonicalAddr) -> Option<Vec<u8>> {
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_VIEW_KEY, storage);
+    key_store.get(owner.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockStorage};
+
+    use crate::contract::handle;
+    use crate::msg::{HandleAnswer, HandleMsg};
+
+    /// auction state saved by the first version of the contract
+    const STATE_V1: &[u8] = include_bytes!("../tests/fixtures/state_v1.bin");
+    /// bid saved by the first version of the contract
+    const BID_V1: &[u8] = include_bytes!("../tests/fixtures/bid_v1.bin");
+    /// canonical address the fixture bid is saved under
+    const BIDDER: [u8; 20] = [
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20,
+    ];
+
+    fn v1_storage() -> MockStorage {
+        let mut storage = MockStorage::new();
+        storage.set(CONFIG_KEY, STATE_V1);
+        storage.set(&BIDDER, BID_V1);
+        storage
+    }
+
+    #[test]
+    fn fixtures_match_v1_layout() {
+        let state: StateV1 = Bincode2::deserialize(STATE_V1).unwrap();
+        assert_eq!(state.auction_addr, HumanAddr("secret1auction".to_string()));
+        assert_eq!(state.seller, HumanAddr("secret1seller".to_string()));
+        assert_eq!(state.sell_contract.code_hash, "sellhash");
+        assert_eq!(state.bid_contract.address, HumanAddr("secret1bid".to_string()));
+        assert_eq!(state.score, 1666);
+        assert_eq!(state.average_bid, 1200000);
+        assert_eq!(state.currently_consigned, 1666);
+        assert!(state.bidders.contains(&BIDDER.to_vec()));
+        assert!(!state.is_completed);
+        assert!(state.tokens_consigned);
+        assert_eq!(state.description, Some("test auction".to_string()));
+
+        let bid: BidV1 = Bincode2::deserialize(BID_V1).unwrap();
+        assert_eq!(bid.amount, 1100000);
+        assert_eq!(bid.timestamp, 1600000000);
+
+        // the current layout can not read them
+        assert!(Bincode2::deserialize::<State>(STATE_V1).is_err());
+    }
+
+    #[test]
+    fn lazy_upgrade_on_load() {
+        let storage = v1_storage();
+        assert_eq!(stored_version(&storage).unwrap(), 1);
+
+        let state = load_state(&storage).unwrap();
+        assert_eq!(state.seller, HumanAddr("secret1seller".to_string()));
+        assert_eq!(state.currently_consigned, 1666);
+        assert!(!state.is_cancelled && !state.is_paused && !state.is_unwound);
+        assert!(state.oracle_contract.is_none());
+        assert!(state.lender.is_none());
+
+        let bid = load_bid(&storage, &BIDDER).unwrap().unwrap();
+        assert_eq!(bid.amount, 1100000);
+        assert_eq!(bid.timestamp, 1600000000);
+        assert_eq!(bid.max_term, None);
+        assert_eq!(bid.grace_period, 0);
+        assert_eq!(bid.prepayment_penalty, 0);
+        assert!(load_bid(&storage, &[0u8; 20]).unwrap().is_none());
+    }
+
+    #[test]
+    fn migrate_rewrites_state_and_bids() {
+        let mut storage = v1_storage();
+        assert!(storage.get(PRNG_SEED_KEY).is_none());
+        assert_eq!(migrate_state(&mut storage, &mock_env("anyone", &[])).unwrap(), Some(1));
+        assert_eq!(stored_version(&storage).unwrap(), STATE_VERSION);
+        let prng_seed: Vec<u8> = load(&storage, PRNG_SEED_KEY).unwrap();
+
+        // stored in the current layout now
+        let state: State = load(&storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.score, 1666);
+        let bid: Bid = load(&storage, &BIDDER).unwrap();
+        assert_eq!(bid.amount, 1100000);
+
+        // migrating again is a no-op and keeps the seed
+        assert_eq!(migrate_state(&mut storage, &mock_env("other", &[])).unwrap(), None);
+        assert_eq!(load::<Vec<u8>, _>(&storage, PRNG_SEED_KEY).unwrap(), prng_seed);
+
+        // a newer layout is refused instead of misread
+        save(&mut storage, VERSION_KEY, &(STATE_VERSION + 1)).unwrap();
+        assert!(load_state(&storage).is_err());
+        assert!(migrate_state(&mut storage, &mock_env("anyone", &[])).is_err());
+    }
+
+    #[test]
+    fn migrated_auctions_create_viewing_keys() {
+        let mut deps = mock_dependencies(20, &[]);
+        deps.storage = v1_storage();
+
+        // the first handle migrates the state, seeding the prng viewing keys are made with
+        let create_key = HandleMsg::CreateViewingKey {
+            entropy: "entropy".to_string(),
+            padding: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("secret1bidder", &[]), create_key).unwrap();
+        let answer: HandleAnswer = from_binary(&handle_result.data.unwrap()).unwrap();
+        assert!(matches!(answer, HandleAnswer::CreateViewingKey { .. }));
+        assert_eq!(stored_version(&deps.storage).unwrap(), STATE_VERSION);
+    }
+}
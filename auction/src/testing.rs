@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Binary, CosmosMsg, Empty, Extern, HumanAddr, Querier,
+    QuerierResult, QueryRequest, StdResult, SystemError, Uint128, WasmMsg, WasmQuery,
+};
+
+use oracle::msg::{
+    HandleMsg as OracleHandleMsg, History as OracleHistory, InitMsg as OracleInitMsg,
+    QueryMsg as OracleQueryMsg,
+};
+
+use registry::msg::{
+    HandleMsg as RegistryHandleMsg, InitMsg as RegistryInitMsg, QueryMsg as RegistryQueryMsg,
+};
+
+use crate::fixtures::{contract_info, BID_TOKEN, ORACLE, REGISTRY, SELL_TOKEN};
+use crate::msg::{AuctionKind, BidPreferences};
+use crate::state::State;
+
+/// token info a SNIP-20 stand-in answers the token_info query with
+#[derive(Serialize, Clone)]
+struct TokenInfoStandIn {
+    name: String,
+    symbol: String,
+    decimals: u8,
+    total_supply: Option<Uint128>,
+}
+
+/// SNIP-20 queries the stand-ins answer
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenQueryMsg {
+    TokenInfo {},
+}
+
+/// SNIP-20 token_info query response
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenQueryAnswer<'a> {
+    TokenInfo(&'a TokenInfoStandIn),
+}
+
+/// SNIP-20 handles whose effects the tests check
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenHandleMsg {
+    Transfer { recipient: HumanAddr, amount: Uint128 },
+}
+
+/// transfer of SNIP-20 tokens requested by a CosmosMsg
+#[derive(Debug, PartialEq)]
+pub struct Transfer {
+    /// address of the token contract
+    pub token: HumanAddr,
+    /// address the tokens are sent to
+    pub recipient: HumanAddr,
+    /// amount of tokens sent
+    pub amount: Uint128,
+}
+
+/// Querier that answers smart queries to the oracle and the lender registry with in-process
+/// contracts, and token_info queries with SNIP-20 stand-ins.  Every other query goes to the
+/// MockQuerier
+pub struct ContractsQuerier {
+    base: MockQuerier,
+    oracle: Extern<MockStorage, MockApi, MockQuerier>,
+    registry: Extern<MockStorage, MockApi, MockQuerier>,
+    tokens: HashMap<HumanAddr, TokenInfoStandIn>,
+}
+
+impl ContractsQuerier {
+    /// Returns a ContractsQuerier with an empty oracle, a lender registry administered by
+    /// "admin" and both tokens of an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - MockQuerier that answers every other query
+    pub fn new(base: MockQuerier) -> Self {
+        let mut oracle = mock_dependencies(20, &[]);
+        oracle::contract::init(&mut oracle, mock_env("owner", &[]), OracleInitMsg::default())
+            .unwrap();
+        let mut registry = mock_dependencies(20, &[]);
+        let registry_init = RegistryInitMsg { admin: None };
+        registry::contract::init(&mut registry, mock_env("admin", &[]), registry_init).unwrap();
+        let mut querier = ContractsQuerier {
+            base,
+            oracle,
+            registry,
+            tokens: HashMap::new(),
+        };
+        querier.add_token(SELL_TOKEN, "SELL");
+        querier.add_token(BID_TOKEN, "BID");
+        querier
+    }
+
+    /// Adds a SNIP-20 stand-in
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the token contract
+    /// * `symbol` - symbol of the token
+    pub fn add_token(&mut self, address: &str, symbol: &str) {
+        let info = TokenInfoStandIn {
+            name: format!("{} token", symbol),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            total_supply: None,
+        };
+        self.tokens.insert(HumanAddr(address.to_string()), info);
+    }
+
+    /// Adds a credit history to the oracle
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - address of the user
+    /// * `history` - credit history of the user
+    pub fn add_history(&mut self, user: &str, history: OracleHistory) {
+        let handle_msg = OracleHandleMsg::AddHistory {
+            user: HumanAddr(user.to_string()),
+            history,
+        };
+        oracle::contract::handle(&mut self.oracle, mock_env("owner", &[]), handle_msg).unwrap();
+    }
+
+    /// Runs a handle of the lender registry
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - address sending the handle, "admin" for the registry's admin
+    /// * `msg` - handle message sent to the registry
+    pub fn handle_registry(&mut self, sender: &str, msg: RegistryHandleMsg) {
+        registry::contract::handle(&mut self.registry, mock_env(sender, &[]), msg).unwrap();
+    }
+
+    /// Returns the oracle's answer to a smart query
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - query message sent to the oracle
+    fn query_oracle(&self, msg: &Binary) -> StdResult<Binary> {
+        let query: OracleQueryMsg = from_binary(msg)?;
+        oracle::contract::query(&self.oracle, query)
+    }
+
+    /// Returns the lender registry's answer to a smart query
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - query message sent to the registry
+    fn query_registry(&self, msg: &Binary) -> StdResult<Binary> {
+        let query: RegistryQueryMsg = from_binary(msg)?;
+        registry::contract::query(&self.registry, query)
+    }
+
+    /// Returns a SNIP-20 stand-in's answer to a smart query
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - token info of the stand-in
+    /// * `msg` - query message sent to the token
+    fn query_token(token: &TokenInfoStandIn, msg: &Binary) -> StdResult<Binary> {
+        match from_binary(msg)? {
+            TokenQueryMsg::TokenInfo {} => to_binary(&TokenQueryAnswer::TokenInfo(token)),
+        }
+    }
+}
+
+impl Querier for ContractsQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(request) => request,
+            Err(_) => return self.base.raw_query(bin_request),
+        };
+        if let QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr, msg, ..
+        }) = request
+        {
+            if contract_addr.as_str() == ORACLE {
+                return Ok(self.query_oracle(&msg));
+            }
+            if contract_addr.as_str() == REGISTRY {
+                return Ok(self.query_registry(&msg));
+            }
+            return match self.tokens.get(&contract_addr) {
+                Some(token) => Ok(Self::query_token(token, &msg)),
+                None => Err(SystemError::NoSuchContract {
+                    addr: contract_addr,
+                }),
+            };
+        }
+        self.base.raw_query(bin_request)
+    }
+}
+
+/// Returns dependencies whose querier answers for the oracle, the lender registry and both
+/// tokens of an auction
+pub fn mock_deps() -> Extern<MockStorage, MockApi, ContractsQuerier> {
+    let deps = mock_dependencies(20, &[]);
+    Extern {
+        storage: deps.storage,
+        api: deps.api,
+        querier: ContractsQuerier::new(deps.querier),
+    }
+}
+
+/// Returns the state of an open auction of the stand-in tokens scoring 1000, with its 1000 sale
+/// tokens consigned and no bids.  Saving it bypasses the oracle that creating an auction queries
+///
+/// # Arguments
+///
+/// * `auction_addr` - address the auction lives at
+/// * `seller` - address of the seller
+pub fn open_auction_state(auction_addr: &str, seller: &str) -> State {
+    State {
+        auction_addr: HumanAddr(auction_addr.to_string()),
+        seller: HumanAddr(seller.to_string()),
+        sell_contract: contract_info(SELL_TOKEN),
+        bid_contract: contract_info(BID_TOKEN),
+        score: 1000,
+        average_bid: 1200000,
+        currently_consigned: 1000,
+        bidders: HashSet::new(),
+        is_completed: false,
+        is_cancelled: false,
+        is_paused: false,
+        is_unwound: false,
+        ends_at: None,
+        tokens_consigned: true,
+        description: None,
+        winning_bid: 0,
+        preferences: BidPreferences::default(),
+        admin: None,
+        fee: None,
+        lender_registry: None,
+        oracle_contract: None,
+        kind: AuctionKind::default(),
+        started_at: 0,
+        lender: None,
+    }
+}
+
+/// Returns the SNIP-20 transfers among the messages, in order
+///
+/// # Arguments
+///
+/// * `messages` - messages returned by a handle
+pub fn transfers(messages: &[CosmosMsg]) -> Vec<Transfer> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => match from_binary(msg) {
+                Ok(TokenHandleMsg::Transfer { recipient, amount }) => Some(Transfer {
+                    token: contract_addr.clone(),
+                    recipient,
+                    amount,
+                }),
+                Err(_) => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Binary, Env};
+
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// size of a hashed viewing key
+pub const VIEWING_KEY_SIZE: usize = 32;
+
+/// prefix of every generated viewing key
+const VIEWING_KEY_PREFIX: &str = "api_key_";
+
+/// viewing key used to authenticate queries
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct ViewingKey(pub String);
+
+impl ViewingKey {
+    /// Returns a new pseudo-random viewing key
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - reference to the Env of the contract's environment
+    /// * `seed` - the contract's prng seed
+    /// * `entropy` - entropy supplied by the caller
+    pub fn new(env: &Env, seed: &[u8], entropy: &[u8]) -> Self {
+        let mut rng_entropy = Vec::with_capacity(
+            seed.len() + 16 + env.message.sender.0.len() + entropy.len(),
+        );
+        rng_entropy.extend_from_slice(seed);
+        rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
+        rng_entropy.extend_from_slice(&env.block.time.to_be_bytes());
+        rng_entropy.extend_from_slice(env.message.sender.0.as_bytes());
+        rng_entropy.extend_from_slice(entropy);
+
+        let key = sha_256(&rng_entropy);
+        ViewingKey(VIEWING_KEY_PREFIX.to_string() + &Binary(key.to_vec()).to_base64())
+    }
+
+    /// Returns the hash of this key that gets saved in storage
+    pub fn to_hashed(&self) -> [u8; VIEWING_KEY_SIZE] {
+        sha_256(self.0.as_bytes())
+    }
+
+    /// Returns true if this key matches the saved hash.  Compares in constant time
+    ///
+    /// # Arguments
+    ///
+    /// * `hashed` - the saved hash of a viewing key
+    pub fn check_viewing_key(&self, hashed: &[u8]) -> bool {
+        bool::from(self.to_hashed()[..].ct_eq(hashed))
+    }
+}
+
+impl fmt::Display for ViewingKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Returns the sha256 hash of the data
+///
+/// # Arguments
+///
+/// * `data` - the data to hash
+pub fn sha_256(data: &[u8]) -> [u8; VIEWING_KEY_SIZE] {
+    let mut hashed = [0u8; VIEWING_KEY_SIZE];
+    hashed.copy_from_slice(&Sha256::digest(data));
+    hashed
+}
@@ -1,18 +1,672 @@
-//! This integration test tries to run and call the generated wasm.
-//! It depends on a Wasm build being available, which you can create with `cargo wasm`.
-//! Then running `cargo integration-test` will validate we can properly call into that generated Wasm.
+//! This integration test runs the compiled auction and oracle contracts in cosmwasm-vm.
+//! It depends on Wasm builds of both contracts, which you can create with
+//!     cargo wasm && (cd ../oracle && cargo wasm)
+//! Then running `cargo integration-test` will call into the generated Wasm.
 //!
-//! You can easily convert unit tests to integration tests.
-//! 1. First copy them over verbatum,
-//! 2. Then change
-//!      let mut deps = mock_dependencies(20, &[]);
-//!    to
-//!      let mut deps = mock_instance(WASM, &[]);
-//! 3. If you access raw storage, where ever you see something like:
-//!      deps.storage.get(CONFIG_KEY).expect("no data stored");
-//!    replace it with:
-//!      deps.with_storage(|store| {
-//!          let data = store.get(CONFIG_KEY).expect("no data stored");
-//!          //...
-//!      });
-//! 4. Anywhere you see query(&deps, ...) you must replace it with query(&mut deps, ...)
+//! The auction's smart queries are answered by the oracle instance and by SNIP-20 token_info
+//! stand-ins, and the messages the auction returns to the oracle are executed on the oracle
+//! instance, so the whole init/handle/query surface of both contracts runs as Wasm.  The gas
+//! every call uses is checked against GAS_BUDGETS.  A call that got more expensive fails the
+//! test, and its budget has to be raised on purpose.  Running
+//!     GAS_REPORT=1 cargo integration-test -- --nocapture
+//! prints the gas of every call and the budget it would get.
+
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use cosmwasm_std::testing::mock_env;
+use cosmwasm_std::{
+    from_slice, to_binary, to_vec, Binary, CosmosMsg, Empty, Env, HandleResponse, HumanAddr,
+    InitResponse, QueryRequest, StdError, StdResult, Uint128, WasmMsg, WasmQuery,
+};
+use cosmwasm_vm::testing::{MockApi, MockQuerier, MockStorage};
+use cosmwasm_vm::{
+    call_handle_raw, call_init_raw, call_query_raw, Extern, FfiResult, GasInfo, Instance, Querier,
+};
+use parity_wasm::elements::{Instruction, Module, Type, ValueType};
+
+use diploma_master::fixtures::auction_init_msg;
+use diploma_master::msg::{AuctionKind, HandleMsg, InitMsg, QueryAnswer, QueryMsg, ReceiveMsg};
+use oracle::fixtures::sample_history;
+use oracle::msg::{
+    ExposureResponse, HandleAnswer as OracleHandleAnswer,
+    HandleMsg as OracleHandleMsg, InitMsg as OracleInitMsg, QueryMsg as OracleQueryMsg,
+    QueryResponse as OracleQueryResponse,
+};
+
+/// Wasm build of the auction contract
+static AUCTION_WASM: &str = "target/wasm32-unknown-unknown/release/diploma_master.wasm";
+/// Wasm build of the oracle contract
+static ORACLE_WASM: &str = "../oracle/target/wasm32-unknown-unknown/release/oracle.wasm";
+
+/// gas each instance starts with
+const GAS_LIMIT: u64 = 10_000_000_000;
+
+/// most gas each call may use.  Each is the largest budget GAS_REPORT prints for the call,
+/// which adds a margin of about 10% to the gas it used.  A call over its budget fails with the
+/// gas it used
+const GAS_BUDGETS: &[(&str, u64)] = &[
+    ("oracle init", 150_000),
+    ("oracle add_history", 300_000),
+    ("oracle add_histories", 600_000),
+    ("oracle set_reporters", 150_000),
+    ("oracle set_exposure_callers", 150_000),
+    ("oracle get_history", 250_000),
+    ("oracle open_exposure", 250_000),
+    ("oracle settle_exposure", 250_000),
+    ("oracle get_exposure", 150_000),
+    ("oracle migrate", 150_000),
+    ("auction init", 1_000_000),
+    ("auction consign", 600_000),
+    ("auction bid", 700_000),
+    ("auction accept_rate", 1_200_000),
+    ("auction view_bid", 400_000),
+    ("auction set_viewing_key", 400_000),
+    ("auction my_bid", 400_000),
+    ("auction permit_my_bid", 5_000_000),
+    ("auction list_bids", 500_000),
+    ("auction update", 500_000),
+    ("auction cancel", 700_000),
+    ("auction auction_info", 700_000),
+    ("auction bid_count", 250_000),
+    ("auction finalize", 1_200_000),
+    ("auction return_all", 500_000),
+    ("auction rejected", 500_000),
+];
+
+type OracleInstance = Instance<MockStorage, MockApi, MockQuerier>;
+type AuctionInstance = Instance<MockStorage, MockApi, ContractsQuerier>;
+
+/// Querier of the auction instance.  Smart queries to the oracle are run on the oracle instance,
+/// and token_info queries are answered by stand-ins of the two tokens
+struct ContractsQuerier {
+    oracle: Rc<RefCell<OracleInstance>>,
+}
+
+impl Querier for ContractsQuerier {
+    fn query_raw(
+        &self,
+        request: &[u8],
+        _gas_limit: u64,
+    ) -> FfiResult<cosmwasm_std_vm::SystemResult<cosmwasm_std_vm::StdResult<cosmwasm_std_vm::Binary>>>
+    {
+        let request: QueryRequest<Empty> = from_slice(request).expect("unexpected query");
+        let (contract_addr, msg) = match request {
+            QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr, msg, ..
+            }) => (contract_addr, msg),
+            _ => panic!("only smart queries are expected"),
+        };
+        match contract_addr.as_str() {
+            "oracle" => {
+                let mut oracle = self.oracle.borrow_mut();
+                let gas_before = oracle.get_gas_left();
+                let result = call_query_raw(&mut oracle, msg.as_slice()).expect("oracle failed");
+                let used = gas_before - oracle.get_gas_left();
+                let answer: cosmwasm_std_vm::StdResult<cosmwasm_std_vm::Binary> =
+                    cosmwasm_std_vm::from_slice(&result).expect("invalid oracle answer");
+                (Ok(Ok(answer)), GasInfo::with_externally_used(used))
+            }
+            "selltoken" | "bidtoken" => {
+                let symbol = if contract_addr.as_str() == "selltoken" {
+                    "SELL"
+                } else {
+                    "BID"
+                };
+                let answer = format!(
+                    r#"{{"token_info":{{"name":"{} token","symbol":"{}","decimals":6,"total_supply":null}}}}"#,
+                    symbol, symbol
+                );
+                (
+                    Ok(Ok(Ok(cosmwasm_std_vm::Binary(answer.into_bytes())))),
+                    GasInfo::free(),
+                )
+            }
+            other => panic!("unexpected query to {}", other),
+        }
+    }
+}
+
+/// Returns the Wasm at the path, with instructions to build it if it is missing
+fn wasm(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| {
+        panic!(
+            "Could not read {}: {}.  Build it with `cargo wasm` in the contract's directory",
+            path, e
+        )
+    })
+}
+
+/// Returns the Env of a call to a contract
+fn env(sender: &str, contract: &str) -> Vec<u8> {
+    let mut env: Env = mock_env(sender, &[]);
+    env.contract.address = HumanAddr(contract.to_string());
+    env.contract_code_hash = format!("{}hash", contract);
+    to_vec(&env).unwrap()
+}
+
+/// Fails if the call used more gas than its budget
+fn check_gas(call: &str, used: u64) {
+    let budget = GAS_BUDGETS
+        .iter()
+        .find(|(name, _)| *name == call)
+        .map(|(_, budget)| *budget)
+        .unwrap_or_else(|| panic!("no gas budget for {}", call));
+    if std::env::var_os("GAS_REPORT").is_some() {
+        println!("(\"{}\", {}), // used {}", call, used + used / 10, used);
+    }
+    assert!(used <= budget, "{} used {} gas, over its budget of {}", call, used, budget);
+}
+
+fn init<S, A, Q>(
+    instance: &mut Instance<S, A, Q>,
+    call: &str,
+    env: Vec<u8>,
+    msg: Vec<u8>,
+) -> StdResult<InitResponse>
+where
+    S: cosmwasm_vm::Storage + 'static,
+    A: cosmwasm_vm::Api + 'static,
+    Q: Querier + 'static,
+{
+    let gas_before = instance.get_gas_left();
+    let result = call_init_raw(instance, &env, &msg).expect("init trapped");
+    check_gas(call, gas_before - instance.get_gas_left());
+    from_slice(&result).unwrap()
+}
+
+fn handle<S, A, Q>(
+    instance: &mut Instance<S, A, Q>,
+    call: &str,
+    env: Vec<u8>,
+    msg: Vec<u8>,
+) -> StdResult<HandleResponse>
+where
+    S: cosmwasm_vm::Storage + 'static,
+    A: cosmwasm_vm::Api + 'static,
+    Q: Querier + 'static,
+{
+    let gas_before = instance.get_gas_left();
+    let result = call_handle_raw(instance, &env, &msg).expect("handle trapped");
+    check_gas(call, gas_before - instance.get_gas_left());
+    from_slice(&result).unwrap()
+}
+
+fn query<S, A, Q>(instance: &mut Instance<S, A, Q>, call: &str, msg: Vec<u8>) -> StdResult<Binary>
+where
+    S: cosmwasm_vm::Storage + 'static,
+    A: cosmwasm_vm::Api + 'static,
+    Q: Querier + 'static,
+{
+    let gas_before = instance.get_gas_left();
+    let result = call_query_raw(instance, &msg).expect("query trapped");
+    check_gas(call, gas_before - instance.get_gas_left());
+    from_slice(&result).unwrap()
+}
+
+/// Executes the messages the auction sent to the oracle on the oracle instance
+fn forward_to_oracle(oracle: &Rc<RefCell<OracleInstance>>, call: &str, messages: &[CosmosMsg]) {
+    for message in messages {
+        if let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) = message
+        {
+            if contract_addr.as_str() == "oracle" {
+                let mut oracle = oracle.borrow_mut();
+                handle(&mut oracle, call, env("auction", "oracle"), msg.0.clone()).unwrap();
+            }
+        }
+    }
+}
+
+/// Fails unless the handle was rejected with the ContractError code
+fn assert_code(result: StdResult<HandleResponse>, code: u16) {
+    match result {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert!(msg.contains(&format!(r#""code":{}"#, code)), "{}", msg)
+        }
+        other => panic!("expected error code {}, got {:?}", code, other.map(|_| ())),
+    }
+}
+
+fn oracle_instance() -> Rc<RefCell<OracleInstance>> {
+    let deps = Extern {
+        storage: MockStorage::default(),
+        api: MockApi::new(20),
+        querier: MockQuerier::new(&[]),
+    };
+    let mut oracle = Instance::from_code(&wasm(ORACLE_WASM), deps, GAS_LIMIT).unwrap();
+    let init_msg = OracleInitMsg {
+        // the oracle's MockQuerier can not ask the auction for its code, so it is a factory
+        factories: Some(vec![HumanAddr("auction".to_string())]),
+        ..OracleInitMsg::default()
+    };
+    init(&mut oracle, "oracle init", env("owner", "oracle"), to_vec(&init_msg).unwrap()).unwrap();
+    let add_history = OracleHandleMsg::AddHistory {
+        user: HumanAddr("seller".to_string()),
+        history: sample_history(),
+    };
+    handle(
+        &mut oracle,
+        "oracle add_history",
+        env("owner", "oracle"),
+        to_vec(&add_history).unwrap(),
+    )
+    .unwrap();
+    Rc::new(RefCell::new(oracle))
+}
+
+fn receive(sender: &str, amount: u128, msg: Option<Binary>) -> Vec<u8> {
+    to_vec(&HandleMsg::Receive {
+        sender: HumanAddr(sender.to_string()),
+        from: HumanAddr(sender.to_string()),
+        amount: Uint128(amount),
+        msg,
+    })
+    .unwrap()
+}
+
+#[test]
+fn oracle_surface() {
+    let oracle = oracle_instance();
+    let mut oracle = oracle.borrow_mut();
+
+    let get_history = OracleQueryMsg::GetHistory {
+        user: HumanAddr("seller".to_string()),
+    };
+    let answer = query(&mut oracle, "oracle get_history", to_vec(&get_history).unwrap()).unwrap();
+    let response: OracleQueryResponse = from_slice(answer.as_slice()).unwrap();
+    assert_eq!(response.history, Some(sample_history()));
+
+    let set_reporters = OracleHandleMsg::SetReporters {
+        reporters: vec![HumanAddr("reporter".to_string())],
+    };
+    let set_reporters = to_vec(&set_reporters).unwrap();
+    handle(&mut oracle, "oracle set_reporters", env("owner", "oracle"), set_reporters).unwrap();
+    let entries = ["borrower1", "borrower2", "borrower1"]
+        .iter()
+        .map(|user| (HumanAddr(user.to_string()), sample_history()))
+        .collect();
+    let add_histories = to_vec(&OracleHandleMsg::AddHistories { entries }).unwrap();
+    let response = handle(
+        &mut oracle,
+        "oracle add_histories",
+        env("reporter", "oracle"),
+        add_histories,
+    )
+    .unwrap();
+    let OracleHandleAnswer::AddHistories { added, rejected } =
+        from_slice(response.data.unwrap().as_slice()).unwrap();
+    assert_eq!(added, 2);
+    assert_eq!(rejected[0].index, 2);
+
+    let set_callers = OracleHandleMsg::SetExposureCallers {
+        auction_code_hash: None,
+        factories: vec![HumanAddr("auction".to_string()), HumanAddr("factory".to_string())],
+    };
+    let set_callers = to_vec(&set_callers).unwrap();
+    handle(&mut oracle, "oracle set_exposure_callers", env("owner", "oracle"), set_callers)
+        .unwrap();
+
+    let open = OracleHandleMsg::OpenExposure {
+        borrower: HumanAddr("seller".to_string()),
+        amount: Uint128(1000),
+        payment: Uint128(1200000),
+        auction_id: None,
+    };
+    let env_auction = || env("auction", "oracle");
+    handle(&mut oracle, "oracle open_exposure", env_auction(), to_vec(&open).unwrap()).unwrap();
+    // the same auction can only record its exposure once
+    assert!(
+        handle(&mut oracle, "oracle open_exposure", env_auction(), to_vec(&open).unwrap()).is_err()
+    );
+    let settle = OracleHandleMsg::SettleExposure {
+        borrower: HumanAddr("seller".to_string()),
+        outstanding: false,
+        auction_id: None,
+    };
+    handle(&mut oracle, "oracle settle_exposure", env_auction(), to_vec(&settle).unwrap()).unwrap();
+    let migrate = to_vec(&OracleHandleMsg::Migrate {}).unwrap();
+    handle(&mut oracle, "oracle migrate", env("owner", "oracle"), migrate).unwrap();
+
+    let get_exposure = OracleQueryMsg::GetExposure {
+        user: HumanAddr("seller".to_string()),
+    };
+    let answer = query(&mut oracle, "oracle get_exposure", to_vec(&get_exposure).unwrap()).unwrap();
+    let exposure: ExposureResponse = from_slice(answer.as_slice()).unwrap();
+    assert_eq!(exposure.open_auctions, 0);
+    assert_eq!(exposure.total, Uint128(0));
+}
+
+#[test]
+fn auction_surface() {
+    let oracle = oracle_instance();
+    let deps = Extern {
+        storage: MockStorage::default(),
+        api: MockApi::new(20),
+        querier: ContractsQuerier {
+            oracle: oracle.clone(),
+        },
+    };
+    let mut auction = Instance::from_code(&wasm(AUCTION_WASM), deps, GAS_LIMIT).unwrap();
+
+    let init_msg = InitMsg {
+        description: Some("integration".to_string()),
+        ..auction_init_msg()
+    };
+    let response = init(
+        &mut auction,
+        "auction init",
+        env("seller", "auction"),
+        to_vec(&init_msg).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(response.messages.len(), 3);
+    forward_to_oracle(&oracle, "oracle open_exposure", &response.messages);
+
+    let consign = Some(to_binary(&ReceiveMsg::Consign {}).unwrap());
+    handle(
+        &mut auction,
+        "auction consign",
+        env("selltoken", "auction"),
+        receive("seller", 1666, consign),
+    )
+    .unwrap();
+    for (lender, amount) in [("lender1", 1100000), ("lender2", 1050000)].iter() {
+        handle(
+            &mut auction,
+            "auction bid",
+            env("bidtoken", "auction"),
+            receive(lender, *amount, None),
+        )
+        .unwrap();
+    }
+    let view_bid = to_vec(&HandleMsg::ViewBid {}).unwrap();
+    handle(&mut auction, "auction view_bid", env("lender1", "auction"), view_bid).unwrap();
+
+    // rejected calls return the contract error instead of trapping
+    let result = handle(
+        &mut auction,
+        "auction rejected",
+        env("bidtoken", "auction"),
+        receive("lender3", 0, None),
+    );
+    assert_code(result, 401);
+    let invalid = Some(Binary(b"{\"unknown\":{}}".to_vec()));
+    let result = handle(
+        &mut auction,
+        "auction rejected",
+        env("bidtoken", "auction"),
+        receive("lender3", 1000, invalid),
+    );
+    assert_code(result, 201);
+    let result = handle(
+        &mut auction,
+        "auction rejected",
+        env("seller", "auction"),
+        b"{\"not_a_message\":{}}".to_vec(),
+    );
+    assert!(result.is_err());
+
+    let answer = query(&mut auction, "auction bid_count", to_vec(&QueryMsg::BidCount {}).unwrap());
+    match from_slice(answer.unwrap().as_slice()).unwrap() {
+        QueryAnswer::BidCount { count } => assert_eq!(count, 2),
+        _ => panic!("Unexpected answer"),
+    }
+
+    let finalize = to_vec(&HandleMsg::Finalize { only_if_bids: true }).unwrap();
+    let response = handle(&mut auction, "auction finalize", env("seller", "auction"), finalize)
+        .unwrap();
+    // two transfers of the sale, one refund and the exposure settlement
+    assert_eq!(response.messages.len(), 4);
+    forward_to_oracle(&oracle, "oracle settle_exposure", &response.messages);
+
+    let return_all = to_vec(&HandleMsg::ReturnAll {}).unwrap();
+    let response = handle(&mut auction, "auction return_all", env("lender1", "auction"), return_all)
+        .unwrap();
+    assert!(response.messages.is_empty());
+
+    let answer = query(
+        &mut auction,
+        "auction auction_info",
+        to_vec(&QueryMsg::AuctionInfo {}).unwrap(),
+    );
+    match from_slice(answer.unwrap().as_slice()).unwrap() {
+        QueryAnswer::AuctionInfo {
+            status,
+            winning_bid,
+            sell_token,
+            ..
+        } => {
+            assert_eq!(status, "Closed");
+            assert_eq!(winning_bid, Some(Uint128(1050000)));
+            assert_eq!(sell_token.token_info.symbol, "SELL");
+        }
+        _ => panic!("Unexpected answer"),
+    }
+
+    let get_exposure = OracleQueryMsg::GetExposure {
+        user: HumanAddr("seller".to_string()),
+    };
+    let mut oracle = oracle.borrow_mut();
+    let answer = query(&mut oracle, "oracle get_exposure", to_vec(&get_exposure).unwrap()).unwrap();
+    let exposure: ExposureResponse = from_slice(answer.as_slice()).unwrap();
+    assert_eq!(exposure.outstanding_loans, 1);
+}
+
+/// Returns an auction instance created with the InitMsg, whose exposure the oracle has opened
+fn auction_instance(oracle: &Rc<RefCell<OracleInstance>>, init_msg: &InitMsg) -> AuctionInstance {
+    let deps = Extern {
+        storage: MockStorage::default(),
+        api: MockApi::new(20),
+        querier: ContractsQuerier {
+            oracle: oracle.clone(),
+        },
+    };
+    let mut auction = Instance::from_code(&wasm(AUCTION_WASM), deps, GAS_LIMIT).unwrap();
+    let response = init(
+        &mut auction,
+        "auction init",
+        env("seller", "auction"),
+        to_vec(init_msg).unwrap(),
+    )
+    .unwrap();
+    forward_to_oracle(oracle, "oracle open_exposure", &response.messages);
+    let consign = Some(to_binary(&ReceiveMsg::Consign {}).unwrap());
+    handle(
+        &mut auction,
+        "auction consign",
+        env("selltoken", "auction"),
+        receive("seller", 1666, consign),
+    )
+    .unwrap();
+    auction
+}
+
+/// Returns the AuctionInfo answer of the auction
+fn auction_info(auction: &mut AuctionInstance) -> QueryAnswer {
+    let answer = query(
+        auction,
+        "auction auction_info",
+        to_vec(&QueryMsg::AuctionInfo {}).unwrap(),
+    );
+    from_slice(answer.unwrap().as_slice()).unwrap()
+}
+
+#[test]
+fn auction_keys_and_updates() {
+    let oracle = oracle_instance();
+    let mut auction = auction_instance(&oracle, &auction_init_msg());
+
+    let update = to_vec(&HandleMsg::UpdateAuction {
+        description: Some("updated".to_string()),
+        max_bid: None,
+        ends_at: None,
+    })
+    .unwrap();
+    handle(&mut auction, "auction update", env("seller", "auction"), update).unwrap();
+    for who in ["seller", "lender1"].iter() {
+        let set_key = to_vec(&HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        })
+        .unwrap();
+        handle(&mut auction, "auction set_viewing_key", env(who, "auction"), set_key).unwrap();
+    }
+    handle(
+        &mut auction,
+        "auction bid",
+        env("bidtoken", "auction"),
+        receive("lender1", 1100000, None),
+    )
+    .unwrap();
+
+    let my_bid = QueryMsg::MyBid {
+        address: HumanAddr("lender1".to_string()),
+        key: "key".to_string(),
+    };
+    let answer = query(&mut auction, "auction my_bid", to_vec(&my_bid).unwrap()).unwrap();
+    match from_slice(answer.as_slice()).unwrap() {
+        QueryAnswer::MyBid { bid: Some(bid) } => assert_eq!(bid.amount, Uint128(1100000)),
+        _ => panic!("Expected the lender's bid"),
+    }
+    let list_bids = QueryMsg::ListBids {
+        address: HumanAddr("seller".to_string()),
+        key: "key".to_string(),
+        page: None,
+        page_size: 10,
+    };
+    let answer = query(&mut auction, "auction list_bids", to_vec(&list_bids).unwrap()).unwrap();
+    match from_slice(answer.as_slice()).unwrap() {
+        QueryAnswer::ListBids { count, .. } => assert_eq!(count, 1),
+        _ => panic!("Expected a list of bids"),
+    }
+
+    // the signature was made with the key over other bytes, so the permit is validated in full
+    // and then refused
+    let with_permit = br#"{"with_permit":{"permit":{"params":{"allowed_tokens":["auction"],
+        "permit_name":"integration","chain_id":"secret-4","permissions":["owner"]},
+        "signature":{"pub_key":{"type":"tendermint/PubKeySecp256k1",
+        "value":"AoII9avwQGa60dudRvi89abMEdBVirUj5708DsCL23gv"},
+        "signature":"RRHLjPB4qQMISAwjcu0n421+GoYv6fMCck4Kh3J0lFwDWlNjpfn5EN3oNgbAF96i+ZwaXz6WaZlBs+aL1KcwNA=="}},
+        "query":{"my_bid":{}}}}"#;
+    let answer = query(&mut auction, "auction permit_my_bid", with_permit.to_vec());
+    assert!(answer.is_err());
+
+    match auction_info(&mut auction) {
+        QueryAnswer::AuctionInfo { description, .. } => {
+            assert_eq!(description, Some("updated".to_string()))
+        }
+        _ => panic!("Unexpected answer"),
+    }
+}
+
+#[test]
+fn auction_cancel() {
+    let oracle = oracle_instance();
+    let mut auction = auction_instance(&oracle, &auction_init_msg());
+
+    let cancel = to_vec(&HandleMsg::CancelAuction {}).unwrap();
+    let response = handle(&mut auction, "auction cancel", env("seller", "auction"), cancel)
+        .unwrap();
+    forward_to_oracle(&oracle, "oracle settle_exposure", &response.messages);
+    match auction_info(&mut auction) {
+        QueryAnswer::AuctionInfo { status, .. } => assert_eq!(status, "Cancelled"),
+        _ => panic!("Unexpected answer"),
+    }
+
+    let get_exposure = OracleQueryMsg::GetExposure {
+        user: HumanAddr("seller".to_string()),
+    };
+    let mut oracle = oracle.borrow_mut();
+    let answer = query(&mut oracle, "oracle get_exposure", to_vec(&get_exposure).unwrap()).unwrap();
+    let exposure: ExposureResponse = from_slice(answer.as_slice()).unwrap();
+    assert_eq!(exposure.open_auctions, 0);
+}
+
+#[test]
+fn dutch_auction() {
+    let oracle = oracle_instance();
+    let init_msg = InitMsg {
+        kind: Some(AuctionKind::Dutch {
+            step: Uint128(1000),
+            interval: 60,
+            floor: Uint128(1000000),
+        }),
+        ..auction_init_msg()
+    };
+    let mut auction = auction_instance(&oracle, &init_msg);
+
+    // the auction has just started, so the current rate is the average bid
+    let rate = match auction_info(&mut auction) {
+        QueryAnswer::AuctionInfo { average_bid, .. } => average_bid,
+        _ => panic!("Unexpected answer"),
+    };
+    let response = handle(
+        &mut auction,
+        "auction accept_rate",
+        env("bidtoken", "auction"),
+        receive("lender1", rate.u128(), None),
+    )
+    .unwrap();
+    forward_to_oracle(&oracle, "oracle settle_exposure", &response.messages);
+    match auction_info(&mut auction) {
+        QueryAnswer::AuctionInfo {
+            status,
+            winning_bid,
+            ..
+        } => {
+            assert_eq!(status, "Closed");
+            assert_eq!(winning_bid, Some(rate));
+        }
+        _ => panic!("Unexpected answer"),
+    }
+}
+
+/// Returns the names of the float instructions and signatures in the Wasm, which would make the
+/// contract non-deterministic and be rejected on upload
+fn float_uses(wasm: &[u8]) -> Vec<String> {
+    let module: Module = parity_wasm::deserialize_buffer(wasm).unwrap();
+    let mut uses = Vec::new();
+    let is_float = |value: &ValueType| *value == ValueType::F32 || *value == ValueType::F64;
+    if let Some(types) = module.type_section() {
+        for Type::Function(function) in types.types() {
+            if function.params().iter().any(is_float) || function.return_type().iter().any(is_float)
+            {
+                uses.push(format!("signature {:?}", function));
+            }
+        }
+    }
+    if let Some(code) = module.code_section() {
+        for body in code.bodies() {
+            for instruction in body.code().elements() {
+                let name = format!("{:?}", instruction);
+                if name.starts_with("F32") || name.starts_with("F64") {
+                    uses.push(name);
+                } else if let Instruction::I32TruncSF32
+                | Instruction::I32TruncUF32
+                | Instruction::I32TruncSF64
+                | Instruction::I32TruncUF64
+                | Instruction::I64TruncSF32
+                | Instruction::I64TruncUF32
+                | Instruction::I64TruncSF64
+                | Instruction::I64TruncUF64
+                | Instruction::I32ReinterpretF32
+                | Instruction::I64ReinterpretF64 = instruction
+                {
+                    uses.push(name);
+                }
+            }
+        }
+    }
+    uses
+}
+
+#[test]
+fn contracts_have_no_floats() {
+    for path in [AUCTION_WASM, ORACLE_WASM].iter() {
+        let uses = float_uses(&wasm(path));
+        assert!(uses.is_empty(), "{} uses floats: {:?}", path, uses);
+    }
+}
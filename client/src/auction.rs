@@ -0,0 +1,405 @@
+use cosmwasm_std::{Binary, HumanAddr, StdError, StdResult, Uint128};
+use secret_toolkit::permit::Permit;
+
+use diploma_master::msg::{
+    ContractInfo, FeeConfig, HandleMsg, InitMsg, QueryMsg, QueryWithPermit, ReceiveMsg,
+    MAX_NOTE_LEN,
+};
+
+use crate::snip20::{send, Snip20HandleMsg};
+use crate::tx::Call;
+
+/// optional terms a lender can attach to a bid
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BidTerms {
+    /// Optional longest loan term (in months) the lender is willing to offer
+    pub max_term: Option<u64>,
+    /// Optional grace period (in months) before the first repayment is due
+    pub grace_period: Option<u64>,
+    /// Optional penalty (in basis points) for repaying the loan early
+    pub prepayment_penalty: Option<u64>,
+    /// Optional note to the borrower
+    pub note: Option<String>,
+}
+
+/// builds the messages of one auction contract
+#[derive(Clone, Debug)]
+pub struct AuctionClient {
+    /// address of the auction
+    pub auction: HumanAddr,
+    /// address of the token being sold
+    pub sell_token: HumanAddr,
+    /// address of the token bids are placed in
+    pub bid_token: HumanAddr,
+}
+
+impl AuctionClient {
+    /// Returns a client of an instantiated auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction` - address of the auction
+    /// * `sell_token` - address of the token being sold
+    /// * `bid_token` - address of the token bids are placed in
+    pub fn new(auction: HumanAddr, sell_token: HumanAddr, bid_token: HumanAddr) -> Self {
+        AuctionClient {
+            auction,
+            sell_token,
+            bid_token,
+        }
+    }
+
+    /// Returns the InitMsg of an auction with the default preferences, kind and no fee.  The
+    /// optional fields can be set on the returned message
+    ///
+    /// # Arguments
+    ///
+    /// * `sell_contract` - code hash and address of the token being sold
+    /// * `bid_contract` - code hash and address of the token bids are placed in
+    /// * `oracle_contract` - code hash and address of the oracle scoring the borrower
+    /// * `expected` - expected sum of credit
+    /// * `payment` - alleged sum to pay
+    /// * `prng_seed` - secret seed the auction generates viewing keys with
+    pub fn init_msg(
+        sell_contract: ContractInfo,
+        bid_contract: ContractInfo,
+        oracle_contract: ContractInfo,
+        expected: Uint128,
+        payment: Uint128,
+        prng_seed: Binary,
+    ) -> InitMsg {
+        InitMsg {
+            sell_contract,
+            bid_contract,
+            expected,
+            payment,
+            oracle_contract,
+            description: None,
+            preferences: None,
+            kind: None,
+            prng_seed,
+            admin: None,
+            fee: None,
+            lender_registry: None,
+        }
+    }
+
+    /// Returns the Send of sell tokens that consigns them to the auction
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - amount of sell tokens
+    pub fn consign(&self, amount: Uint128) -> StdResult<Call<Snip20HandleMsg>> {
+        send(
+            &self.sell_token,
+            &self.auction,
+            amount,
+            &ReceiveMsg::Consign {},
+        )
+    }
+
+    /// Returns the Send of bid tokens that places a bid.  A note the auction would reject is an
+    /// error, so the tokens are not sent for nothing
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - amount of the bid
+    /// * `terms` - loan terms offered with the bid
+    pub fn bid(&self, amount: Uint128, terms: BidTerms) -> StdResult<Call<Snip20HandleMsg>> {
+        if terms.note.as_ref().map_or(false, |note| note.len() > MAX_NOTE_LEN) {
+            return Err(StdError::generic_err(format!(
+                "Bid note can not be longer than {} bytes",
+                MAX_NOTE_LEN
+            )));
+        }
+        let bid = ReceiveMsg::Bid {
+            max_term: terms.max_term,
+            grace_period: terms.grace_period,
+            prepayment_penalty: terms.prepayment_penalty,
+            note: terms.note,
+        };
+        send(&self.bid_token, &self.auction, amount, &bid)
+    }
+
+    /// Returns the Call that closes the auction
+    ///
+    /// # Arguments
+    ///
+    /// * `only_if_bids` - true to keep the auction open if there are no active bids
+    pub fn finalize(&self, only_if_bids: bool) -> Call<HandleMsg> {
+        self.call(HandleMsg::Finalize { only_if_bids })
+    }
+
+    /// Returns the Call that returns the funds a closed auction still holds
+    pub fn return_all(&self) -> Call<HandleMsg> {
+        self.call(HandleMsg::ReturnAll {})
+    }
+
+    /// Returns the Call that displays the sender's active bid
+    pub fn view_bid(&self) -> Call<HandleMsg> {
+        self.call(HandleMsg::ViewBid {})
+    }
+
+    /// Returns the Call that cancels the auction before the first bid
+    pub fn cancel(&self) -> Call<HandleMsg> {
+        self.call(HandleMsg::CancelAuction {})
+    }
+
+    /// Returns the Call that amends the auction before the first bid
+    ///
+    /// # Arguments
+    ///
+    /// * `description` - Optional new description of the auction
+    /// * `max_bid` - Optional new average bid that will be accepted
+    /// * `ends_at` - Optional new time after which bids are no longer accepted
+    pub fn update_auction(
+        &self,
+        description: Option<String>,
+        max_bid: Option<Uint128>,
+        ends_at: Option<u64>,
+    ) -> Call<HandleMsg> {
+        self.call(HandleMsg::UpdateAuction {
+            description,
+            max_bid,
+            ends_at,
+        })
+    }
+
+    /// Returns the Call of the admin that sets the platform fee before the first bid
+    ///
+    /// # Arguments
+    ///
+    /// * `fee` - Optional new fee configuration, None removes the fee
+    pub fn set_fee(&self, fee: Option<FeeConfig>) -> Call<HandleMsg> {
+        self.call(HandleMsg::SetFee { fee })
+    }
+
+    /// Returns the Call of the admin that stops new bids and consignments
+    pub fn pause(&self) -> Call<HandleMsg> {
+        self.call(HandleMsg::Pause {})
+    }
+
+    /// Returns the Call of the admin that accepts bids and consignments again
+    pub fn unpause(&self) -> Call<HandleMsg> {
+        self.call(HandleMsg::Unpause {})
+    }
+
+    /// Returns the Call of the admin that closes the auction and returns every fund it holds
+    pub fn emergency_unwind(&self) -> Call<HandleMsg> {
+        self.call(HandleMsg::EmergencyUnwind {})
+    }
+
+    /// Returns the Call that creates a viewing key for the sender
+    ///
+    /// # Arguments
+    ///
+    /// * `entropy` - string used as entropy when generating the key
+    pub fn create_viewing_key(&self, entropy: String) -> Call<HandleMsg> {
+        self.call(HandleMsg::CreateViewingKey {
+            entropy,
+            padding: None,
+        })
+    }
+
+    /// Returns the Call that sets the sender's viewing key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the viewing key
+    pub fn set_viewing_key(&self, key: String) -> Call<HandleMsg> {
+        self.call(HandleMsg::SetViewingKey { key, padding: None })
+    }
+
+    /// Returns the Call that revokes one of the sender's permits
+    ///
+    /// # Arguments
+    ///
+    /// * `permit_name` - name of the permit
+    pub fn revoke_permit(&self, permit_name: String) -> Call<HandleMsg> {
+        self.call(HandleMsg::RevokePermit {
+            permit_name,
+            padding: None,
+        })
+    }
+
+    /// Returns the query of the auction information
+    pub fn auction_info(&self) -> QueryMsg {
+        QueryMsg::AuctionInfo {}
+    }
+
+    /// Returns the query of the number of active bids
+    pub fn bid_count(&self) -> QueryMsg {
+        QueryMsg::BidCount {}
+    }
+
+    /// Returns the query of a bidder's active bid
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the bidder
+    /// * `key` - viewing key of the bidder
+    pub fn my_bid(&self, address: HumanAddr, key: String) -> QueryMsg {
+        QueryMsg::MyBid { address, key }
+    }
+
+    /// Returns the query of the score of a bidder's active bid
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the bidder
+    /// * `key` - viewing key of the bidder
+    pub fn bid_score(&self, address: HumanAddr, key: String) -> QueryMsg {
+        QueryMsg::BidScore { address, key }
+    }
+
+    /// Returns the query of the bids, which only the seller can list
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the seller
+    /// * `key` - viewing key of the seller
+    /// * `page` - Optional page to display, starting at 0
+    /// * `page_size` - number of bids per page
+    pub fn list_bids(
+        &self,
+        address: HumanAddr,
+        key: String,
+        page: Option<u32>,
+        page_size: u32,
+    ) -> QueryMsg {
+        QueryMsg::ListBids {
+            address,
+            key,
+            page,
+            page_size,
+        }
+    }
+
+    /// Returns the query of the active bid of the permit's signer
+    ///
+    /// # Arguments
+    ///
+    /// * `permit` - permit signed by the bidder
+    pub fn my_bid_with_permit(&self, permit: Permit) -> QueryMsg {
+        QueryMsg::WithPermit {
+            permit,
+            query: QueryWithPermit::MyBid {},
+        }
+    }
+
+    /// Returns a Call of a message to the auction
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - the message
+    fn call(&self, msg: HandleMsg) -> Call<HandleMsg> {
+        Call {
+            contract: self.auction.clone(),
+            msg,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::from_slice;
+    use diploma_master::msg::QueryAnswer;
+
+    use crate::tx::{decode_query_answer, to_json};
+
+    fn client() -> AuctionClient {
+        AuctionClient::new(
+            HumanAddr("auction".to_string()),
+            HumanAddr("selltoken".to_string()),
+            HumanAddr("bidtoken".to_string()),
+        )
+    }
+
+    #[test]
+    fn sends_carry_base64_receive_msgs() {
+        let consign = client().consign(Uint128(1666)).unwrap();
+        assert_eq!(consign.contract, HumanAddr("selltoken".to_string()));
+        assert_eq!(
+            consign.to_json().unwrap(),
+            r#"{"send":{"recipient":"auction","amount":"1666","msg":"eyJjb25zaWduIjp7fX0="}}"#
+        );
+
+        let bid = client().bid(Uint128(1050000), BidTerms::default()).unwrap();
+        assert_eq!(bid.contract, HumanAddr("bidtoken".to_string()));
+        assert_eq!(
+            bid.to_json().unwrap(),
+            r#"{"send":{"recipient":"auction","amount":"1050000","msg":"eyJiaWQiOnt9fQ=="}}"#
+        );
+        let terms = BidTerms {
+            max_term: Some(12),
+            ..BidTerms::default()
+        };
+        match client().bid(Uint128(1050000), terms).unwrap().msg {
+            Snip20HandleMsg::Send { msg, .. } => {
+                let receive: ReceiveMsg = from_slice(msg.unwrap().as_slice()).unwrap();
+                assert_eq!(
+                    receive,
+                    ReceiveMsg::Bid {
+                        max_term: Some(12),
+                        grace_period: None,
+                        prepayment_penalty: None,
+                        note: None,
+                    }
+                );
+            }
+        }
+
+        let terms = |note: String| BidTerms {
+            note: Some(note),
+            ..BidTerms::default()
+        };
+        assert!(client().bid(Uint128(1050000), terms("n".repeat(MAX_NOTE_LEN))).is_ok());
+        let too_long = client().bid(Uint128(1050000), terms("n".repeat(MAX_NOTE_LEN + 1)));
+        assert!(too_long.unwrap_err().to_string().contains("can not be longer"));
+    }
+
+    #[test]
+    fn builds_handles_and_decodes_queries() {
+        let finalize = client().finalize(true);
+        assert_eq!(finalize.contract, HumanAddr("auction".to_string()));
+        assert_eq!(
+            finalize.to_json().unwrap(),
+            r#"{"finalize":{"only_if_bids":true}}"#
+        );
+
+        match decode_query_answer(br#"{"bid_count":{"count":2}}"#).unwrap() {
+            QueryAnswer::BidCount { count } => assert_eq!(count, 2),
+            _ => panic!("Unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn builds_admin_handles_and_keyed_queries() {
+        let handles = vec![
+            (client().pause(), r#"{"pause":{}}"#),
+            (client().unpause(), r#"{"unpause":{}}"#),
+            (client().emergency_unwind(), r#"{"emergency_unwind":{}}"#),
+            (client().set_fee(None), r#"{"set_fee":{}}"#),
+            (
+                client().update_auction(None, Some(Uint128(1100000)), None),
+                r#"{"update_auction":{"max_bid":"1100000"}}"#,
+            ),
+            (
+                client().create_viewing_key("entropy".to_string()),
+                r#"{"create_viewing_key":{"entropy":"entropy"}}"#,
+            ),
+        ];
+        for (call, json) in handles {
+            assert_eq!(call.contract, HumanAddr("auction".to_string()));
+            assert_eq!(call.to_json().unwrap(), json);
+        }
+
+        let seller = HumanAddr("seller".to_string());
+        let list_bids = client().list_bids(seller, "key".to_string(), None, 10);
+        assert_eq!(
+            to_json(&list_bids).unwrap(),
+            r#"{"list_bids":{"address":"seller","key":"key","page_size":10}}"#
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Command line client of the auction and oracle contracts, replacing `auction.sh` and
+//! `oracle.sh`.  Queries and transactions go through `secretcli`, but the messages are built
+//! from the contracts' own types and every argument is validated before anything is sent.
+//!
+//! With `--dry-run` the messages are printed instead of broadcast.  Queries needed to build
+//! them, such as the decimals of a token, are still made.  With `--json` every result is printed
+//! as one line of JSON for scripting.
+
+use std::env;
+use std::process;
+
+use serde_json::json;
+
+use client::cli::{context, message, output, run, Args, USAGE};
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", message(&e), USAGE);
+            process::exit(2);
+        }
+    };
+    if args.flag("help") || args.words.len() != 2 {
+        println!("{}", USAGE);
+        process::exit(if args.flag("help") { 0 } else { 2 });
+    }
+    let result = context(&args).and_then(|ctx| run(&ctx, &args));
+
+    let json = args.flag("json");
+    match result {
+        Ok((value, text)) => println!("{}", output(&value, text, json)),
+        Err(e) => {
+            if json {
+                println!("{}", json!({ "error": message(&e) }));
+            } else {
+                eprintln!("{}", message(&e));
+            }
+            process::exit(1);
+        }
+    }
+}
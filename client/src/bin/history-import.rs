@@ -0,0 +1,130 @@
+//! Imports credit histories from a CSV or JSON file, validates them offline, and prints the
+//! AddHistories messages that add them to the oracle, ready to sign.
+//!
+//! Usage: `history-import [--json] --oracle <address> [--payment <amount>] [--batch-size <n>]
+//! <records.csv or histories.json>`.  A CSV file has the header
+//! `user,debts,sum,interest_rate,time,is_closed` and a row for each credit.  A JSON file maps
+//! user addresses to histories.  With `--payment`, in base units of the bid token, the score
+//! each history would get in an auction with that payment is shown.  The validation report goes
+//! to stderr and the batches to stdout, unless `--json` prints both as one JSON object.  Exits
+//! with 1, and prints no batches, if any history is invalid.
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use serde_json::json;
+
+use cosmwasm_std::{HumanAddr, Uint128};
+
+use client::import::{batches, check, read_file};
+use client::oracle::OracleClient;
+use oracle::msg::MAX_HISTORIES_PER_BATCH;
+
+static USAGE: &str = "Usage: history-import [--json] --oracle <address> [--payment <amount>] \
+[--batch-size <n>] <records.csv or histories.json>";
+
+/// histories in an AddHistories if --batch-size is not given
+const DEFAULT_BATCH_SIZE: usize = MAX_HISTORIES_PER_BATCH;
+
+/// Prints the error and the usage, and exits with 2
+///
+/// # Arguments
+///
+/// * `error` - what is wrong with the command line
+fn usage_error(error: &str) -> ! {
+    eprintln!("{}\n\n{}", error, USAGE);
+    process::exit(2);
+}
+
+fn main() {
+    let mut json = false;
+    let mut oracle = None;
+    let mut payment = None;
+    let mut batch_size = DEFAULT_BATCH_SIZE;
+    let mut file = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| usage_error(&format!("{} needs a value", name)))
+        };
+        match arg.as_str() {
+            "--json" => json = true,
+            "--oracle" => oracle = Some(HumanAddr(value("--oracle"))),
+            "--payment" => {
+                let amount = value("--payment");
+                match amount.parse::<u128>() {
+                    Ok(amount) if amount > 0 => payment = Some(Uint128(amount)),
+                    _ => usage_error(&format!(
+                        "--payment must be a positive whole number, not \"{}\"",
+                        amount
+                    )),
+                }
+            }
+            "--batch-size" => {
+                let size = value("--batch-size");
+                match size.parse::<usize>() {
+                    Ok(size) if size > 0 && size <= MAX_HISTORIES_PER_BATCH => batch_size = size,
+                    _ => usage_error(&format!(
+                        "--batch-size must be between 1 and {}, not \"{}\"",
+                        MAX_HISTORIES_PER_BATCH, size
+                    )),
+                }
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown option {}", arg)),
+            _ if file.is_none() => file = Some(PathBuf::from(&arg)),
+            _ => usage_error("Only one file can be imported at a time"),
+        }
+    }
+    let oracle = oracle.unwrap_or_else(|| usage_error("--oracle is required"));
+    let file = file.unwrap_or_else(|| usage_error("A file to import is required"));
+
+    let histories = match read_file(&file) {
+        Ok(histories) => histories,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let reports = check(&histories, payment);
+    let valid = reports.iter().all(|report| report.problems.is_empty());
+
+    if !json {
+        for report in &reports {
+            let score = match (payment, report.score) {
+                (None, _) => String::new(),
+                (Some(_), Some(score)) => format!(", score {}", score),
+                (Some(_), None) if report.problems.is_empty() => {
+                    ", score too low to open an auction".to_string()
+                }
+                (Some(_), None) => String::new(),
+            };
+            let status = if report.problems.is_empty() { "OK  " } else { "FAIL" };
+            eprintln!("{} {}: {} credits{}", status, report.user, report.credits, score);
+            for problem in &report.problems {
+                eprintln!("     {}", problem);
+            }
+        }
+        let invalid = reports.iter().filter(|r| !r.problems.is_empty()).count();
+        eprintln!("\n{} histories, {} invalid", reports.len(), invalid);
+    }
+
+    let batches = if valid {
+        batches(&OracleClient::new(oracle), histories, batch_size)
+    } else {
+        Vec::new()
+    };
+    if json {
+        println!("{}", json!({ "reports": reports, "batches": batches }));
+    } else if valid {
+        println!("{}", serde_json::to_string_pretty(&batches).unwrap());
+    }
+    if !valid {
+        process::exit(1);
+    }
+}
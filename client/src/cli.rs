@@ -0,0 +1,841 @@
+//! Argument parsing and dispatch of the `credit-auction` command line client.  The commands
+//! reach the network through a Chain, which is `secretcli` outside of the tests.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use cosmwasm_std::{Binary, HumanAddr, StdError, StdResult};
+
+use crate::auction::{AuctionClient, BidTerms};
+use crate::denom::{from_base_units, to_base_units};
+use crate::import::read_file;
+use crate::oracle::OracleClient;
+use crate::secretcli::{ContractListing, SecretCli};
+use crate::snip20::{Snip20QueryAnswer, Snip20QueryMsg};
+use crate::tx::Call;
+use diploma_master::msg::{ContractInfo, QueryAnswer, QueryMsg};
+use oracle::msg::{History, QueryResponse};
+
+pub static USAGE: &str = "Usage: credit-auction [--json] [--dry-run] [--from <key>] [--gas <limit>] \
+[--secretcli <path>] <command>
+
+Commands:
+  oracle init --code-id <id> [--label <label>] [--user <address> --file <history.json>]
+              [--histories <records.csv or histories.json>] [--reporters <address,...>]
+              [--auction-code-hash <hash>] [--factories <address,...>]
+  oracle set-reporters --oracle <address> [--reporters <address,...>]
+  oracle set-exposure-callers --oracle <address> [--auction-code-hash <hash>]
+                              [--factories <address,...>]
+  oracle add-history --oracle <address> --user <address> --file <history.json>
+  oracle history --oracle <address> --user <address>
+  auction create --code-id <id> --label <label> --sell-token <address> --bid-token <address>
+                 --oracle <address> --expected <amount> --payment <amount> [--description <text>]
+  auction list --code-id <id>
+  auction info --auction <address>
+  auction consign --auction <address> --amount <amount>
+  auction bid --auction <address> --amount <amount> [--max-term <months>]
+              [--grace-period <months>] [--prepayment-penalty <basis points>] [--note <text>]
+  auction finalize --auction <address> [--only-if-bids]
+
+Amounts are in whole tokens, such as 1.5, and are converted with the token's decimals.";
+
+/// options that do not take a value
+const FLAGS: &[&str] = &["json", "dry-run", "only-if-bids", "help"];
+
+/// parsed command line
+#[derive(Debug)]
+pub struct Args {
+    /// command words
+    pub words: Vec<String>,
+    /// options with their values
+    pub options: BTreeMap<String, String>,
+    /// options without values
+    pub flags: Vec<String>,
+}
+
+impl Args {
+    /// Returns the parsed command line
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - command line arguments, without the program name
+    pub fn parse(mut args: impl Iterator<Item = String>) -> StdResult<Self> {
+        let mut parsed = Args {
+            words: Vec::new(),
+            options: BTreeMap::new(),
+            flags: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                parsed.words.push(arg);
+                continue;
+            }
+            let name = arg.trim_start_matches("--").to_string();
+            if FLAGS.contains(&name.as_str()) {
+                parsed.flags.push(name);
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| StdError::generic_err(format!("--{} needs a value", name)))?;
+            if parsed.options.insert(name.clone(), value).is_some() {
+                return Err(StdError::generic_err(format!("--{} is given twice", name)));
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns true if the flag was given
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the flag
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    /// Returns the value of an option, if it was given
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the option
+    pub fn optional(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Returns the value of an option that must be given
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the option
+    pub fn required(&self, name: &str) -> StdResult<&str> {
+        self.optional(name).ok_or_else(|| required(name))
+    }
+
+    /// Returns the address given for an option
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the option
+    pub fn address(&self, name: &str) -> StdResult<HumanAddr> {
+        let address = self.required(name)?;
+        if address.is_empty() || !address.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(StdError::generic_err(format!(
+                "--{} must be an address, not \"{}\"",
+                name, address
+            )));
+        }
+        Ok(HumanAddr(address.to_string()))
+    }
+
+    /// Returns the comma separated addresses given for an option, empty if it was not given
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the option
+    pub fn addresses(&self, name: &str) -> Vec<HumanAddr> {
+        self.optional(name)
+            .map(|list| {
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|address| !address.is_empty())
+                    .map(|address| HumanAddr(address.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the number given for an option, if it was given
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the option
+    pub fn number<T: FromStr>(&self, name: &str) -> StdResult<Option<T>> {
+        self.optional(name)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    StdError::generic_err(format!(
+                        "--{} must be a whole number, not \"{}\"",
+                        name, value
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
+/// what the commands need from the network
+pub trait Chain {
+    /// Returns the answer of a contract to a query
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the contract
+    /// * `msg` - the query
+    fn query<T: Serialize, R: DeserializeOwned>(
+        &self,
+        contract: &HumanAddr,
+        msg: &T,
+    ) -> StdResult<R>;
+
+    /// Returns the code hash of a contract
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the contract
+    fn contract_hash(&self, contract: &HumanAddr) -> StdResult<String>;
+
+    /// Returns the contracts instantiated from a code id
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` - id of the uploaded code
+    fn list_contracts(&self, code_id: u64) -> StdResult<Vec<ContractListing>>;
+
+    /// Returns the address of the contract instantiated with the InitMsg
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` - id of the uploaded code
+    /// * `label` - unique label of the new contract
+    /// * `msg` - the InitMsg
+    fn instantiate<T: Serialize>(
+        &self,
+        code_id: u64,
+        label: &str,
+        msg: &T,
+    ) -> StdResult<HumanAddr>;
+
+    /// Returns the answer of the contract to an executed Call
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - the Call to execute
+    fn execute<T: Serialize, R: DeserializeOwned>(&self, call: &Call<T>) -> StdResult<R>;
+}
+
+impl Chain for SecretCli {
+    fn query<T: Serialize, R: DeserializeOwned>(
+        &self,
+        contract: &HumanAddr,
+        msg: &T,
+    ) -> StdResult<R> {
+        SecretCli::query(self, contract, msg)
+    }
+
+    fn contract_hash(&self, contract: &HumanAddr) -> StdResult<String> {
+        SecretCli::contract_hash(self, contract)
+    }
+
+    fn list_contracts(&self, code_id: u64) -> StdResult<Vec<ContractListing>> {
+        SecretCli::list_contracts(self, code_id)
+    }
+
+    fn instantiate<T: Serialize>(
+        &self,
+        code_id: u64,
+        label: &str,
+        msg: &T,
+    ) -> StdResult<HumanAddr> {
+        SecretCli::instantiate(self, code_id, label, msg)
+    }
+
+    fn execute<T: Serialize, R: DeserializeOwned>(&self, call: &Call<T>) -> StdResult<R> {
+        SecretCli::execute(self, call)
+    }
+}
+
+/// the chain and whether to broadcast
+pub struct Context<C: Chain> {
+    /// the chain queries and transactions go to
+    pub cli: C,
+    /// true to print transactions instead of broadcasting them
+    pub dry_run: bool,
+}
+
+impl<C: Chain> Context<C> {
+    /// Returns the address of the instantiated contract, or the instantiation on a dry run
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` - id of the uploaded code
+    /// * `label` - unique label of the new contract
+    /// * `msg` - the InitMsg
+    fn instantiate<T: Serialize>(&self, code_id: u64, label: &str, msg: &T) -> StdResult<Value> {
+        if self.dry_run {
+            return Ok(json!({
+                "instantiate": { "code_id": code_id, "label": label, "msg": msg }
+            }));
+        }
+        let address = self.cli.instantiate(code_id, label, msg)?;
+        Ok(json!({ "address": address }))
+    }
+
+    /// Returns the contract's answer to the Call, or the Call on a dry run
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - the Call to execute
+    fn execute<T: Serialize>(&self, call: &Call<T>) -> StdResult<Value> {
+        if self.dry_run {
+            return Ok(json!({
+                "execute": { "contract": call.contract, "msg": call.msg }
+            }));
+        }
+        let answer: Value = self.cli.execute(call)?;
+        Ok(json!({ "answer": answer }))
+    }
+
+    /// Returns the code hash and address of a contract
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the contract
+    fn contract_info(&self, address: HumanAddr) -> StdResult<ContractInfo> {
+        Ok(ContractInfo {
+            code_hash: self.cli.contract_hash(&address)?,
+            address,
+        })
+    }
+
+    /// Returns the number of decimals of a token
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    fn decimals(&self, token: &HumanAddr) -> StdResult<u8> {
+        match self.cli.query(token, &Snip20QueryMsg::TokenInfo {})? {
+            Snip20QueryAnswer::TokenInfo { decimals, .. } => Ok(decimals),
+        }
+    }
+
+    /// Returns the AuctionInfo answer of an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction` - address of the auction
+    fn auction_info(&self, auction: &HumanAddr) -> StdResult<QueryAnswer> {
+        self.cli.query(auction, &QueryMsg::AuctionInfo {})
+    }
+}
+
+/// Returns the credit history in a JSON file, which is an error if the oracle would reject it
+///
+/// # Arguments
+///
+/// * `path` - path of the file
+fn read_history(path: &str) -> StdResult<History> {
+    let file = fs::read(path)
+        .map_err(|e| StdError::generic_err(format!("Could not read {}: {}", path, e)))?;
+    let history: History = serde_json::from_slice(&file)
+        .map_err(|e| StdError::parse_err("History", format!("{}: {}", path, e)))?;
+    let problems = history.problems();
+    if !problems.is_empty() {
+        return Err(StdError::generic_err(format!(
+            "{}: Invalid history: {}",
+            path,
+            problems.join("; ")
+        )));
+    }
+    Ok(history)
+}
+
+/// Returns a random seed for an auction to generate viewing keys with
+fn random_seed() -> StdResult<Binary> {
+    let mut seed = [0u8; 32];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(&mut seed))
+        .map_err(|e| StdError::generic_err(format!("Could not read /dev/urandom: {}", e)))?;
+    Ok(Binary::from(seed.to_vec()))
+}
+
+/// Returns the result of an oracle command and its text output, if it has one
+///
+/// # Arguments
+///
+/// * `ctx` - the chain and whether to broadcast
+/// * `command` - the oracle command
+/// * `args` - parsed command line
+fn oracle<C: Chain>(
+    ctx: &Context<C>,
+    command: &str,
+    args: &Args,
+) -> StdResult<(Value, Option<String>)> {
+    match command {
+        "init" => {
+            let code_id = args.number("code-id")?.ok_or_else(|| required("code-id"))?;
+            let label = args.optional("label").unwrap_or("oracle");
+            let (user, history) = match (args.optional("user"), args.optional("file")) {
+                (Some(_), Some(file)) => (Some(args.address("user")?), Some(read_history(file)?)),
+                (None, None) => (None, None),
+                _ => return Err(StdError::generic_err("--user and --file go together")),
+            };
+            let histories = match args.optional("histories") {
+                Some(path) => read_file(Path::new(path))?.into_iter().collect(),
+                None => Vec::new(),
+            };
+            let reporters = args.addresses("reporters");
+            let mut msg = OracleClient::init_msg(user, history, histories, reporters);
+            msg.auction_code_hash = args.optional("auction-code-hash").map(str::to_string);
+            let factories = args.addresses("factories");
+            if !factories.is_empty() {
+                msg.factories = Some(factories);
+            }
+            Ok((ctx.instantiate(code_id, label, &msg)?, None))
+        }
+        "set-reporters" => {
+            let client = OracleClient::new(args.address("oracle")?);
+            let call = client.set_reporters(args.addresses("reporters"));
+            Ok((ctx.execute(&call)?, None))
+        }
+        "set-exposure-callers" => {
+            let client = OracleClient::new(args.address("oracle")?);
+            let call = client.set_exposure_callers(
+                args.optional("auction-code-hash").map(str::to_string),
+                args.addresses("factories"),
+            );
+            Ok((ctx.execute(&call)?, None))
+        }
+        "add-history" => {
+            let client = OracleClient::new(args.address("oracle")?);
+            let history = read_history(args.required("file")?)?;
+            let call = client.add_history(args.address("user")?, history);
+            Ok((ctx.execute(&call)?, None))
+        }
+        "history" => {
+            let client = OracleClient::new(args.address("oracle")?);
+            let query = client.get_history(args.address("user")?);
+            let response: QueryResponse = ctx.cli.query(&client.oracle, &query)?;
+            Ok((json!(response), None))
+        }
+        _ => Err(unknown(&["oracle", command])),
+    }
+}
+
+/// Returns the result of an auction command and its text output, if it has one
+///
+/// # Arguments
+///
+/// * `ctx` - the chain and whether to broadcast
+/// * `command` - the auction command
+/// * `args` - parsed command line
+fn auction<C: Chain>(
+    ctx: &Context<C>,
+    command: &str,
+    args: &Args,
+) -> StdResult<(Value, Option<String>)> {
+    match command {
+        "create" => {
+            let code_id = args.number("code-id")?.ok_or_else(|| required("code-id"))?;
+            let label = args.required("label")?;
+            let sell_contract = ctx.contract_info(args.address("sell-token")?)?;
+            let bid_contract = ctx.contract_info(args.address("bid-token")?)?;
+            let oracle_contract = ctx.contract_info(args.address("oracle")?)?;
+            let decimals = ctx.decimals(&bid_contract.address)?;
+            let expected = to_base_units(args.required("expected")?, decimals)?;
+            let payment = to_base_units(args.required("payment")?, decimals)?;
+            let mut msg = AuctionClient::init_msg(
+                sell_contract,
+                bid_contract,
+                oracle_contract,
+                expected,
+                payment,
+                random_seed()?,
+            );
+            msg.description = args.optional("description").map(str::to_string);
+            Ok((ctx.instantiate(code_id, label, &msg)?, None))
+        }
+        "list" => {
+            let code_id = args.number("code-id")?.ok_or_else(|| required("code-id"))?;
+            let listings = ctx.cli.list_contracts(code_id)?;
+            let text = if listings.is_empty() {
+                "There are no auctions.  Try creating one!".to_string()
+            } else {
+                listings
+                    .iter()
+                    .map(|l| format!("{}\t{}\t{}", l.label, l.address, l.creator))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            Ok((json!(listings), Some(text)))
+        }
+        "info" => {
+            let info = ctx.auction_info(&args.address("auction")?)?;
+            let text = describe(&info);
+            Ok((json!(info), text))
+        }
+        "consign" | "bid" => {
+            let client = client_of(ctx, &args.address("auction")?)?;
+            let token = if command == "consign" {
+                &client.sell_token
+            } else {
+                &client.bid_token
+            };
+            let amount = to_base_units(args.required("amount")?, ctx.decimals(token)?)?;
+            let call = if command == "consign" {
+                client.consign(amount)?
+            } else {
+                let terms = BidTerms {
+                    max_term: args.number("max-term")?,
+                    grace_period: args.number("grace-period")?,
+                    prepayment_penalty: args.number("prepayment-penalty")?,
+                    note: args.optional("note").map(str::to_string),
+                };
+                client.bid(amount, terms)?
+            };
+            Ok((ctx.execute(&call)?, None))
+        }
+        "finalize" => {
+            let client = client_of(ctx, &args.address("auction")?)?;
+            let call = client.finalize(args.flag("only-if-bids"));
+            Ok((ctx.execute(&call)?, None))
+        }
+        _ => Err(unknown(&["auction", command])),
+    }
+}
+
+/// Returns a client of an auction, with the tokens it names in its AuctionInfo
+///
+/// # Arguments
+///
+/// * `ctx` - the chain and whether to broadcast
+/// * `auction` - address of the auction
+fn client_of<C: Chain>(ctx: &Context<C>, auction: &HumanAddr) -> StdResult<AuctionClient> {
+    match ctx.auction_info(auction)? {
+        QueryAnswer::AuctionInfo {
+            sell_token,
+            bid_token,
+            ..
+        } => Ok(AuctionClient::new(
+            auction.clone(),
+            sell_token.contract_address,
+            bid_token.contract_address,
+        )),
+        _ => Err(StdError::generic_err("Unexpected answer to auction_info")),
+    }
+}
+
+/// Returns the auction information as text, amounts converted to whole tokens
+///
+/// # Arguments
+///
+/// * `info` - AuctionInfo answer
+pub fn describe(info: &QueryAnswer) -> Option<String> {
+    if let QueryAnswer::AuctionInfo {
+        sell_token,
+        bid_token,
+        score,
+        average_bid,
+        description,
+        auction_address,
+        status,
+        winning_bid,
+        oracle_contract,
+        ..
+    } = info
+    {
+        let sell_decimals = sell_token.token_info.decimals;
+        let bid_decimals = bid_token.token_info.decimals;
+        let mut lines = vec![
+            format!(
+                "Sale Token: {} ({})",
+                sell_token.token_info.symbol, sell_token.contract_address
+            ),
+            format!(
+                "Bid Token: {} ({})",
+                bid_token.token_info.symbol, bid_token.contract_address
+            ),
+            format!("Score: {}", from_base_units(*score, sell_decimals)),
+            format!("Average Bid: {}", from_base_units(*average_bid, bid_decimals)),
+        ];
+        if let Some(oracle) = oracle_contract {
+            lines.push(format!("Score Oracle: {}", oracle.address));
+        }
+        if let Some(description) = description {
+            lines.push(format!("Description: {}", description));
+        }
+        lines.push(format!("Auction Address: {}", auction_address));
+        lines.push(format!("Status: {}", status));
+        if let Some(winning_bid) = winning_bid {
+            lines.push(format!(
+                "Winning Bid: {}",
+                from_base_units(*winning_bid, bid_decimals)
+            ));
+        }
+        Some(lines.join("\n"))
+    } else {
+        None
+    }
+}
+
+/// Returns the error of a missing option
+///
+/// # Arguments
+///
+/// * `name` - name of the option
+fn required(name: &str) -> StdError {
+    StdError::generic_err(format!("--{} is required", name))
+}
+
+/// Returns the error of an unknown command, with the usage
+///
+/// # Arguments
+///
+/// * `words` - words of the command
+fn unknown(words: &[&str]) -> StdError {
+    StdError::generic_err(format!("Unknown command \"{}\"\n\n{}", words.join(" "), USAGE))
+}
+
+/// Returns the secretcli and whether to broadcast given on the command line
+///
+/// # Arguments
+///
+/// * `args` - parsed command line
+pub fn context(args: &Args) -> StdResult<Context<SecretCli>> {
+    let mut cli = SecretCli::default();
+    cli.from = args.optional("from").map(str::to_string);
+    if let Some(program) = args.optional("secretcli") {
+        cli.program = program.to_string();
+    }
+    if let Some(gas) = args.number("gas")? {
+        cli.gas = gas;
+    }
+    Ok(Context {
+        cli,
+        dry_run: args.flag("dry-run"),
+    })
+}
+
+/// Returns the message of an error, without the StdError variant around it
+///
+/// # Arguments
+///
+/// * `error` - the error
+pub fn message(error: &StdError) -> String {
+    match error {
+        StdError::GenericErr { msg, .. } => msg.clone(),
+        StdError::ParseErr {
+            target, msg, ..
+        } => format!("Invalid {}: {}", target, msg),
+        other => other.to_string(),
+    }
+}
+
+/// Returns the result of the command on the command line and its text output, if it has one
+///
+/// # Arguments
+///
+/// * `ctx` - the chain and whether to broadcast
+/// * `args` - parsed command line, with the two words of a command
+pub fn run<C: Chain>(ctx: &Context<C>, args: &Args) -> StdResult<(Value, Option<String>)> {
+    match args.words[0].as_str() {
+        "oracle" => oracle(ctx, &args.words[1], args),
+        "auction" => auction(ctx, &args.words[1], args),
+        _ => Err(unknown(&[&args.words[0], &args.words[1]])),
+    }
+}
+
+/// Returns what a successful command prints: its text output if it has one, unless `--json`
+/// asks for the single line of JSON, and otherwise the pretty printed JSON
+///
+/// # Arguments
+///
+/// * `value` - result of the command
+/// * `text` - Optional text output of the command
+/// * `json` - true if `--json` was given
+pub fn output(value: &Value, text: Option<String>, json: bool) -> String {
+    match text {
+        Some(text) if !json => text,
+        _ if json => value.to_string(),
+        _ => serde_json::to_string_pretty(value).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::Uint128;
+    use diploma_master::msg::{AuctionKind, BidPreferences, Token};
+    use secret_toolkit::snip20::TokenInfo;
+
+    /// chain answering queries with stored answers, which a dry run never broadcasts to
+    #[derive(Default)]
+    struct FakeChain {
+        /// answer of each contract to every query
+        answers: BTreeMap<String, Value>,
+    }
+
+    impl Chain for FakeChain {
+        fn query<T: Serialize, R: DeserializeOwned>(
+            &self,
+            contract: &HumanAddr,
+            _msg: &T,
+        ) -> StdResult<R> {
+            let answer = self.answers.get(contract.as_str()).cloned().unwrap_or_default();
+            serde_json::from_value(answer)
+                .map_err(|e| StdError::parse_err("answer", e.to_string()))
+        }
+
+        fn contract_hash(&self, contract: &HumanAddr) -> StdResult<String> {
+            Ok(format!("{}hash", contract))
+        }
+
+        fn list_contracts(&self, _code_id: u64) -> StdResult<Vec<ContractListing>> {
+            Ok(Vec::new())
+        }
+
+        fn instantiate<T: Serialize>(
+            &self,
+            _code_id: u64,
+            _label: &str,
+            _msg: &T,
+        ) -> StdResult<HumanAddr> {
+            panic!("a dry run does not instantiate")
+        }
+
+        fn execute<T: Serialize, R: DeserializeOwned>(&self, _call: &Call<T>) -> StdResult<R> {
+            panic!("a dry run does not execute")
+        }
+    }
+
+    fn parse(line: &str) -> StdResult<Args> {
+        Args::parse(line.split_whitespace().map(str::to_string))
+    }
+
+    fn token(address: &str, symbol: &str) -> Token {
+        Token {
+            contract_address: HumanAddr(address.to_string()),
+            token_info: TokenInfo {
+                name: format!("{} token", symbol),
+                symbol: symbol.to_string(),
+                decimals: 6,
+                total_supply: None,
+            },
+        }
+    }
+
+    fn auction_info() -> QueryAnswer {
+        QueryAnswer::AuctionInfo {
+            sell_token: token("selltoken", "SELL"),
+            bid_token: token("bidtoken", "BID"),
+            score: Uint128(1666),
+            average_bid: Uint128(1200000),
+            description: Some("integration".to_string()),
+            auction_address: HumanAddr("auction".to_string()),
+            status: "Accepting bids".to_string(),
+            winning_bid: None,
+            preferences: BidPreferences::default(),
+            kind: AuctionKind::default(),
+            started_at: 0,
+            ends_at: None,
+            fee: None,
+            oracle_contract: None,
+        }
+    }
+
+    /// dry run on a chain with an auction of tokens with 6 decimals
+    fn dry_run() -> Context<FakeChain> {
+        let mut answers = BTreeMap::new();
+        answers.insert("auction".to_string(), json!(auction_info()));
+        let token_info = Snip20QueryAnswer::TokenInfo {
+            name: "BID token".to_string(),
+            symbol: "BID".to_string(),
+            decimals: 6,
+            total_supply: None,
+        };
+        answers.insert("bidtoken".to_string(), json!(token_info));
+        Context {
+            cli: FakeChain { answers },
+            dry_run: true,
+        }
+    }
+
+    fn error<T>(result: StdResult<T>) -> String {
+        match result {
+            Err(e) => message(&e),
+            Ok(_) => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    fn parses_words_options_and_flags() {
+        let line = "--json auction bid --auction auction --amount 1.5 --max-term 12";
+        let args = parse(line).unwrap();
+        assert_eq!(args.words, vec!["auction", "bid"]);
+        assert!(args.flag("json") && !args.flag("dry-run"));
+        assert_eq!(args.required("amount").unwrap(), "1.5");
+        assert_eq!(args.address("auction").unwrap(), HumanAddr("auction".to_string()));
+        assert_eq!(args.number::<u64>("max-term").unwrap(), Some(12));
+        assert_eq!(args.number::<u64>("grace-period").unwrap(), None);
+        assert_eq!(error(args.required("note")), "--note is required");
+
+        let args = parse("oracle history --oracle secret1-oracle --user a,b --max-term x").unwrap();
+        assert!(error(args.address("oracle")).contains("must be an address"));
+        assert!(error(args.number::<u64>("max-term")).contains("must be a whole number"));
+        let users = args.addresses("user");
+        assert_eq!(users, vec![HumanAddr("a".to_string()), HumanAddr("b".to_string())]);
+    }
+
+    #[test]
+    fn refuses_repeated_and_missing_values() {
+        let repeated = parse("auction info --auction one --auction two");
+        assert_eq!(error(repeated), "--auction is given twice");
+        let missing = parse("auction info --auction");
+        assert_eq!(error(missing), "--auction needs a value");
+
+        let args = parse("oracle init --code-id 1 --user alice --dry-run").unwrap();
+        assert_eq!(error(run(&dry_run(), &args)), "--user and --file go together");
+        let args = parse("auction list --gas lots").unwrap();
+        assert_eq!(error(context(&args)), "--gas must be a whole number, not \"lots\"");
+    }
+
+    #[test]
+    fn dry_run_bid_prints_the_send() {
+        let args = parse("auction bid --auction auction --amount 1.05 --dry-run").unwrap();
+        let (value, text) = run(&dry_run(), &args).unwrap();
+        assert_eq!(text, None);
+        assert_eq!(
+            value,
+            json!({
+                "execute": {
+                    "contract": "bidtoken",
+                    "msg": {
+                        "send": {
+                            "recipient": "auction",
+                            "amount": "1050000",
+                            "msg": "eyJiaWQiOnt9fQ=="
+                        }
+                    }
+                }
+            })
+        );
+        assert_eq!(output(&value, None, true), value.to_string());
+        assert!(output(&value, None, false).contains("\n"));
+    }
+
+    #[test]
+    fn info_is_described_in_whole_tokens() {
+        let args = parse("auction info --auction auction").unwrap();
+        let (value, text) = run(&dry_run(), &args).unwrap();
+        let text = text.unwrap();
+        assert_eq!(
+            text,
+            "Sale Token: SELL (selltoken)\n\
+             Bid Token: BID (bidtoken)\n\
+             Score: 0.001666\n\
+             Average Bid: 1.2\n\
+             Description: integration\n\
+             Auction Address: auction\n\
+             Status: Accepting bids"
+        );
+        assert_eq!(output(&value, Some(text.clone()), false), text);
+        assert_eq!(output(&value, Some(text), true), value.to_string());
+    }
+}
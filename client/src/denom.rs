@@ -0,0 +1,70 @@
+use cosmwasm_std::{StdError, StdResult, Uint128};
+
+/// Returns the amount of base units of a decimal token amount, such as 1.5 tokens with 6
+/// decimals being 1500000.  Fails on anything but digits with an optional fractional part, on
+/// more fractional digits than the token has, and on amounts that do not fit a Uint128
+///
+/// # Arguments
+///
+/// * `amount` - decimal amount of tokens
+/// * `decimals` - number of decimals of the token
+pub fn to_base_units(amount: &str, decimals: u8) -> StdResult<Uint128> {
+    let invalid =
+        |reason: &str| StdError::generic_err(format!("Invalid amount \"{}\": {}", amount, reason));
+    let (whole, fraction) = match amount.find('.') {
+        Some(dot) => (&amount[..dot], &amount[dot + 1..]),
+        None => (amount, ""),
+    };
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(invalid("must be digits with an optional fractional part"));
+    }
+    if amount.ends_with('.') {
+        return Err(invalid("can not end with \".\""));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(invalid(&format!("the token only has {} decimals", decimals)));
+    }
+    let padded = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    let base = padded.parse::<u128>().map_err(|_| invalid("too large"))?;
+    Ok(Uint128(base))
+}
+
+/// Returns the decimal amount of tokens of an amount of base units, without trailing zeros
+///
+/// # Arguments
+///
+/// * `amount` - amount of base units
+/// * `decimals` - number of decimals of the token
+pub fn from_base_units(amount: Uint128, decimals: u8) -> String {
+    let digits = format!("{:0>width$}", amount.u128(), width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_decimal_amounts() {
+        assert_eq!(to_base_units("1.5", 6).unwrap(), Uint128(1500000));
+        assert_eq!(to_base_units("12", 6).unwrap(), Uint128(12000000));
+        assert_eq!(to_base_units(".000001", 6).unwrap(), Uint128(1));
+        assert_eq!(to_base_units("1666", 0).unwrap(), Uint128(1666));
+        for invalid in &["", ".", "1.", "1.0000001", "-1", "1e6", "1,5", "1.2.3"] {
+            assert!(to_base_units(invalid, 6).is_err(), "{} was accepted", invalid);
+        }
+        assert!(to_base_units("340282366920938463463374607431768211456", 0).is_err());
+
+        assert_eq!(from_base_units(Uint128(1500000), 6), "1.5");
+        assert_eq!(from_base_units(Uint128(1), 6), "0.000001");
+        assert_eq!(from_base_units(Uint128(12000000), 6), "12");
+        assert_eq!(from_base_units(Uint128(1666), 0), "1666");
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{HumanAddr, StdError, StdResult, Uint128};
+
+use oracle::msg::{Credit, HandleMsg, History, MAX_HISTORIES_PER_BATCH};
+
+use crate::oracle::OracleClient;
+use crate::tx::Call;
+
+/// one row of a CSV import, a credit of a user
+#[derive(Deserialize)]
+struct CreditRecord {
+    user: String,
+    debts: String,
+    sum: String,
+    interest_rate: String,
+    time: String,
+    is_closed: String,
+}
+
+/// validation result of one user's history
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Report {
+    /// address of the user
+    pub user: HumanAddr,
+    /// number of credits in the history
+    pub credits: usize,
+    /// Optional score the auction would give the history.  None if there was no payment to
+    /// score against, or the history is invalid or scores too low to open an auction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<u128>,
+    /// reasons the history can not be added, empty if it is valid
+    pub problems: Vec<String>,
+}
+
+/// Returns the histories by user in a `.csv` or `.json` file
+///
+/// # Arguments
+///
+/// * `path` - path of the file
+pub fn read_file(path: &Path) -> StdResult<BTreeMap<HumanAddr, History>> {
+    let file = fs::read(path)
+        .map_err(|e| StdError::generic_err(format!("Could not read {}: {}", path.display(), e)))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => read_csv(file.as_slice()),
+        Some("json") => read_json(&file),
+        _ => Err(StdError::generic_err(format!(
+            "{} is neither a .csv nor a .json file",
+            path.display()
+        ))),
+    }
+}
+
+/// Returns the histories by user in CSV with the header
+/// `user,debts,sum,interest_rate,time,is_closed` and a row for each credit.  Every row of a
+/// user must state the same debts
+///
+/// # Arguments
+///
+/// * `reader` - the CSV
+pub fn read_csv<R: Read>(reader: R) -> StdResult<BTreeMap<HumanAddr, History>> {
+    let mut histories: BTreeMap<HumanAddr, History> = BTreeMap::new();
+    let mut csv = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    for (i, record) in csv.deserialize::<CreditRecord>().enumerate() {
+        // the header is line 1
+        let line = i + 2;
+        let record = record.map_err(|e| parse_err(line, e.to_string()))?;
+        let number = |field: &str, value: &str| {
+            value.parse::<u128>().map(Uint128).map_err(|_| {
+                parse_err(line, format!("{} must be a whole number, not \"{}\"", field, value))
+            })
+        };
+        let debts = number("debts", &record.debts)?;
+        let credit = Credit {
+            sum: number("sum", &record.sum)?,
+            interest_rate: number("interest_rate", &record.interest_rate)?,
+            time: number("time", &record.time)?,
+            is_closed: match record.is_closed.to_lowercase().as_str() {
+                "true" => true,
+                "false" => false,
+                other => {
+                    let msg = format!("is_closed must be true or false, not \"{}\"", other);
+                    return Err(parse_err(line, msg));
+                }
+            },
+        };
+        if record.user.is_empty() {
+            return Err(parse_err(line, "user is empty".to_string()));
+        }
+        let history = histories
+            .entry(HumanAddr(record.user.clone()))
+            .or_insert_with(|| History {
+                debts,
+                credits: Vec::new(),
+            });
+        if history.debts != debts {
+            let msg = format!(
+                "debts of {} are {}, but {} in an earlier row",
+                record.user, debts, history.debts
+            );
+            return Err(parse_err(line, msg));
+        }
+        history.credits.push(credit);
+    }
+    Ok(histories)
+}
+
+/// Returns the error of an invalid CSV row
+///
+/// # Arguments
+///
+/// * `line` - line of the row
+/// * `msg` - what is wrong with the row
+fn parse_err(line: usize, msg: String) -> StdError {
+    StdError::parse_err("credit record", format!("line {}: {}", line, msg))
+}
+
+/// Returns the histories by user in a JSON object of user addresses to histories
+///
+/// # Arguments
+///
+/// * `json` - the JSON
+pub fn read_json(json: &[u8]) -> StdResult<BTreeMap<HumanAddr, History>> {
+    serde_json::from_slice(json).map_err(|e| StdError::parse_err("histories", e.to_string()))
+}
+
+/// Returns the score the auction would give a history for a payment.  None if the score is too
+/// low to open an auction, which includes debts outweighing the credits.  The history must be
+/// valid, or the calculation may overflow
+///
+/// # Arguments
+///
+/// * `history` - the credit history
+/// * `payment` - alleged sum to pay of the auction
+pub fn score(history: &History, payment: Uint128) -> Option<u128> {
+    history.score(payment)
+}
+
+/// Returns the validation report of every history, scored against the payment if one is given
+///
+/// # Arguments
+///
+/// * `histories` - the histories by user
+/// * `payment` - Optional alleged sum to pay to score the histories against
+pub fn check(histories: &BTreeMap<HumanAddr, History>, payment: Option<Uint128>) -> Vec<Report> {
+    histories
+        .iter()
+        .map(|(user, history)| {
+            let problems = history.problems();
+            let score = match payment {
+                Some(payment) if problems.is_empty() && payment != Uint128(0) => {
+                    score(history, payment)
+                }
+                _ => None,
+            };
+            Report {
+                user: user.clone(),
+                credits: history.credits.len(),
+                score,
+                problems,
+            }
+        })
+        .collect()
+}
+
+/// Returns the AddHistories messages of the histories, each adding at most batch_size of them.
+/// The batch size is capped at the most histories the oracle adds at a time
+///
+/// # Arguments
+///
+/// * `oracle` - client of the oracle the histories are added to
+/// * `histories` - the histories by user
+/// * `batch_size` - most histories in a message
+pub fn batches(
+    oracle: &OracleClient,
+    histories: BTreeMap<HumanAddr, History>,
+    batch_size: usize,
+) -> Vec<Call<HandleMsg>> {
+    let batch_size = batch_size.max(1).min(MAX_HISTORIES_PER_BATCH);
+    let mut batches = Vec::new();
+    let mut entries = Vec::new();
+    for entry in histories {
+        entries.push(entry);
+        if entries.len() == batch_size {
+            batches.push(oracle.add_histories(std::mem::take(&mut entries)));
+        }
+    }
+    if !entries.is_empty() {
+        batches.push(oracle.add_histories(entries));
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CSV: &str = "user,debts,sum,interest_rate,time,is_closed
+alice,100000,1000000,6,12,true
+alice,100000,100000,5,6,false
+alice,100000,500000,10,9,false
+alice,100000,200000,7,6,true
+alice,100000,150000,10,12,true
+bob,0,500000,120,0,true
+";
+
+    #[test]
+    fn imports_validates_and_scores() {
+        let histories = read_csv(CSV.as_bytes()).unwrap();
+        let reports = check(&histories, Some(Uint128(1200000)));
+        assert_eq!(reports.len(), 2);
+        // alice has the history in auction/history.json
+        assert_eq!(reports[0].user, HumanAddr("alice".to_string()));
+        assert_eq!(reports[0].credits, 5);
+        assert_eq!(reports[0].score, Some(1666));
+        assert!(reports[0].problems.is_empty());
+        assert_eq!(reports[1].score, None);
+        assert_eq!(reports[1].problems.len(), 2);
+
+        let json = serde_json::to_vec(&histories).unwrap();
+        assert_eq!(read_json(&json).unwrap(), histories);
+
+        let oracle = OracleClient::new(HumanAddr("oracle".to_string()));
+        let batches = batches(&oracle, histories, 1);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].contract, HumanAddr("oracle".to_string()));
+        match &batches[1].msg {
+            HandleMsg::AddHistories { entries } => {
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].0, HumanAddr("bob".to_string()));
+            }
+            _ => panic!("Expected AddHistories"),
+        }
+    }
+
+    #[test]
+    fn outweighing_debts_score_nothing() {
+        let csv = "user,debts,sum,interest_rate,time,is_closed
+carol,1000000,1000000,10,12,false
+";
+        let histories = read_csv(csv.as_bytes()).unwrap();
+        let reports = check(&histories, Some(Uint128(1000000)));
+        assert!(reports[0].problems.is_empty());
+        assert_eq!(reports[0].score, None);
+    }
+
+    #[test]
+    fn rejects_malformed_records() {
+        let mismatched = "user,debts,sum,interest_rate,time,is_closed
+alice,100000,1000000,6,12,true
+alice,200000,100000,5,6,false
+";
+        let error = read_csv(mismatched.as_bytes()).unwrap_err().to_string();
+        assert!(error.contains("line 3: debts of alice"), "{}", error);
+
+        let not_a_number = "user,debts,sum,interest_rate,time,is_closed
+alice,100000,1e6,6,12,true
+";
+        let error = read_csv(not_a_number.as_bytes()).unwrap_err().to_string();
+        assert!(error.contains("line 2: sum must be a whole number"), "{}", error);
+    }
+}
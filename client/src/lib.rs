@@ -0,0 +1,12 @@
+//! Builds the messages of the auction and oracle contracts from the contracts' own types, and
+//! decodes their answers, so services talking to the contracts are checked at compile time
+//! instead of interpolating JSON strings.
+pub mod auction;
+pub mod cli;
+pub mod denom;
+pub mod import;
+pub mod marketplace;
+pub mod oracle;
+pub mod secretcli;
+pub mod snip20;
+pub mod tx;
@@ -0,0 +1,436 @@
+use cosmwasm_std::{HumanAddr, StdError, StdResult, Uint128};
+use secret_toolkit::permit::Permit;
+
+use diploma_master::msg::{
+    AuctionKind, BidPreferences, FeeConfig, MarketHandleMsg, MarketQueryMsg, MarketReceiveMsg,
+    QueryWithPermit, ReceiveMsg, MAX_NOTE_LEN,
+};
+
+use crate::auction::BidTerms;
+use crate::snip20::{send, Snip20HandleMsg};
+use crate::tx::Call;
+
+/// builds the messages of a marketplace, which hosts many auctions of the same two tokens.
+/// Every message but CreateAuction names the auction it is for by its id
+#[derive(Clone, Debug)]
+pub struct MarketplaceClient {
+    /// address of the marketplace
+    pub marketplace: HumanAddr,
+    /// address of the token being sold
+    pub sell_token: HumanAddr,
+    /// address of the token bids are placed in
+    pub bid_token: HumanAddr,
+}
+
+impl MarketplaceClient {
+    /// Returns a client of an instantiated marketplace
+    ///
+    /// # Arguments
+    ///
+    /// * `marketplace` - address of the marketplace
+    /// * `sell_token` - address of the token being sold
+    /// * `bid_token` - address of the token bids are placed in
+    pub fn new(marketplace: HumanAddr, sell_token: HumanAddr, bid_token: HumanAddr) -> Self {
+        MarketplaceClient {
+            marketplace,
+            sell_token,
+            bid_token,
+        }
+    }
+
+    /// Returns the Call that creates an auction with the sender as its seller
+    ///
+    /// # Arguments
+    ///
+    /// * `expected` - expected sum of credit
+    /// * `payment` - alleged sum to pay
+    /// * `description` - Optional description of the auction
+    /// * `preferences` - Optional weights of the bid terms, equal weights if None
+    /// * `kind` - Optional kind of the auction, a reverse auction if None
+    /// * `entropy` - string used as entropy when generating the auction's seed
+    pub fn create_auction(
+        &self,
+        expected: Uint128,
+        payment: Uint128,
+        description: Option<String>,
+        preferences: Option<BidPreferences>,
+        kind: Option<AuctionKind>,
+        entropy: String,
+    ) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::CreateAuction {
+            expected,
+            payment,
+            description,
+            preferences,
+            kind,
+            entropy,
+        })
+    }
+
+    /// Returns the Send of sell tokens that consigns them to an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `amount` - amount of sell tokens
+    pub fn consign(&self, auction_id: u64, amount: Uint128) -> StdResult<Call<Snip20HandleMsg>> {
+        self.send(&self.sell_token, auction_id, amount, ReceiveMsg::Consign {})
+    }
+
+    /// Returns the Send of bid tokens that places a bid in an auction.  A note the auction would
+    /// reject is an error, so the tokens are not sent for nothing
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `amount` - amount of the bid
+    /// * `terms` - loan terms offered with the bid
+    pub fn bid(
+        &self,
+        auction_id: u64,
+        amount: Uint128,
+        terms: BidTerms,
+    ) -> StdResult<Call<Snip20HandleMsg>> {
+        if terms.note.as_ref().map_or(false, |note| note.len() > MAX_NOTE_LEN) {
+            return Err(StdError::generic_err(format!(
+                "Bid note can not be longer than {} bytes",
+                MAX_NOTE_LEN
+            )));
+        }
+        let bid = ReceiveMsg::Bid {
+            max_term: terms.max_term,
+            grace_period: terms.grace_period,
+            prepayment_penalty: terms.prepayment_penalty,
+            note: terms.note,
+        };
+        self.send(&self.bid_token, auction_id, amount, bid)
+    }
+
+    /// Returns the Call that closes an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `only_if_bids` - true to keep the auction open if there are no active bids
+    pub fn finalize(&self, auction_id: u64, only_if_bids: bool) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::Finalize {
+            auction_id,
+            only_if_bids,
+        })
+    }
+
+    /// Returns the Call that returns the funds a closed auction still holds
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn return_all(&self, auction_id: u64) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::ReturnAll { auction_id })
+    }
+
+    /// Returns the Call that cancels an auction before the first bid
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn cancel(&self, auction_id: u64) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::CancelAuction { auction_id })
+    }
+
+    /// Returns the Call that amends an auction before the first bid
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `description` - Optional new description of the auction
+    /// * `max_bid` - Optional new average bid that will be accepted
+    /// * `ends_at` - Optional new time after which bids are no longer accepted
+    pub fn update_auction(
+        &self,
+        auction_id: u64,
+        description: Option<String>,
+        max_bid: Option<Uint128>,
+        ends_at: Option<u64>,
+    ) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::UpdateAuction {
+            auction_id,
+            description,
+            max_bid,
+            ends_at,
+        })
+    }
+
+    /// Returns the Call of the admin that sets the platform fee of an auction before its first
+    /// bid
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `fee` - Optional new fee configuration, None removes the fee
+    pub fn set_fee(&self, auction_id: u64, fee: Option<FeeConfig>) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::SetFee { auction_id, fee })
+    }
+
+    /// Returns the Call of the admin that stops new bids and consignments in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn pause(&self, auction_id: u64) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::Pause { auction_id })
+    }
+
+    /// Returns the Call of the admin that lets an auction accept bids and consignments again
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn unpause(&self, auction_id: u64) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::Unpause { auction_id })
+    }
+
+    /// Returns the Call of the admin that closes an auction and returns every fund it holds
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn emergency_unwind(&self, auction_id: u64) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::EmergencyUnwind { auction_id })
+    }
+
+    /// Returns the Call that creates a viewing key for the sender in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `entropy` - string used as entropy when generating the key
+    pub fn create_viewing_key(&self, auction_id: u64, entropy: String) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::CreateViewingKey {
+            auction_id,
+            entropy,
+            padding: None,
+        })
+    }
+
+    /// Returns the Call that sets the sender's viewing key in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `key` - the viewing key
+    pub fn set_viewing_key(&self, auction_id: u64, key: String) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::SetViewingKey {
+            auction_id,
+            key,
+            padding: None,
+        })
+    }
+
+    /// Returns the Call that revokes one of the sender's permits in every hosted auction
+    ///
+    /// # Arguments
+    ///
+    /// * `permit_name` - name of the permit
+    pub fn revoke_permit(&self, permit_name: String) -> Call<MarketHandleMsg> {
+        self.call(MarketHandleMsg::RevokePermit {
+            permit_name,
+            padding: None,
+        })
+    }
+
+    /// Returns the query of an auction's information
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn auction_info(&self, auction_id: u64) -> MarketQueryMsg {
+        MarketQueryMsg::AuctionInfo { auction_id }
+    }
+
+    /// Returns the query of the number of active bids in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    pub fn bid_count(&self, auction_id: u64) -> MarketQueryMsg {
+        MarketQueryMsg::BidCount { auction_id }
+    }
+
+    /// Returns the query of the bids of an auction, which only its seller can list
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `address` - address of the seller
+    /// * `key` - viewing key of the seller
+    /// * `page` - Optional page to display, starting at 0
+    /// * `page_size` - number of bids per page
+    pub fn list_bids(
+        &self,
+        auction_id: u64,
+        address: HumanAddr,
+        key: String,
+        page: Option<u32>,
+        page_size: u32,
+    ) -> MarketQueryMsg {
+        MarketQueryMsg::ListBids {
+            auction_id,
+            address,
+            key,
+            page,
+            page_size,
+        }
+    }
+
+    /// Returns the query of a bidder's active bid in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `address` - address of the bidder
+    /// * `key` - viewing key of the bidder
+    pub fn my_bid(&self, auction_id: u64, address: HumanAddr, key: String) -> MarketQueryMsg {
+        MarketQueryMsg::MyBid {
+            auction_id,
+            address,
+            key,
+        }
+    }
+
+    /// Returns the query of the score of a bidder's active bid in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `address` - address of the bidder
+    /// * `key` - viewing key of the bidder
+    pub fn bid_score(&self, auction_id: u64, address: HumanAddr, key: String) -> MarketQueryMsg {
+        MarketQueryMsg::BidScore {
+            auction_id,
+            address,
+            key,
+        }
+    }
+
+    /// Returns the query of the active bid of the permit's signer in an auction
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_id` - id of the auction
+    /// * `permit` - permit signed by the bidder
+    pub fn my_bid_with_permit(&self, auction_id: u64, permit: Permit) -> MarketQueryMsg {
+        MarketQueryMsg::WithPermit {
+            auction_id,
+            permit,
+            query: QueryWithPermit::MyBid {},
+        }
+    }
+
+    /// Returns the query of the number of auctions the marketplace has created
+    pub fn auction_count(&self) -> MarketQueryMsg {
+        MarketQueryMsg::AuctionCount {}
+    }
+
+    /// Returns the Send of tokens to the marketplace, routed to an auction by its id
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `auction_id` - id of the auction
+    /// * `amount` - amount of tokens
+    /// * `action` - what the auction does with the tokens
+    fn send(
+        &self,
+        token: &HumanAddr,
+        auction_id: u64,
+        amount: Uint128,
+        action: ReceiveMsg,
+    ) -> StdResult<Call<Snip20HandleMsg>> {
+        let routed = MarketReceiveMsg {
+            auction_id,
+            action: Some(action),
+        };
+        send(token, &self.marketplace, amount, &routed)
+    }
+
+    /// Returns a Call of a message to the marketplace
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - the message
+    fn call(&self, msg: MarketHandleMsg) -> Call<MarketHandleMsg> {
+        Call {
+            contract: self.marketplace.clone(),
+            msg,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::from_slice;
+
+    use crate::tx::to_json;
+
+    fn client() -> MarketplaceClient {
+        MarketplaceClient::new(
+            HumanAddr("market".to_string()),
+            HumanAddr("selltoken".to_string()),
+            HumanAddr("bidtoken".to_string()),
+        )
+    }
+
+    #[test]
+    fn sends_are_routed_by_auction_id() {
+        let bid = client().bid(3, Uint128(1050000), BidTerms::default()).unwrap();
+        assert_eq!(bid.contract, HumanAddr("bidtoken".to_string()));
+        match bid.msg {
+            Snip20HandleMsg::Send { recipient, msg, .. } => {
+                assert_eq!(recipient, HumanAddr("market".to_string()));
+                let routed: MarketReceiveMsg = from_slice(msg.unwrap().as_slice()).unwrap();
+                assert_eq!(routed.auction_id, 3);
+                assert_eq!(
+                    routed.action,
+                    Some(ReceiveMsg::Bid {
+                        max_term: None,
+                        grace_period: None,
+                        prepayment_penalty: None,
+                        note: None,
+                    })
+                );
+            }
+        }
+        let consign = client().consign(3, Uint128(1666)).unwrap();
+        assert_eq!(consign.contract, HumanAddr("selltoken".to_string()));
+    }
+
+    #[test]
+    fn builds_hosted_handles_and_queries() {
+        let create = client().create_auction(
+            Uint128(1000000),
+            Uint128(1200000),
+            None,
+            None,
+            None,
+            "entropy".to_string(),
+        );
+        assert_eq!(create.contract, HumanAddr("market".to_string()));
+        assert_eq!(
+            create.to_json().unwrap(),
+            r#"{"create_auction":{"expected":"1000000","payment":"1200000","entropy":"entropy"}}"#
+        );
+        assert_eq!(client().pause(3).to_json().unwrap(), r#"{"pause":{"auction_id":3}}"#);
+        assert_eq!(
+            client().emergency_unwind(3).to_json().unwrap(),
+            r#"{"emergency_unwind":{"auction_id":3}}"#
+        );
+
+        let seller = HumanAddr("seller".to_string());
+        let list_bids = client().list_bids(3, seller, "key".to_string(), None, 10);
+        assert_eq!(
+            to_json(&list_bids).unwrap(),
+            r#"{"list_bids":{"auction_id":3,"address":"seller","key":"key","page_size":10}}"#
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use cosmwasm_std::HumanAddr;
+
+use oracle::msg::{HandleMsg, History, InitMsg, QueryMsg};
+
+use crate::tx::Call;
+
+/// builds the messages of the oracle contract
+#[derive(Clone, Debug)]
+pub struct OracleClient {
+    /// address of the oracle
+    pub oracle: HumanAddr,
+}
+
+impl OracleClient {
+    /// Returns a client of an instantiated oracle
+    ///
+    /// # Arguments
+    ///
+    /// * `oracle` - address of the oracle
+    pub fn new(oracle: HumanAddr) -> Self {
+        OracleClient { oracle }
+    }
+
+    /// Returns the InitMsg of an oracle, optionally seeded with histories and a reporter set.  No
+    /// contract may open exposures until its auction_code_hash or factories are set
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - Optional address of the user
+    /// * `history` - Optional credit history of the user
+    /// * `histories` - user addresses and their histories, at most MAX_HISTORIES_PER_BATCH
+    /// * `reporters` - addresses allowed to add histories besides the owner.  Only the owner
+    ///                 may add them if it is empty
+    pub fn init_msg(
+        user: Option<HumanAddr>,
+        history: Option<History>,
+        histories: Vec<(HumanAddr, History)>,
+        reporters: Vec<HumanAddr>,
+    ) -> InitMsg {
+        InitMsg {
+            user,
+            history,
+            histories: if histories.is_empty() { None } else { Some(histories) },
+            reporters: if reporters.is_empty() { None } else { Some(reporters) },
+            ..InitMsg::default()
+        }
+    }
+
+    /// Returns the Call that replaces the addresses allowed to add histories besides the owner.
+    /// Only the owner can execute it
+    ///
+    /// # Arguments
+    ///
+    /// * `reporters` - addresses allowed to add histories.  Only the owner may add them if it
+    ///                 is empty
+    pub fn set_reporters(&self, reporters: Vec<HumanAddr>) -> Call<HandleMsg> {
+        Call {
+            contract: self.oracle.clone(),
+            msg: HandleMsg::SetReporters { reporters },
+        }
+    }
+
+    /// Returns the Call that sets the contracts that may open exposures.  Only the owner can
+    /// execute it
+    ///
+    /// # Arguments
+    ///
+    /// * `auction_code_hash` - Optional code hash of the auction contract, whose instances may
+    ///                         open exposures
+    /// * `factories` - addresses of contracts that host auctions, such as marketplaces
+    pub fn set_exposure_callers(
+        &self,
+        auction_code_hash: Option<String>,
+        factories: Vec<HumanAddr>,
+    ) -> Call<HandleMsg> {
+        Call {
+            contract: self.oracle.clone(),
+            msg: HandleMsg::SetExposureCallers {
+                auction_code_hash,
+                factories,
+            },
+        }
+    }
+
+    /// Returns the Call that records a user's credit history
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - address of the user
+    /// * `history` - credit history of the user
+    pub fn add_history(&self, user: HumanAddr, history: History) -> Call<HandleMsg> {
+        Call {
+            contract: self.oracle.clone(),
+            msg: HandleMsg::AddHistory { user, history },
+        }
+    }
+
+    /// Returns the Call that records the credit histories of many users.  Invalid entries are
+    /// reported in the HandleAnswer
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - user addresses and their histories, at most MAX_HISTORIES_PER_BATCH
+    pub fn add_histories(&self, entries: Vec<(HumanAddr, History)>) -> Call<HandleMsg> {
+        Call {
+            contract: self.oracle.clone(),
+            msg: HandleMsg::AddHistories { entries },
+        }
+    }
+
+    /// Returns the query of a user's credit history, answered with a QueryResponse
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - address of the user
+    pub fn get_history(&self, user: HumanAddr) -> QueryMsg {
+        QueryMsg::GetHistory { user }
+    }
+
+    /// Returns the query of a user's exposure, answered with an ExposureResponse
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - address of the user
+    pub fn get_exposure(&self, user: HumanAddr) -> QueryMsg {
+        QueryMsg::GetExposure { user }
+    }
+}
@@ -0,0 +1,225 @@
+use std::process::Command;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{HumanAddr, StdError, StdResult};
+
+use crate::tx::{decode_query_answer, decode_tx_answer, to_json, Call};
+
+/// default gas limit of transactions
+pub const DEFAULT_GAS: u64 = 300_000;
+
+/// contract listed by `secretcli q compute list-contract-by-code`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContractListing {
+    /// address that instantiated the contract
+    pub creator: HumanAddr,
+    /// label of the contract
+    pub label: String,
+    /// address of the contract
+    pub address: HumanAddr,
+}
+
+/// runs queries and transactions through the `secretcli` binary
+#[derive(Clone, Debug)]
+pub struct SecretCli {
+    /// path of the secretcli binary
+    pub program: String,
+    /// keys alias of the account signing transactions
+    pub from: Option<String>,
+    /// gas limit of transactions
+    pub gas: u64,
+}
+
+impl Default for SecretCli {
+    fn default() -> Self {
+        SecretCli {
+            program: "secretcli".to_string(),
+            from: None,
+            gas: DEFAULT_GAS,
+        }
+    }
+}
+
+impl SecretCli {
+    /// Returns the answer of a contract to a query
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the contract
+    /// * `msg` - the query
+    pub fn query<T: Serialize, R: DeserializeOwned>(
+        &self,
+        contract: &HumanAddr,
+        msg: &T,
+    ) -> StdResult<R> {
+        let msg = to_json(msg)?;
+        let output = self.run(&[
+            "q",
+            "compute",
+            "query",
+            contract.as_str(),
+            &msg,
+            "--trust-node=true",
+            "-o",
+            "json",
+        ])?;
+        decode_query_answer(&output)
+    }
+
+    /// Returns the code hash of a contract
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the contract
+    pub fn contract_hash(&self, contract: &HumanAddr) -> StdResult<String> {
+        let output = self.run(&[
+            "q",
+            "compute",
+            "contract-hash",
+            contract.as_str(),
+            "--trust-node=true",
+            "-o",
+            "json",
+        ])?;
+        let hash = String::from_utf8_lossy(&output);
+        Ok(hash.trim().trim_matches('"').trim_start_matches("0x").to_string())
+    }
+
+    /// Returns the contracts instantiated from a code id
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` - id of the uploaded code
+    pub fn list_contracts(&self, code_id: u64) -> StdResult<Vec<ContractListing>> {
+        let code_id = code_id.to_string();
+        let output = self.run(&[
+            "q",
+            "compute",
+            "list-contract-by-code",
+            &code_id,
+            "--trust-node=true",
+            "-o",
+            "json",
+        ])?;
+        // secretcli prints null when there are no contracts
+        let listings: Option<Vec<ContractListing>> = serde_json::from_slice(&output)
+            .map_err(|e| StdError::parse_err("contract list", e.to_string()))?;
+        Ok(listings.unwrap_or_default())
+    }
+
+    /// Returns the address of the contract instantiated with the InitMsg
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` - id of the uploaded code
+    /// * `label` - unique label of the new contract
+    /// * `msg` - the InitMsg
+    pub fn instantiate<T: Serialize>(
+        &self,
+        code_id: u64,
+        label: &str,
+        msg: &T,
+    ) -> StdResult<HumanAddr> {
+        let code = code_id.to_string();
+        let msg = to_json(msg)?;
+        self.broadcast(&["tx", "compute", "instantiate", &code, &msg, "--label", label])?;
+        self.list_contracts(code_id)?
+            .into_iter()
+            .find(|listing| listing.label == label)
+            .map(|listing| listing.address)
+            .ok_or_else(|| StdError::generic_err(format!("No contract is labeled {}", label)))
+    }
+
+    /// Returns the answer of the contract to an executed Call
+    ///
+    /// # Arguments
+    ///
+    /// * `call` - the Call to execute
+    pub fn execute<T: Serialize, R: DeserializeOwned>(&self, call: &Call<T>) -> StdResult<R> {
+        let msg = call.to_json()?;
+        let txhash = self.broadcast(&["tx", "compute", "execute", call.contract.as_str(), &msg])?;
+        self.tx_answer(&txhash)
+    }
+
+    /// Returns the answer of the contract to a transaction
+    ///
+    /// # Arguments
+    ///
+    /// * `txhash` - hash of the transaction
+    fn tx_answer<R: DeserializeOwned>(&self, txhash: &str) -> StdResult<R> {
+        let output = self.run(&["q", "compute", "tx", txhash, "--trust-node=true", "-o", "json"])?;
+        decode_tx_answer(&output)
+    }
+
+    /// Returns the hash of a transaction the contract executed successfully, once it is
+    /// included in a block
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - secretcli arguments of the transaction, without the signing arguments
+    fn broadcast(&self, args: &[&str]) -> StdResult<String> {
+        let from = self
+            .from
+            .as_deref()
+            .ok_or_else(|| StdError::generic_err("Transactions need a key to sign with (--from)"))?;
+        let gas = self.gas.to_string();
+        let mut args = args.to_vec();
+        args.extend_from_slice(&[
+            "--from",
+            from,
+            "--gas",
+            &gas,
+            "--broadcast-mode",
+            "block",
+            "--trust-node=true",
+            "-o",
+            "json",
+            "-y",
+        ]);
+        let output = self.run(&args)?;
+        let response: serde_json::Value = serde_json::from_slice(&output)
+            .map_err(|e| StdError::parse_err("secretcli tx output", e.to_string()))?;
+        let txhash = response
+            .get("txhash")
+            .and_then(|hash| hash.as_str())
+            .ok_or_else(|| StdError::generic_err("The transaction was not broadcast"))?;
+        if response.get("code").and_then(|code| code.as_u64()).unwrap_or(0) != 0 {
+            let log = response
+                .get("raw_log")
+                .and_then(|log| log.as_str())
+                .unwrap_or_default();
+            if log.contains("out of gas") {
+                return Err(StdError::generic_err(format!(
+                    "Out of gas, retry with more than --gas {}",
+                    self.gas
+                )));
+            }
+            // the log only holds the encrypted error, the transaction query decrypts it
+            self.tx_answer::<serde_json::Value>(txhash)?;
+            return Err(StdError::generic_err(log));
+        }
+        Ok(txhash.to_string())
+    }
+
+    /// Returns the standard output of a successful secretcli run
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - secretcli arguments
+    fn run(&self, args: &[&str]) -> StdResult<Vec<u8>> {
+        let output = Command::new(&self.program)
+            .args(args)
+            .output()
+            .map_err(|e| StdError::generic_err(format!("Could not run {}: {}", self.program, e)))?;
+        if !output.status.success() {
+            return Err(StdError::generic_err(format!(
+                "{} failed: {}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
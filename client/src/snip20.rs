@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{to_binary, Binary, HumanAddr, StdResult, Uint128};
+
+use crate::tx::Call;
+
+/// SNIP-20 handle messages the contracts are called through
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip20HandleMsg {
+    /// send tokens to the recipient, calling its Receive with the msg
+    Send {
+        /// address receiving the tokens
+        recipient: HumanAddr,
+        /// amount of tokens
+        amount: Uint128,
+        /// Optional base64 encoded msg passed on to the recipient's Receive
+        #[serde(skip_serializing_if = "Option::is_none")]
+        msg: Option<Binary>,
+        /// Optional padding to hide the length of the message
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+}
+
+/// Returns the Call that sends tokens to a contract with a message for its Receive
+///
+/// # Arguments
+///
+/// * `token` - address of the token
+/// * `recipient` - address of the contract receiving the tokens
+/// * `amount` - amount of tokens
+/// * `msg` - message passed on to the recipient's Receive
+pub fn send<T: Serialize>(
+    token: &HumanAddr,
+    recipient: &HumanAddr,
+    amount: Uint128,
+    msg: &T,
+) -> StdResult<Call<Snip20HandleMsg>> {
+    Ok(Call {
+        contract: token.clone(),
+        msg: Snip20HandleMsg::Send {
+            recipient: recipient.clone(),
+            amount,
+            msg: Some(to_binary(msg)?),
+            padding: None,
+        },
+    })
+}
+
+/// SNIP-20 queries the clients make
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip20QueryMsg {
+    /// public information of the token
+    TokenInfo {},
+}
+
+/// SNIP-20 query responses
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip20QueryAnswer {
+    /// TokenInfo query response
+    TokenInfo {
+        /// name of the token
+        name: String,
+        /// symbol of the token
+        symbol: String,
+        /// number of decimals of the token
+        decimals: u8,
+        /// Optional total supply, if the token discloses it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        total_supply: Option<Uint128>,
+    },
+}
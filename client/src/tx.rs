@@ -0,0 +1,132 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{from_slice, to_vec, HumanAddr, StdError, StdResult};
+
+/// message to execute on a contract
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Call<T: Serialize> {
+    /// address of the contract executing the message
+    pub contract: HumanAddr,
+    /// the message
+    pub msg: T,
+}
+
+impl<T: Serialize> Call<T> {
+    /// Returns the JSON of the message, as passed to `secretcli tx compute execute`
+    pub fn to_json(&self) -> StdResult<String> {
+        to_json(&self.msg)
+    }
+}
+
+/// Returns the JSON of a message
+///
+/// # Arguments
+///
+/// * `msg` - the message
+pub(crate) fn to_json<T: Serialize>(msg: &T) -> StdResult<String> {
+    String::from_utf8(to_vec(msg)?)
+        .map_err(|e| StdError::generic_err(format!("Message is not UTF-8: {}", e)))
+}
+
+/// the fields of `secretcli q compute tx` output that hold the contract's answer
+#[derive(Deserialize)]
+struct TxOutput {
+    /// answer of the contract, decrypted
+    #[serde(default)]
+    output_data_as_string: Option<String>,
+    /// error returned by the contract, decrypted
+    #[serde(default)]
+    output_error: Option<serde_json::Value>,
+}
+
+/// Returns the answer a contract gave to an executed message, decoded from the JSON output of
+/// `secretcli q compute tx`.  An error returned by the contract is returned as a generic error
+/// holding the contract's message, which for the auction is a JSON object with the error code
+///
+/// # Arguments
+///
+/// * `output` - JSON output of `secretcli q compute tx`
+pub fn decode_tx_answer<T: DeserializeOwned>(output: &[u8]) -> StdResult<T> {
+    let output: TxOutput = serde_json::from_slice(output)
+        .map_err(|e| StdError::parse_err("secretcli tx output", e.to_string()))?;
+    // secretcli prints an empty object when the contract did not fail
+    let error = output
+        .output_error
+        .filter(|e| e.as_object().map_or(!e.is_null(), |e| !e.is_empty()));
+    if let Some(error) = error {
+        let msg = error
+            .get("generic_err")
+            .and_then(|e| e.get("msg"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Err(StdError::generic_err(msg));
+    }
+    match output.output_data_as_string {
+        Some(data) if !data.is_empty() => from_slice(data.as_bytes()),
+        _ => Err(StdError::generic_err("The transaction has no answer")),
+    }
+}
+
+/// error returned by the auction, which states a stable code besides its message
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct CodedError {
+    /// stable code of the error
+    pub code: u16,
+    /// description of the error
+    pub message: String,
+}
+
+/// Returns the code and message of an error decoded by `decode_tx_answer`, None if the contract
+/// did not return a coded error
+///
+/// # Arguments
+///
+/// * `err` - reference to the error
+pub fn decode_coded_error(err: &StdError) -> Option<CodedError> {
+    match err {
+        StdError::GenericErr { msg, .. } => serde_json::from_str(msg).ok(),
+        _ => None,
+    }
+}
+
+/// Returns the answer to a query, decoded from the JSON output of `secretcli q compute query`
+///
+/// # Arguments
+///
+/// * `output` - JSON output of `secretcli q compute query`
+pub fn decode_query_answer<T: DeserializeOwned>(output: &[u8]) -> StdResult<T> {
+    from_slice(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diploma_master::msg::{HandleAnswer, ResponseStatus};
+
+    #[test]
+    fn decodes_answer_and_contract_error() {
+        let output = br#"{"output_data_as_string":"{\"close_auction\":{\"status\":\"Success\",\"message\":\"Outstanding funds have been returned\"}}","output_error":{}}"#;
+        match decode_tx_answer(output).unwrap() {
+            HandleAnswer::CloseAuction {
+                status, message, ..
+            } => {
+                assert_eq!(status, ResponseStatus::Success);
+                assert_eq!(message, "Outstanding funds have been returned");
+            }
+            _ => panic!("Unexpected answer"),
+        }
+
+        let output = br#"{"output_data_as_string":"","output_error":{"generic_err":{"msg":"{\"code\":501,\"message\":\"Only auction creator can finalize the sale\"}"}}}"#;
+        let err = decode_tx_answer::<HandleAnswer>(output).unwrap_err();
+        assert_eq!(
+            decode_coded_error(&err),
+            Some(CodedError {
+                code: 501,
+                message: "Only auction creator can finalize the sale".to_string(),
+            })
+        );
+        assert_eq!(decode_coded_error(&StdError::generic_err("not coded")), None);
+    }
+}
@@ -58,7 +58,16 @@ then
     goodinp=false
     while [ $goodinp == false ]
     do
-        resp=$(secretcli tx compute instantiate $contractcode "{}" --from "$addr" \
+        echo -e "\nWhat is the code hash of the auction contract?  Its auctions may open exposures,"
+        echo -e "and auctions of any other code hash fail at init until set_exposure_callers names it"
+        read auctionhash
+        goodinp=true
+    done
+
+    goodinp=false
+    while [ $goodinp == false ]
+    do
+        resp=$(secretcli tx compute instantiate $contractcode "{\"auction_code_hash\":\"$auctionhash\"}" --from "$addr" \
             --label "oracle" --gas 300000 --broadcast-mode block --trust-node=true \
             -o json -y 2>&1)
         if echo $resp | grep "label already exists"
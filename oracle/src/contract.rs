@@ -1,11 +1,15 @@
-use cosmwasm_std::{to_binary, Api, Env, Extern, HandleResponse, InitResponse, Querier, Storage, HumanAddr, QueryResult, HandleResult, InitResult};
+use cosmwasm_std::{log, to_binary, Api, Env, Extern, HandleResponse, InitResponse, Querier, Storage, HumanAddr, QueryResult, HandleResult, InitResult, StdError, StdResult, Uint128};
 
-use crate::msg::{HandleMsg, InitMsg, QueryMsg, QueryResponse, History, CONFIG_KEY};
-use crate::state::{State, save, load};
-use std::collections::HashMap;
+use secret_toolkit::utils::Query;
+
+use crate::error::ContractError;
+use crate::msg::{HandleMsg, HandleAnswer, InitMsg, QueryMsg, QueryResponse, ExposureResponse, Exposure, History, RejectedHistory, AuctionQueryMsg, AuctionQueryAnswer, EXPOSURE_PER_SCORE, EXPOSURE_REFERENCE_PAYMENT, MAX_HISTORIES_PER_BATCH};
+use crate::state::{State, ExposureCallers, load_state, save_state, migrate_state, save_exposures, load_exposures, save_history, load_history, save_callers, load_callers, STATE_VERSION};
+use std::collections::HashSet;
 
 ////////////////////////////////////// Init ///////////////////////////////////////
-/// Initializes the oracle state
+/// Initializes the oracle state, seeded with the histories of the InitMsg and its reporter set.
+/// The histories are validated like those of AddHistory, and any invalid one fails the init
 ///
 /// # Arguments
 ///
@@ -15,16 +19,44 @@ use std::collections::HashMap;
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    _msg: InitMsg,
+    msg: InitMsg,
 ) -> InitResult {
+    let mut entries = msg.histories.unwrap_or_default();
+    match (msg.user, msg.history) {
+        (Some(user), Some(history)) => entries.insert(0, (user, history)),
+        (None, None) => {}
+        _ => return Err(ContractError::UnpairedHistory.into()),
+    }
+    if entries.len() > MAX_HISTORIES_PER_BATCH {
+        return Err(ContractError::TooManyGenesisHistories { max: MAX_HISTORIES_PER_BATCH }.into());
+    }
+    let mut users = HashSet::new();
+    for (user, history) in &entries {
+        let problems = history.problems();
+        if !problems.is_empty() {
+            return Err(ContractError::InvalidGenesisHistory { user: user.clone(), problems: problems.join("; ") }.into());
+        }
+        if !users.insert(user) {
+            return Err(ContractError::DuplicateGenesisUser { user: user.clone() }.into());
+        }
+        save_history(&mut deps.storage, user, history)?;
+    }
     let state = State {
-        histories: HashMap::new(),
         owner: env.message.sender,
+        reporters: msg.reporters.unwrap_or_default(),
     };
 
-    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    save_state(&mut deps.storage, &state)?;
+    let callers = ExposureCallers {
+        auction_code_hash: msg.auction_code_hash,
+        factories: msg.factories.unwrap_or_default(),
+    };
+    save_callers(&mut deps.storage, &callers)?;
 
-    Ok(InitResponse::default())
+    Ok(InitResponse {
+        messages: vec![],
+        log: vec![log("histories", entries.len()), log("reporters", state.reporters.len())],
+    })
 }
 
 ///////////////////////////////////// Handle //////////////////////////////////////
@@ -40,9 +72,34 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
+    // upgrade state saved by an older version of the contract before handling anything
+    let migrated_from = migrate_state(&mut deps.storage)?;
     match msg {
         HandleMsg::AddHistory {user, history} => try_add_history(deps, env, user, history),
+        HandleMsg::AddHistories {entries} => try_add_histories(deps, env, entries),
+        HandleMsg::OpenExposure {borrower, amount, payment, auction_id} => try_open_exposure(deps, env, borrower, amount, payment, auction_id),
+        HandleMsg::SettleExposure {borrower, outstanding, auction_id} => try_settle_exposure(deps, env, borrower, outstanding, auction_id),
+        HandleMsg::SetReporters {reporters} => try_set_reporters(deps, env, reporters),
+        HandleMsg::SetExposureCallers {auction_code_hash, factories} => try_set_exposure_callers(deps, env, auction_code_hash, factories),
+        HandleMsg::Migrate {} => try_migrate(migrated_from),
+    }
+}
+
+/// Report the state migration performed before handling the message
+///
+/// # Arguments
+///
+/// * `migrated_from` - version the state was upgraded from, None if it was already current
+pub fn try_migrate(migrated_from: Option<u16>) -> HandleResult {
+    let mut logs = vec![log("state_version", STATE_VERSION)];
+    if let Some(version) = migrated_from {
+        logs.push(log("migrated_from", version));
     }
+    Ok(HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    })
 }
 
 /// Add credit history for user
@@ -55,15 +112,245 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 /// * `history` - user history
 pub fn try_add_history<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    _env: Env,
+    env: Env,
     user: HumanAddr,
     history: History,
 ) -> HandleResult {
-    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
-    state.histories.insert(user, history);
-    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    history.validate()?;
+    let state = load_state(&deps.storage)?;
+    check_reporter(&state, &env.message.sender)?;
+    save_history(&mut deps.storage, &user, &history)?;
+
+    Ok(HandleResponse::default())
+}
+
+/// Add the credit histories of many users, skipping and reporting the invalid entries
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `entries` - user addresses and their histories
+pub fn try_add_histories<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entries: Vec<(HumanAddr, History)>,
+) -> HandleResult {
+    if entries.is_empty() {
+        return Err(ContractError::NoHistories.into());
+    }
+    if entries.len() > MAX_HISTORIES_PER_BATCH {
+        return Err(ContractError::TooManyHistories { max: MAX_HISTORIES_PER_BATCH }.into());
+    }
+    let state = load_state(&deps.storage)?;
+    check_reporter(&state, &env.message.sender)?;
+    let mut added: Vec<HumanAddr> = Vec::new();
+    let mut rejected = Vec::new();
+    for (index, (user, history)) in entries.into_iter().enumerate() {
+        let reason = if added.contains(&user) {
+            Some("user appears more than once in the batch".to_string())
+        } else {
+            let problems = history.problems();
+            if problems.is_empty() {
+                None
+            } else {
+                Some(format!("Invalid history: {}", problems.join("; ")))
+            }
+        };
+        match reason {
+            Some(reason) => rejected.push(RejectedHistory { index: index as u32, user, reason }),
+            None => {
+                save_history(&mut deps.storage, &user, &history)?;
+                added.push(user);
+            }
+        }
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("added", added.len()), log("rejected", rejected.len())],
+        data: Some(to_binary(&HandleAnswer::AddHistories { added: added.len() as u32, rejected })?),
+    })
+}
+
+/// Returns StdResult<()> which is an error if the address may not add histories
+///
+/// # Arguments
+///
+/// * `state` - reference to oracle state
+/// * `sender` - address adding histories
+fn check_reporter(state: &State, sender: &HumanAddr) -> StdResult<()> {
+    if !state.may_report(sender) {
+        return Err(StdError::unauthorized());
+    }
+    Ok(())
+}
+
+/// Set the addresses allowed to add histories besides the owner
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `reporters` - addresses allowed to add histories
+pub fn try_set_reporters<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    reporters: Vec<HumanAddr>,
+) -> HandleResult {
+    let mut state = load_state(&deps.storage)?;
+    if env.message.sender != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    state.reporters = reporters;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("reporters", state.reporters.len())],
+        data: None,
+    })
+}
+
+/// Set the contracts that may open exposures
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `auction_code_hash` - Optional code hash of the auction contract
+/// * `factories` - addresses of contracts that host auctions
+pub fn try_set_exposure_callers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    auction_code_hash: Option<String>,
+    factories: Vec<HumanAddr>,
+) -> HandleResult {
+    let state = load_state(&deps.storage)?;
+    if env.message.sender != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    let callers = ExposureCallers { auction_code_hash, factories };
+    save_callers(&mut deps.storage, &callers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("factories", callers.factories.len())],
+        data: None,
+    })
+}
+
+/// Returns StdResult<()> which is an error if the address may not open exposures.  Registered
+/// factories may, and so may instances of the auction code
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `sender` - address opening the exposure
+fn check_exposure_caller<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    sender: &HumanAddr,
+) -> StdResult<()> {
+    let callers = load_callers(&deps.storage)?;
+    if callers.factories.contains(sender) {
+        return Ok(());
+    }
+    if let Some(code_hash) = callers.auction_code_hash {
+        // a query sent with the code hash of other code fails, so only an auction answers it
+        let answer: StdResult<AuctionQueryAnswer> =
+            AuctionQueryMsg::BidCount {}.query(&deps.querier, code_hash, sender.clone());
+        if answer.is_ok() {
+            return Ok(());
+        }
+    }
+    Err(StdError::unauthorized())
+}
+
+/// Returns the address an auction's exposure is recorded under.  Auctions hosted by a contract
+/// with many auctions are recorded as `<contract>#<auction_id>`
+///
+/// # Arguments
+///
+/// * `sender` - address of the contract that hosts the auction
+/// * `auction_id` - Optional id of the auction within that contract
+fn exposure_auction(sender: HumanAddr, auction_id: Option<u64>) -> HumanAddr {
+    match auction_id {
+        Some(id) => HumanAddr(format!("{}#{}", sender, id)),
+        None => sender,
+    }
+}
+
+/// Record an auction opened by a borrower.  The limit of the borrower's total exposure comes
+/// from the score of their stored history at EXPOSURE_REFERENCE_PAYMENT, so every auction of the
+/// borrower shares the same limit whatever payment it declares
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `borrower` - borrower that opened the auction
+/// * `amount` - amount of credit requested
+/// * `payment` - alleged sum the borrower pays, which the history must score high enough at
+/// * `auction_id` - Optional id of the auction, for contracts that host many auctions
+pub fn try_open_exposure<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    borrower: HumanAddr,
+    amount: Uint128,
+    payment: Uint128,
+    auction_id: Option<u64>,
+) -> HandleResult {
+    check_exposure_caller(deps, &env.message.sender)?;
+    let score = load_history(&deps.storage, &borrower)?
+        .filter(|history| history.score(payment).is_some())
+        .and_then(|history| history.score(Uint128(EXPOSURE_REFERENCE_PAYMENT)))
+        .ok_or(ContractError::NoScoringHistory)?;
+    let limit = score.saturating_mul(EXPOSURE_PER_SCORE);
+
+    let auction = exposure_auction(env.message.sender, auction_id);
+    let mut exposures = load_exposures(&deps.storage, &borrower)?;
+    if exposures.iter().any(|exposure| exposure.auction == auction) {
+        return Err(ContractError::ExposureRecorded.into());
+    }
+    let total = exposures.iter().fold(0u128, |total, exposure| total.saturating_add(exposure.amount.u128()));
+    if total.saturating_add(amount.u128()) > limit {
+        return Err(ContractError::ExposureLimit { total, amount: amount.u128(), limit }.into());
+    }
+    exposures.push(Exposure { auction, amount, is_loan: false });
+    save_exposures(&mut deps.storage, &borrower, &exposures)?;
+
+    Ok(HandleResponse::default())
+}
+
+/// Settle the exposure recorded by the calling auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `borrower` - borrower that opened the auction
+/// * `outstanding` - true if the auction resulted in a loan that is still outstanding
+/// * `auction_id` - Optional id of the auction, for contracts that host many auctions
+pub fn try_settle_exposure<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    borrower: HumanAddr,
+    outstanding: bool,
+    auction_id: Option<u64>,
+) -> HandleResult {
+    let auction = exposure_auction(env.message.sender, auction_id);
+    let mut exposures = load_exposures(&deps.storage, &borrower)?;
+    let position = exposures
+        .iter()
+        .position(|exposure| exposure.auction == auction)
+        .ok_or(ContractError::NoExposure)?;
+    if outstanding {
+        exposures[position].is_loan = true;
+    } else {
+        exposures.remove(position);
+    }
+    save_exposures(&mut deps.storage, &borrower, &exposures)?;
 
-    print!("History for user added successfully");
     Ok(HandleResponse::default())
 }
 
@@ -80,20 +367,31 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
 ) -> QueryResult {
     match msg {
         QueryMsg::GetHistory {user} => query_get_history(deps, user),
+        QueryMsg::GetExposure {user} => query_get_exposure(deps, user),
     }
 }
 
+fn query_get_exposure<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    user: HumanAddr
+) -> QueryResult {
+    let exposures = load_exposures(&deps.storage, &user)?;
+    let outstanding_loans = exposures.iter().filter(|exposure| exposure.is_loan).count() as u32;
+    let total = exposures.iter().fold(0u128, |total, exposure| total.saturating_add(exposure.amount.u128()));
+    to_binary(&ExposureResponse {
+        open_auctions: exposures.len() as u32 - outstanding_loans,
+        outstanding_loans,
+        total: Uint128(total),
+    })
+}
+
 fn query_get_history<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     user: HumanAddr
 ) -> QueryResult {
-    let state: State = load(&deps.storage, CONFIG_KEY)?;
-    let hists = state.histories;
-    if hists.contains_key(&user) {
-        let history = &hists[&user];
-        to_binary(&QueryResponse { history: Some(History{ debts: history.debts, credits: history.credits.clone()}), message: String::from("History for user found")})
-    } else {
-        to_binary(&QueryResponse { history: None, message: String::from("No history for user found")})
+    match load_history(&deps.storage, &user)? {
+        Some(history) => to_binary(&QueryResponse { history: Some(history), message: String::from("History for user found")}),
+        None => to_binary(&QueryResponse { history: None, message: String::from("No history for user found")}),
     }
 }
 
@@ -101,8 +399,11 @@ fn query_get_history<S: Storage, A: Api, Q: Querier>(
 mod tests {
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, MockStorage, MockApi, MockQuerier};
-    use cosmwasm_std::{from_binary, Uint128};
-    use crate::msg::Credit;
+    use cosmwasm_std::{from_binary, ReadonlyStorage, Uint128};
+    use cosmwasm_storage::ReadonlyPrefixedStorage;
+    use crate::msg::{Credit, CONFIG_KEY, PREFIX_HISTORIES, VERSION_KEY};
+    use crate::state::{save, stored_version, StateV2};
+    use crate::fixtures::sample_history;
 
     fn init_helper() -> (
             InitResult,
@@ -111,8 +412,22 @@ mod tests {
         let mut deps = mock_dependencies(20, &[]);
         let env = mock_env("oracle", &[]);
 
-        let init_msg = InitMsg {user: None, history: None};
-        (init(&mut deps, env, init_msg), deps)
+        (init(&mut deps, env, InitMsg::default()), deps)
+    }
+
+    /// oracle with the sample history for "user", which scores 1666 in an auction of 1200000,
+    /// and the auctions of the tests registered as factories
+    fn exposure_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let factories = ["auction1", "auction2", "market"].iter().map(|address| HumanAddr(address.to_string())).collect();
+        let init_msg = InitMsg {
+            user: Some(HumanAddr("user".to_string())),
+            history: Some(sample_history()),
+            factories: Some(factories),
+            ..InitMsg::default()
+        };
+        init(&mut deps, mock_env("oracle", &[]), init_msg).unwrap();
+        deps
     }
 
     #[test]
@@ -166,4 +481,275 @@ mod tests {
         let value: QueryResponse = from_binary(&query_result).unwrap();
         assert_ne!(None, value.history);
     }
+
+    #[test]
+    fn exposure_limit() {
+        let mut deps = exposure_helper();
+        let borrower = HumanAddr("user".to_string());
+
+        // the score of 1666 allows an exposure of 1666000
+        let open = |auction: &str, deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            let handle_msg = HandleMsg::OpenExposure {borrower: HumanAddr("user".to_string()), amount: Uint128(1000000), payment: Uint128(1200000), auction_id: None};
+            handle(deps, mock_env(auction, &[]), handle_msg)
+        };
+        assert!(open("auction1", &mut deps).is_ok());
+        assert!(open("auction2", &mut deps).is_err(), "Second auction exceeds the limit");
+
+        // an auction that closed with a loan keeps its exposure
+        let handle_msg = HandleMsg::SettleExposure {borrower: borrower.clone(), outstanding: true, auction_id: None};
+        handle(&mut deps, mock_env("auction1", &[]), handle_msg).unwrap();
+        let query_result = query(&deps, QueryMsg::GetExposure {user: borrower.clone()}).unwrap();
+        let value: ExposureResponse = from_binary(&query_result).unwrap();
+        assert_eq!(ExposureResponse {open_auctions: 0, outstanding_loans: 1, total: Uint128(1000000)}, value);
+        assert!(open("auction2", &mut deps).is_err(), "Outstanding loan still counts");
+
+        // settling the loan as no longer outstanding releases the exposure
+        let handle_msg = HandleMsg::SettleExposure {borrower: borrower.clone(), outstanding: false, auction_id: None};
+        handle(&mut deps, mock_env("auction1", &[]), handle_msg).unwrap();
+        assert!(open("auction2", &mut deps).is_ok());
+    }
+
+    #[test]
+    fn migrate_unversioned_state() {
+        // state saved by the first version of the contract, which stored no version
+        let mut deps = mock_dependencies(20, &[]);
+        deps.storage.set(CONFIG_KEY, include_bytes!("../tests/fixtures/state_v1.bin"));
+        assert_eq!(stored_version(&deps.storage).unwrap(), 1);
+
+        let user = HumanAddr("secret1user".to_string());
+        let query_result = query(&deps, QueryMsg::GetHistory {user: user.clone()}).unwrap();
+        let value: QueryResponse = from_binary(&query_result).unwrap();
+        let history = value.history.unwrap();
+        assert_eq!(Uint128(1000000), history.debts);
+        assert_eq!(vec![Credit {sum: Uint128(1000000), interest_rate: Uint128(6), time: Uint128(12), is_closed: true}], history.credits);
+
+        let handle_result = handle(&mut deps, mock_env("anyone", &[]), HandleMsg::Migrate {}).unwrap();
+        assert_eq!(vec![log("state_version", STATE_VERSION), log("migrated_from", 1)], handle_result.log);
+        let state = load_state(&deps.storage).unwrap();
+        assert_eq!(HumanAddr("secret1owner".to_string()), state.owner);
+        // the history moved out of the state, under its user
+        let stored = ReadonlyPrefixedStorage::new(PREFIX_HISTORIES, &deps.storage);
+        assert!(stored.get(user.0.as_bytes()).is_some());
+        assert_eq!(Some(history), load_history(&deps.storage, &user).unwrap());
+
+        let handle_result = handle(&mut deps, mock_env("anyone", &[]), HandleMsg::Migrate {}).unwrap();
+        assert_eq!(vec![log("state_version", STATE_VERSION)], handle_result.log);
+    }
+
+    #[test]
+    fn migrated_unversioned_state_only_lets_the_owner_add_histories() {
+        // the first version let anyone add histories, and migrating it names no reporters
+        let mut deps = mock_dependencies(20, &[]);
+        deps.storage.set(CONFIG_KEY, include_bytes!("../tests/fixtures/state_v1.bin"));
+        handle(&mut deps, mock_env("anyone", &[]), HandleMsg::Migrate {}).unwrap();
+        assert!(load_state(&deps.storage).unwrap().reporters.is_empty());
+
+        let add = || HandleMsg::AddHistory {user: HumanAddr("user".to_string()), history: sample_history()};
+        let add_many = || HandleMsg::AddHistories {entries: vec![(HumanAddr("user".to_string()), sample_history())]};
+        let handle_result = handle(&mut deps, mock_env("anyone", &[]), add());
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        let handle_result = handle(&mut deps, mock_env("anyone", &[]), add_many());
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        assert!(handle(&mut deps, mock_env("secret1owner", &[]), add()).is_ok());
+
+        // the owner restores access by naming the reporters
+        let set_reporters = HandleMsg::SetReporters {reporters: vec![HumanAddr("anyone".to_string())]};
+        handle(&mut deps, mock_env("secret1owner", &[]), set_reporters).unwrap();
+        assert!(handle(&mut deps, mock_env("anyone", &[]), add()).is_ok());
+        assert!(handle(&mut deps, mock_env("anyone", &[]), add_many()).is_ok());
+    }
+
+    #[test]
+    fn migrate_state_without_reporters() {
+        // state saved by the second version of the contract, before reporters were kept
+        let mut deps = mock_dependencies(20, &[]);
+        save(&mut deps.storage, CONFIG_KEY, &StateV2 {owner: HumanAddr("owner".to_string())}).unwrap();
+        save(&mut deps.storage, VERSION_KEY, &2u16).unwrap();
+        assert_eq!(HumanAddr("owner".to_string()), load_state(&deps.storage).unwrap().owner);
+
+        let handle_result = handle(&mut deps, mock_env("anyone", &[]), HandleMsg::Migrate {}).unwrap();
+        assert_eq!(vec![log("state_version", STATE_VERSION), log("migrated_from", 2)], handle_result.log);
+        assert_eq!(stored_version(&deps.storage).unwrap(), STATE_VERSION);
+        let state = load_state(&deps.storage).unwrap();
+        assert_eq!(HumanAddr("owner".to_string()), state.owner);
+        assert!(state.reporters.is_empty());
+
+        // only the owner can add histories until they name some reporters
+        let add = || HandleMsg::AddHistory {user: HumanAddr("user".to_string()), history: sample_history()};
+        let handle_result = handle(&mut deps, mock_env("anyone", &[]), add());
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        assert!(handle(&mut deps, mock_env("owner", &[]), add()).is_ok());
+    }
+
+    #[test]
+    fn marketplace_exposures() {
+        let mut deps = exposure_helper();
+        let borrower = HumanAddr("user".to_string());
+
+        let open = |auction_id: u64, deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            let handle_msg = HandleMsg::OpenExposure {borrower: HumanAddr("user".to_string()), amount: Uint128(800000), payment: Uint128(1200000), auction_id: Some(auction_id)};
+            handle(deps, mock_env("market", &[]), handle_msg)
+        };
+        // auctions hosted by the same contract are recorded separately
+        assert!(open(0, &mut deps).is_ok());
+        assert!(open(1, &mut deps).is_ok());
+        assert!(open(1, &mut deps).is_err(), "Auction already recorded its exposure");
+
+        let handle_msg = HandleMsg::SettleExposure {borrower: borrower.clone(), outstanding: false, auction_id: Some(0)};
+        handle(&mut deps, mock_env("market", &[]), handle_msg).unwrap();
+        let query_result = query(&deps, QueryMsg::GetExposure {user: borrower}).unwrap();
+        let value: ExposureResponse = from_binary(&query_result).unwrap();
+        assert_eq!(ExposureResponse {open_auctions: 1, outstanding_loans: 0, total: Uint128(800000)}, value);
+    }
+
+    #[test]
+    fn exposure_callers_and_limits() {
+        let mut deps = exposure_helper();
+        let open = |borrower: &str, payment: u128| HandleMsg::OpenExposure {
+            borrower: HumanAddr(borrower.to_string()), amount: Uint128(1000), payment: Uint128(payment), auction_id: None
+        };
+
+        // contracts that are neither factories nor answer as the auction code are refused
+        let handle_result = handle(&mut deps, mock_env("stranger", &[]), open("user", 1200000));
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        let set_callers = |factories: &[&str]| HandleMsg::SetExposureCallers {
+            auction_code_hash: Some("auctionhash".to_string()),
+            factories: factories.iter().map(|address| HumanAddr(address.to_string())).collect(),
+        };
+        let handle_result = handle(&mut deps, mock_env("stranger", &[]), set_callers(&["stranger"]));
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        handle(&mut deps, mock_env("oracle", &[]), set_callers(&[])).unwrap();
+        let handle_result = handle(&mut deps, mock_env("stranger", &[]), open("user", 1200000));
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        handle(&mut deps, mock_env("oracle", &[]), set_callers(&["stranger"])).unwrap();
+
+        // the limit comes from the stored history, so borrowers without one can not borrow
+        assert!(handle(&mut deps, mock_env("stranger", &[]), open("nobody", 1200000)).is_err());
+        assert!(handle(&mut deps, mock_env("stranger", &[]), open("user", 0)).is_err());
+        assert!(handle(&mut deps, mock_env("stranger", &[]), open("user", 1200000)).is_ok());
+        assert_eq!(Some(1666), sample_history().score(Uint128(1200000)));
+    }
+
+    #[test]
+    fn exposure_limit_ignores_the_declared_payment() {
+        let mut deps = exposure_helper();
+        let open = |auction: &str, amount: u128, payment: u128, deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            let handle_msg = HandleMsg::OpenExposure {borrower: HumanAddr("user".to_string()), amount: Uint128(amount), payment: Uint128(payment), auction_id: None};
+            handle(deps, mock_env(auction, &[]), handle_msg)
+        };
+
+        // a larger payment scores higher, but both auctions share the limit of 1666000
+        assert_eq!(Some(251666), sample_history().score(Uint128(120000000)));
+        assert!(open("auction2", 2000000, 120000000, &mut deps).is_err(), "Payment raised the limit");
+        assert!(open("auction1", 1000000, 1200000, &mut deps).is_ok());
+        let handle_result = open("auction2", 666001, 120000000, &mut deps);
+        assert!(handle_result.unwrap_err().to_string().contains("limit of 1666000"));
+        assert!(open("auction2", 666000, 120000000, &mut deps).is_ok());
+    }
+
+    #[test]
+    fn history_validation() {
+        let credit = |sum: u128, interest_rate: u128, time: u128, is_closed: bool| Credit {
+            sum: Uint128(sum), interest_rate: Uint128(interest_rate), time: Uint128(time), is_closed
+        };
+        let history = History {debts: Uint128(100000), credits: vec![credit(1000000, 6, 12, true), credit(500000, 10, 9, false)]};
+        assert!(history.validate().is_ok());
+
+        let history = History {debts: Uint128(600000), credits: vec![credit(1000000, 0, 0, true), credit(500000, 1000, 9, false)]};
+        let problems = history.problems();
+        assert_eq!(3, problems.len(), "{:?}", problems);
+        assert!(problems[0].starts_with("credit 0: time"));
+        assert!(problems[1].starts_with("credit 1: interest rate"));
+        assert!(problems[2].starts_with("debts of 600000"));
+        assert!(History {debts: Uint128(0), credits: vec![]}.validate().is_err());
+
+        // a valid history whose debts outweigh its credits scores nothing, instead of wrapping
+        let history = History {debts: Uint128(1000000), credits: vec![credit(1000000, 10, 12, false)]};
+        assert!(history.validate().is_ok());
+        assert_eq!(None, history.score(Uint128(1000000)));
+    }
+
+    #[test]
+    fn add_histories() {
+        let (_init_result, mut deps) = init_helper();
+        let credit = |interest_rate: u128, time: u128| Credit {
+            sum: Uint128(1000000), interest_rate: Uint128(interest_rate), time: Uint128(time), is_closed: true
+        };
+        let history = |time: u128| History {debts: Uint128(0), credits: vec![credit(6, time)]};
+        let entries = vec![
+            (HumanAddr("user0".to_string()), history(12)),
+            (HumanAddr("user1".to_string()), history(0)),
+            (HumanAddr("user0".to_string()), history(24)),
+            (HumanAddr("user2".to_string()), History {debts: Uint128(0), credits: vec![credit(6, 6), credit(7, 9)]}),
+        ];
+        // with no reporters only the owner may add histories, until it names some
+        let add = |entries| HandleMsg::AddHistories {entries};
+        let handle_result = handle(&mut deps, mock_env("reporter", &[]), add(entries.clone()));
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        let set_reporters = |names: &[&str]| HandleMsg::SetReporters {reporters: names.iter().map(|name| HumanAddr(name.to_string())).collect()};
+        assert!(handle(&mut deps, mock_env("reporter", &[]), set_reporters(&["reporter"])).is_err());
+        let handle_result = handle(&mut deps, mock_env("oracle", &[]), set_reporters(&["reporter"])).unwrap();
+        assert_eq!(vec![log("reporters", 1)], handle_result.log);
+        let handle_result = handle(&mut deps, mock_env("reporter", &[]), add(entries)).unwrap();
+        match from_binary(&handle_result.data.unwrap()).unwrap() {
+            HandleAnswer::AddHistories {added, rejected} => {
+                assert_eq!(2, added);
+                assert_eq!(vec![1, 2], rejected.iter().map(|r| r.index).collect::<Vec<_>>());
+                assert!(rejected[0].reason.contains("credit 0: time"));
+                assert!(rejected[1].reason.contains("more than once"));
+            }
+        }
+        let stored = |name: &str| load_history(&deps.storage, &HumanAddr(name.to_string())).unwrap();
+        assert_eq!(Uint128(12), stored("user0").unwrap().credits[0].time);
+        assert_eq!(None, stored("user1"));
+        assert_eq!(2, stored("user2").unwrap().credits.len());
+
+        let entries = (0..=MAX_HISTORIES_PER_BATCH).map(|i| (HumanAddr(format!("user{}", i)), history(12))).collect();
+        assert!(handle(&mut deps, mock_env("reporter", &[]), HandleMsg::AddHistories {entries}).is_err());
+        assert!(handle(&mut deps, mock_env("reporter", &[]), HandleMsg::AddHistories {entries: vec![]}).is_err());
+
+        handle(&mut deps, mock_env("oracle", &[]), set_reporters(&[])).unwrap();
+        let entries = vec![(HumanAddr("user3".to_string()), history(12))];
+        let handle_result = handle(&mut deps, mock_env("reporter", &[]), HandleMsg::AddHistories {entries});
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+    }
+
+    #[test]
+    fn genesis_histories_and_reporters() {
+        let credit = |time: u128| Credit {
+            sum: Uint128(1000000), interest_rate: Uint128(6), time: Uint128(time), is_closed: true
+        };
+        let history = |time: u128| History {debts: Uint128(0), credits: vec![credit(time)]};
+        let user = |name: &str| HumanAddr(name.to_string());
+        let init_msg = |histories: Vec<(HumanAddr, History)>| InitMsg {
+            user: Some(user("user0")),
+            history: Some(history(12)),
+            histories: Some(histories),
+            reporters: Some(vec![user("reporter")]),
+            ..InitMsg::default()
+        };
+
+        let mut deps = mock_dependencies(20, &[]);
+        let init_result = init(&mut deps, mock_env("oracle", &[]), init_msg(vec![(user("user1"), history(9))])).unwrap();
+        assert_eq!(vec![log("histories", 2), log("reporters", 1)], init_result.log);
+        assert_eq!(Uint128(12), load_history(&deps.storage, &user("user0")).unwrap().unwrap().credits[0].time);
+        assert_eq!(Uint128(9), load_history(&deps.storage, &user("user1")).unwrap().unwrap().credits[0].time);
+
+        let add = || HandleMsg::AddHistory {user: user("user2"), history: history(6)};
+        let handle_result = handle(&mut deps, mock_env("stranger", &[]), add());
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+        assert!(handle(&mut deps, mock_env("reporter", &[]), add()).is_ok());
+        assert!(handle(&mut deps, mock_env("oracle", &[]), add()).is_ok());
+        let entries = vec![(user("user3"), history(6))];
+        let handle_result = handle(&mut deps, mock_env("stranger", &[]), HandleMsg::AddHistories {entries});
+        assert!(matches!(handle_result, Err(StdError::Unauthorized { .. })));
+
+        let mut deps = mock_dependencies(20, &[]);
+        let init_result = init(&mut deps, mock_env("oracle", &[]), init_msg(vec![(user("user1"), history(0))]));
+        assert!(init_result.unwrap_err().to_string().contains("Invalid history of user1: credit 0: time"));
+        let init_result = init(&mut deps, mock_env("oracle", &[]), init_msg(vec![(user("user0"), history(9))]));
+        assert!(init_result.unwrap_err().to_string().contains("more than one history"));
+        let init_msg = InitMsg {user: Some(user("user0")), ..InitMsg::default()};
+        assert!(init(&mut deps, mock_env("oracle", &[]), init_msg).is_err());
+    }
 }
@@ -0,0 +1,109 @@
+use serde::Serialize;
+use snafu::Snafu;
+
+use cosmwasm_std::{HumanAddr, StdError};
+
+/// errors of the oracle contract.  Each error has a stable code, so clients can branch on the
+/// code instead of the message
+#[derive(Snafu, Debug)]
+#[snafu(visibility = "pub")]
+pub enum ContractError {
+    /// init: only one of the user and the history was given
+    #[snafu(display("user and history must be given together"))]
+    UnpairedHistory,
+    /// init: more histories were given than one batch may add
+    #[snafu(display(
+        "Can not start with more than {} histories, add the rest with AddHistories",
+        max
+    ))]
+    TooManyGenesisHistories { max: usize },
+    /// init: a history given is invalid
+    #[snafu(display("Invalid history of {}: {}", user, problems))]
+    InvalidGenesisHistory { user: HumanAddr, problems: String },
+    /// init: a user was given more than one history
+    #[snafu(display("{} is given more than one history", user))]
+    DuplicateGenesisUser { user: HumanAddr },
+    /// add history: the history is invalid
+    #[snafu(display("Invalid history: {}", problems))]
+    InvalidHistory { problems: String },
+    /// add histories: the batch is empty
+    #[snafu(display("No histories to add"))]
+    NoHistories,
+    /// add histories: the batch is larger than the maximum
+    #[snafu(display("Can not add more than {} histories at a time, split the batch", max))]
+    TooManyHistories { max: usize },
+    /// open exposure: the borrower has no history, or it scores too low
+    #[snafu(display("Borrower has no credit history that scores high enough to borrow"))]
+    NoScoringHistory,
+    /// open exposure: the auction opened its exposure before
+    #[snafu(display("Auction has already recorded its exposure"))]
+    ExposureRecorded,
+    /// open exposure: the borrower's total exposure would pass their limit
+    #[snafu(display(
+        "Borrower exposure of {} plus {} requested would exceed the limit of {}",
+        total,
+        amount,
+        limit
+    ))]
+    ExposureLimit { total: u128, amount: u128, limit: u128 },
+    /// settle exposure: the auction has no exposure to settle
+    #[snafu(display("No exposure recorded by this auction"))]
+    NoExposure,
+}
+
+impl ContractError {
+    /// Returns the stable code of the error
+    pub fn code(&self) -> u16 {
+        match self {
+            ContractError::UnpairedHistory => 100,
+            ContractError::TooManyGenesisHistories { .. } => 101,
+            ContractError::InvalidGenesisHistory { .. } => 102,
+            ContractError::DuplicateGenesisUser { .. } => 103,
+            ContractError::InvalidHistory { .. } => 200,
+            ContractError::NoHistories => 201,
+            ContractError::TooManyHistories { .. } => 202,
+            ContractError::NoScoringHistory => 300,
+            ContractError::ExposureRecorded => 301,
+            ContractError::ExposureLimit { .. } => 302,
+            ContractError::NoExposure => 303,
+        }
+    }
+}
+
+/// machine-readable form of a contract error
+#[derive(Serialize)]
+struct ErrorResponse {
+    /// stable code of the error
+    code: u16,
+    /// description of the error
+    message: String,
+}
+
+impl From<ContractError> for StdError {
+    /// Returns a generic error whose message is the JSON `{"code": .., "message": ..}` of the
+    /// contract error
+    fn from(err: ContractError) -> Self {
+        let response = ErrorResponse {
+            code: err.code(),
+            message: err.to_string(),
+        };
+        StdError::generic_err(serde_json::to_string(&response).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_code_and_message() {
+        let err: StdError = ContractError::ExposureLimit { total: 1000, amount: 1, limit: 1000 }.into();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(
+                msg,
+                r#"{"code":302,"message":"Borrower exposure of 1000 plus 1 requested would exceed the limit of 1000"}"#
+            ),
+            _ => panic!("Unexpected error"),
+        }
+    }
+}
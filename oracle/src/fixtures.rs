@@ -0,0 +1,26 @@
+//! Fixtures shared by the tests of the oracle, the auction and the simulator
+
+use cosmwasm_std::Uint128;
+
+use crate::msg::{Credit, History};
+
+/// Returns the credit history in `auction/history.json`, which scores 1666 for a payment of
+/// 1200000
+pub fn sample_history() -> History {
+    let credit = |sum: u128, interest_rate: u128, time: u128, is_closed: bool| Credit {
+        sum: Uint128(sum),
+        interest_rate: Uint128(interest_rate),
+        time: Uint128(time),
+        is_closed,
+    };
+    History {
+        debts: Uint128(100000),
+        credits: vec![
+            credit(1000000, 6, 12, true),
+            credit(100000, 5, 6, false),
+            credit(500000, 10, 9, false),
+            credit(200000, 7, 6, true),
+            credit(150000, 10, 12, true),
+        ],
+    }
+}
@@ -1,4 +1,7 @@
 pub mod contract;
+pub mod error;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
 pub mod msg;
 pub mod state;
 
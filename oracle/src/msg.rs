@@ -1,32 +1,164 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{Uint128, HumanAddr};
+use cosmwasm_std::{Uint128, HumanAddr, StdResult};
+use secret_toolkit::utils::Query;
+use scoring::ScoredCredit;
+
+use crate::error::ContractError;
 
 /// storage key for oracle state
 pub static CONFIG_KEY: &[u8] = b"config";
 
+/// storage key for the version of the stored oracle state layout
+pub static VERSION_KEY: &[u8] = b"version";
+
+/// storage prefix for borrower exposures
+pub static PREFIX_EXPOSURES: &[u8] = b"exposures";
+
+/// storage key for the contracts that may open exposures
+pub static CALLERS_KEY: &[u8] = b"callers";
+
+/// storage prefix for user credit histories
+pub static PREFIX_HISTORIES: &[u8] = b"histories";
+
 /// block size
 pub const BLOCK_SIZE: usize = 256;
 
+/// most credits a history can hold
+pub const MAX_CREDITS: usize = 100;
+
+/// largest sum of a credit, which keeps the score calculation from overflowing
+pub const MAX_CREDIT_SUM: u128 = 1_000_000_000_000_000_000_000_000_000_000;
+
+/// largest interest rate of a credit (in percent)
+pub const MAX_INTEREST_RATE: u128 = 100;
+
+/// longest time to close a credit (in months)
+pub const MAX_CREDIT_TIME: u128 = 600;
+
+/// most histories one AddHistories can add, which keeps the transaction within gas limits
+pub const MAX_HISTORIES_PER_BATCH: usize = 50;
+
+/// amount of credit each point of score lets a borrower have in open auctions and outstanding
+/// loans
+pub const EXPOSURE_PER_SCORE: u128 = 1_000;
+
+/// payment the exposure limit scores the history at, so an auction can not raise the limit by
+/// declaring a larger payment.  A payment only enters the score through the number of its digits,
+/// so any payment from 1_000_000 to 9_999_999 gives the same limit: that of an auction of a few
+/// whole tokens of a 6 decimal SNIP-20, the size auctions have been opened at so far.  The value
+/// is the payment of that reference auction, and changing it to another number of digits changes
+/// every borrower's limit
+pub const EXPOSURE_REFERENCE_PAYMENT: u128 = 1_200_000;
+
 /// Instantiation message
-#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 pub struct InitMsg {
     /// init user address
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<HumanAddr>,
     /// init user history
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub history: Option<History>
+    pub history: Option<History>,
+    /// Optional user addresses and histories to start with, validated like AddHistory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histories: Option<Vec<(HumanAddr, History)>>,
+    /// Optional addresses allowed to add histories besides the owner.  Only the owner can add
+    /// histories if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reporters: Option<Vec<HumanAddr>>,
+    /// Optional code hash of the auction contract.  Instances of that code may open exposures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auction_code_hash: Option<String>,
+    /// Optional addresses of contracts that host auctions, such as marketplaces, which may open
+    /// exposures for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factories: Option<Vec<HumanAddr>>,
 }
 
 /// Handle message
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
+    /// add or replace the history of a user, only the owner and the reporters can
     AddHistory {
         user: HumanAddr,
         history: History
     },
+    /// add the histories of many users in one transaction.  Invalid entries are skipped and
+    /// reported in the answer, the valid ones are added
+    AddHistories {
+        /// user addresses and their histories, at most MAX_HISTORIES_PER_BATCH
+        entries: Vec<(HumanAddr, History)>,
+    },
+    /// record an auction opened by the borrower, called by an auction or a factory.  Fails if
+    /// it would take the borrower's total exposure over the limit their stored history scores
+    OpenExposure {
+        /// borrower address
+        borrower: HumanAddr,
+        /// amount of credit requested
+        amount: Uint128,
+        /// alleged sum the borrower pays.  The history must score high enough to borrow at it,
+        /// but the limit is scored at EXPOSURE_REFERENCE_PAYMENT
+        payment: Uint128,
+        /// Optional id of the auction, for contracts that host many auctions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auction_id: Option<u64>,
+    },
+    /// settle the exposure recorded by the calling auction contract
+    SettleExposure {
+        /// borrower address
+        borrower: HumanAddr,
+        /// true if the auction resulted in a loan that is still outstanding, false releases the
+        /// exposure
+        outstanding: bool,
+        /// Optional id of the auction, for contracts that host many auctions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auction_id: Option<u64>,
+    },
+    /// set the addresses allowed to add histories besides the owner, only the owner can.  An
+    /// empty list leaves only the owner
+    SetReporters {
+        /// addresses allowed to add histories
+        reporters: Vec<HumanAddr>,
+    },
+    /// set the contracts that may open exposures, only the owner can
+    SetExposureCallers {
+        /// Optional code hash of the auction contract.  Instances of that code may open
+        /// exposures
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auction_code_hash: Option<String>,
+        /// addresses of contracts that host auctions, which may open exposures for them
+        factories: Vec<HumanAddr>,
+    },
+    /// upgrade state saved by an older version of the contract to the current layout.  Any
+    /// later handle does the same.  Older versions let anyone add histories, but a migrated
+    /// oracle has no reporters, so only the owner can until it sets some with SetReporters
+    Migrate {},
+}
+
+/// Responses from handle functions
+#[derive(Serialize, Deserialize, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleAnswer {
+    /// response from adding a batch of histories
+    AddHistories {
+        /// number of histories added
+        added: u32,
+        /// entries that were not added
+        rejected: Vec<RejectedHistory>,
+    },
+}
+
+/// entry of an AddHistories that was not added
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema, PartialEq)]
+pub struct RejectedHistory {
+    /// position of the entry in the batch
+    pub index: u32,
+    /// user address of the entry
+    pub user: HumanAddr,
+    /// why the entry was not added
+    pub reason: String,
 }
 
 /// Query message
@@ -38,6 +170,35 @@ pub enum QueryMsg {
         /// user address
         user: HumanAddr,
     },
+    /// get user exposure query
+    GetExposure {
+        /// user address
+        user: HumanAddr,
+    },
+}
+
+/// Query the oracle sends to the contracts opening exposures.  Only an instance of the code
+/// the query is sent with can answer it
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionQueryMsg {
+    /// number of active bids of the auction
+    BidCount {},
+}
+
+impl Query for AuctionQueryMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// answer of an auction to an AuctionQueryMsg
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionQueryAnswer {
+    /// BidCount query response
+    BidCount {
+        /// number of active bids
+        count: u32,
+    },
 }
 
 /// Query response
@@ -51,6 +212,29 @@ pub struct QueryResponse {
     pub message: String
 }
 
+/// Exposure query response
+#[derive(Serialize, Deserialize, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct ExposureResponse {
+    /// number of open auctions
+    pub open_auctions: u32,
+    /// number of outstanding loans
+    pub outstanding_loans: u32,
+    /// total amount of credit of open auctions and outstanding loans
+    pub total: Uint128,
+}
+
+/// Credit requested or taken by a borrower through an auction
+#[derive(Serialize, Deserialize, Clone, JsonSchema, PartialEq, Debug)]
+pub struct Exposure {
+    /// address of the auction contract
+    pub auction: HumanAddr,
+    /// amount of credit
+    pub amount: Uint128,
+    /// true if the auction resulted in a loan that is still outstanding
+    pub is_loan: bool,
+}
+
 /// Client credit history
 #[derive(Serialize, Deserialize, Clone, JsonSchema, PartialEq, Debug)]
 pub struct History {
@@ -60,6 +244,66 @@ pub struct History {
     pub credits: Vec<Credit>,
 }
 
+impl History {
+    /// Returns the reasons the history can not be scored, empty if it is valid
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.credits.is_empty() {
+            problems.push("history has no credits".to_string());
+        }
+        if self.credits.len() > MAX_CREDITS {
+            problems.push(format!("history has more than {} credits", MAX_CREDITS));
+        }
+        for (i, credit) in self.credits.iter().enumerate() {
+            if credit.sum == Uint128(0) || credit.sum.u128() > MAX_CREDIT_SUM {
+                problems.push(format!("credit {}: sum must be between 1 and {}", i, MAX_CREDIT_SUM));
+            }
+            if credit.interest_rate.u128() > MAX_INTEREST_RATE {
+                problems.push(format!("credit {}: interest rate can not be greater than {}", i, MAX_INTEREST_RATE));
+            }
+            if credit.time == Uint128(0) || credit.time.u128() > MAX_CREDIT_TIME {
+                problems.push(format!("credit {}: time must be between 1 and {} months", i, MAX_CREDIT_TIME));
+            }
+        }
+        // debts are what is still owed on the open credits
+        let open = self.credits.iter()
+            .filter(|credit| !credit.is_closed)
+            .fold(0u128, |open, credit| open.saturating_add(credit.sum.u128()));
+        if self.debts.u128() > open {
+            problems.push(format!("debts of {} are more than the {} of open credits", self.debts, open));
+        }
+        problems
+    }
+
+    /// Returns the score of the history in an auction of the payment, None if it is too low to
+    /// borrow.  This is the score the auction contract calculates
+    ///
+    /// # Arguments
+    ///
+    /// * `payment` - alleged sum to pay
+    pub fn score(&self, payment: Uint128) -> Option<u128> {
+        let credits: Vec<ScoredCredit> = self.credits.iter()
+            .map(|credit| ScoredCredit {
+                sum: credit.sum.u128(),
+                interest_rate: credit.interest_rate.u128(),
+                time: credit.time.u128(),
+                is_closed: credit.is_closed,
+            })
+            .collect();
+        scoring::score(&credits, self.debts.u128(), payment.u128())
+    }
+
+    /// Returns StdResult<()> which is an error listing the problems of an invalid history
+    pub fn validate(&self) -> StdResult<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ContractError::InvalidHistory { problems: problems.join("; ") }.into())
+        }
+    }
+}
+
 /// Client credit data
 #[derive(Serialize, Deserialize, Clone, JsonSchema, PartialEq, Debug)]
 pub struct Credit {
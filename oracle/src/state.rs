@@ -1,19 +1,134 @@
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Storage, HumanAddr, StdResult, ReadonlyStorage, StdError};
-use crate::msg::History;
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use crate::msg::{Exposure, History, CALLERS_KEY, CONFIG_KEY, PREFIX_EXPOSURES, PREFIX_HISTORIES, VERSION_KEY};
 use std::collections::HashMap;
 use secret_toolkit::serialization::{Bincode2, Serde};
 use serde::de::DeserializeOwned;
 use std::any::type_name;
 
-/// state of the oracle
+/// state of the oracle.  The histories are stored under their users, see save_history
 #[derive(Serialize, Deserialize, Clone)]
 pub struct State {
+    /// address of the oracle's owner
+    pub owner: HumanAddr,
+    /// addresses allowed to add histories besides the owner.  Only the owner can if empty
+    pub reporters: Vec<HumanAddr>,
+}
+
+impl State {
+    /// Returns true if the address may add histories
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address adding histories
+    pub fn may_report(&self, address: &HumanAddr) -> bool {
+        *address == self.owner || self.reporters.contains(address)
+    }
+}
+
+/// contracts the owner lets open and settle exposures.  No contract may until they are set
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ExposureCallers {
+    /// code hash of the auction contract, whose instances may open exposures
+    pub auction_code_hash: Option<String>,
+    /// contracts that host auctions and may open exposures for them
+    pub factories: Vec<HumanAddr>,
+}
+
+/// version of the current state layout
+pub const STATE_VERSION: u16 = 3;
+
+/// state of the oracle as saved by the first version of the contract.  Instances saved with
+/// this layout have no version stored
+#[derive(Serialize, Deserialize)]
+pub struct StateV1 {
+    /// histories of every user, kept in the state itself
     pub histories: HashMap<HumanAddr, History>,
+    /// address of the oracle's owner
+    pub owner: HumanAddr,
+}
+
+impl StateV1 {
+    /// Returns the state in the current layout, without the histories, which migrate_state
+    /// moves under their users.  The first version let anyone add histories, but with no
+    /// reporters only the owner can until they set some
+    pub fn upgrade(self) -> State {
+        State {
+            owner: self.owner,
+            reporters: vec![],
+        }
+    }
+}
+
+/// state of the oracle as saved by the second version of the contract, which had no reporters
+#[derive(Serialize, Deserialize)]
+pub struct StateV2 {
+    /// address of the oracle's owner
     pub owner: HumanAddr,
 }
 
+impl StateV2 {
+    /// Returns the state in the current layout, where only the owner can add histories until
+    /// they set some reporters
+    pub fn upgrade(self) -> State {
+        State {
+            owner: self.owner,
+            reporters: vec![],
+        }
+    }
+}
+
+/// Returns the version of the stored state layout
+pub fn stored_version<S: ReadonlyStorage>(storage: &S) -> StdResult<u16> {
+    let version = match storage.get(VERSION_KEY) {
+        Some(value) => Bincode2::deserialize(&value)?,
+        None => 1,
+    };
+    if version > STATE_VERSION {
+        return Err(StdError::generic_err(format!(
+            "Stored state version {} is newer than this contract's version {}",
+            version, STATE_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+/// Loads the oracle state, refusing layouts newer than this contract understands
+pub fn load_state<S: ReadonlyStorage>(storage: &S) -> StdResult<State> {
+    match stored_version(storage)? {
+        1 => load::<StateV1, _>(storage, CONFIG_KEY).map(StateV1::upgrade),
+        2 => load::<StateV2, _>(storage, CONFIG_KEY).map(StateV2::upgrade),
+        _ => load(storage, CONFIG_KEY),
+    }
+}
+
+/// Saves the oracle state and the version of its layout
+pub fn save_state<S: Storage>(storage: &mut S, state: &State) -> StdResult<()> {
+    save(storage, CONFIG_KEY, state)?;
+    save(storage, VERSION_KEY, &STATE_VERSION)
+}
+
+/// Rewrites the stored state in the current layout.  Returns the version it was upgraded
+/// from, or None if it was already current
+pub fn migrate_state<S: Storage>(storage: &mut S) -> StdResult<Option<u16>> {
+    let version = stored_version(storage)?;
+    if version == STATE_VERSION {
+        return Ok(None);
+    }
+    if version == 1 {
+        // the first version kept every history in the state
+        let state: StateV1 = load(storage, CONFIG_KEY)?;
+        for (user, history) in &state.histories {
+            save_history(storage, user, history)?;
+        }
+    }
+    let state = load_state(storage)?;
+    save_state(storage, &state)?;
+    Ok(Some(version))
+}
+
 pub fn save<T: Serialize, S: Storage>(storage: &mut S, key: &[u8], value: &T) -> StdResult<()> {
     storage.set(key, &Bincode2::serialize(value)?);
     Ok(())
@@ -26,3 +141,51 @@ pub fn load<T: DeserializeOwned, S: ReadonlyStorage>(storage: &S, key: &[u8]) ->
             .ok_or_else(|| StdError::not_found(type_name::<T>()))?,
     )
 }
+
+pub fn save_exposures<S: Storage>(storage: &mut S, borrower: &HumanAddr, exposures: &[Exposure]) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_EXPOSURES, storage);
+    if exposures.is_empty() {
+        store.remove(borrower.0.as_bytes());
+        Ok(())
+    } else {
+        save(&mut store, borrower.0.as_bytes(), &exposures)
+    }
+}
+
+pub fn load_exposures<S: ReadonlyStorage>(storage: &S, borrower: &HumanAddr) -> StdResult<Vec<Exposure>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_EXPOSURES, storage);
+    match store.get(borrower.0.as_bytes()) {
+        Some(value) => Bincode2::deserialize(&value),
+        None => Ok(vec![]),
+    }
+}
+
+pub fn save_callers<S: Storage>(storage: &mut S, callers: &ExposureCallers) -> StdResult<()> {
+    save(storage, CALLERS_KEY, callers)
+}
+
+pub fn load_callers<S: ReadonlyStorage>(storage: &S) -> StdResult<ExposureCallers> {
+    match storage.get(CALLERS_KEY) {
+        Some(value) => Bincode2::deserialize(&value),
+        None => Ok(ExposureCallers::default()),
+    }
+}
+
+pub fn save_history<S: Storage>(storage: &mut S, user: &HumanAddr, history: &History) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_HISTORIES, storage);
+    save(&mut store, user.0.as_bytes(), history)
+}
+
+/// Loads the history of a user.  State saved by the first version still holds every history
+/// until it is migrated, so queries read them from there
+pub fn load_history<S: ReadonlyStorage>(storage: &S, user: &HumanAddr) -> StdResult<Option<History>> {
+    if stored_version(storage)? == 1 {
+        let state: StateV1 = load(storage, CONFIG_KEY)?;
+        return Ok(state.histories.get(user).cloned());
+    }
+    let store = ReadonlyPrefixedStorage::new(PREFIX_HISTORIES, storage);
+    match store.get(user.0.as_bytes()) {
+        Some(value) => Bincode2::deserialize(&value).map(Some),
+        None => Ok(None),
+    }
+}
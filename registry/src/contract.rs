@@ -0,0 +1,320 @@
+use cosmwasm_std::{to_binary, Api, CanonicalAddr, Env, Extern, HandleResponse, InitResponse, Querier, ReadonlyStorage, Storage, HumanAddr, QueryResult, HandleResult, InitResult, StdError, StdResult};
+
+use crate::msg::{HandleMsg, InitMsg, QueryMsg, QueryAnswer, Approval, CONFIG_KEY};
+use crate::state::{State, save, load, save_approval, remove_approval, may_load_approval, read_viewing_key, write_viewing_key};
+use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
+
+////////////////////////////////////// Init ///////////////////////////////////////
+/// Initializes the registry state
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - InitMsg passed in with the instantiation message
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: InitMsg,
+) -> InitResult {
+    let state = State {
+        admin: msg.admin.unwrap_or(env.message.sender),
+        viewers: vec![],
+    };
+
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    Ok(InitResponse::default())
+}
+
+///////////////////////////////////// Handle //////////////////////////////////////
+/// Handle incoming messages from nodes
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - HandleMsg passed in with the execute message
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> HandleResult {
+    match msg {
+        HandleMsg::Approve {lender, expires_at} => {
+            enforce_admin(&deps.storage, &env)?;
+            try_approve(deps, env, lender, expires_at)
+        }
+        HandleMsg::Revoke {lender} => {
+            enforce_admin(&deps.storage, &env)?;
+            let lender = deps.api.canonical_address(&lender)?;
+            remove_approval(&mut deps.storage, &lender);
+            Ok(HandleResponse::default())
+        }
+        HandleMsg::ChangeAdmin {admin} => {
+            let mut state = enforce_admin(&deps.storage, &env)?;
+            state.admin = admin;
+            save(&mut deps.storage, CONFIG_KEY, &state)?;
+            Ok(HandleResponse::default())
+        }
+        HandleMsg::SetViewers {viewers} => {
+            let mut state = enforce_admin(&deps.storage, &env)?;
+            state.viewers = viewers
+                .iter()
+                .map(|viewer| deps.api.canonical_address(viewer))
+                .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+            save(&mut deps.storage, CONFIG_KEY, &state)?;
+            Ok(HandleResponse::default())
+        }
+        HandleMsg::AddViewers {viewers} => {
+            let mut state = enforce_admin(&deps.storage, &env)?;
+            for viewer in &viewers {
+                let viewer = deps.api.canonical_address(viewer)?;
+                if !state.viewers.contains(&viewer) {
+                    state.viewers.push(viewer);
+                }
+            }
+            save(&mut deps.storage, CONFIG_KEY, &state)?;
+            Ok(HandleResponse::default())
+        }
+        HandleMsg::RemoveViewers {viewers} => {
+            let mut state = enforce_admin(&deps.storage, &env)?;
+            let removed = viewers
+                .iter()
+                .map(|viewer| deps.api.canonical_address(viewer))
+                .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+            state.viewers.retain(|viewer| !removed.contains(viewer));
+            save(&mut deps.storage, CONFIG_KEY, &state)?;
+            Ok(HandleResponse::default())
+        }
+        // anyone can set a viewing key
+        HandleMsg::SetViewingKey {key, ..} => {
+            let owner = deps.api.canonical_address(&env.message.sender)?;
+            write_viewing_key(&mut deps.storage, &owner, &ViewingKey(key));
+            Ok(HandleResponse::default())
+        }
+    }
+}
+
+/// Returns StdResult<State> of the registry state, which is an error if the calling address is
+/// not the admin
+///
+/// # Arguments
+///
+/// * `storage` - reference to the contract's storage
+/// * `env` - reference to the Env of contract's environment
+fn enforce_admin<S: ReadonlyStorage>(storage: &S, env: &Env) -> StdResult<State> {
+    let state: State = load(storage, CONFIG_KEY)?;
+    if env.message.sender != state.admin {
+        return Err(StdError::unauthorized());
+    }
+    Ok(state)
+}
+
+/// Approve a lender
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `lender` - lender to approve
+/// * `expires_at` - Optional time the approval expires
+pub fn try_approve<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    lender: HumanAddr,
+    expires_at: Option<u64>,
+) -> HandleResult {
+    if expires_at.map_or(false, |expiry| expiry <= env.block.time) {
+        return Err(StdError::generic_err("Approval must expire in the future"));
+    }
+    let lender = deps.api.canonical_address(&lender)?;
+    save_approval(&mut deps.storage, &lender, &Approval { expires_at })?;
+
+    Ok(HandleResponse::default())
+}
+
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns QueryResult
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `msg` - QueryMsg passed in with the query call
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> QueryResult {
+    match msg {
+        QueryMsg::IsApproved {address, time, viewer, key} => {
+            let lender = deps.api.canonical_address(&address)?;
+            let viewer = deps.api.canonical_address(&viewer)?;
+            let state: State = load(&deps.storage, CONFIG_KEY)?;
+            if !is_key_valid(&deps.storage, &viewer, key) || (viewer != lender && !state.viewers.contains(&viewer)) {
+                return viewing_key_error();
+            }
+            let approved = may_load_approval(&deps.storage, &lender)?.map_or(false, |approval| approval.is_valid_at(time));
+            to_binary(&QueryAnswer::IsApproved { approved })
+        }
+        QueryMsg::Approval {address, key} => {
+            let lender = deps.api.canonical_address(&address)?;
+            if !is_key_valid(&deps.storage, &lender, key) {
+                return viewing_key_error();
+            }
+            to_binary(&QueryAnswer::Approval { approval: may_load_approval(&deps.storage, &lender)? })
+        }
+    }
+}
+
+/// Returns true if the viewing key is correct for the address
+///
+/// # Arguments
+///
+/// * `storage` - reference to the contract's storage
+/// * `owner` - a reference to the canonical address whose key is being checked
+/// * `key` - viewing key
+fn is_key_valid<S: ReadonlyStorage>(storage: &S, owner: &CanonicalAddr, key: String) -> bool {
+    let viewing_key = ViewingKey(key);
+    // check against a dummy hash when no key was set, so the timing does not reveal it
+    match read_viewing_key(storage, owner) {
+        Some(hashed) => viewing_key.check_viewing_key(&hashed),
+        None => {
+            viewing_key.check_viewing_key(&[0u8; VIEWING_KEY_SIZE]);
+            false
+        }
+    }
+}
+
+/// Returns QueryResult of the response sent when a viewing key is wrong, or the address asking
+/// may not see the approval
+fn viewing_key_error() -> QueryResult {
+    to_binary(&QueryAnswer::ViewingKeyError {
+        msg: "Wrong viewing key for this address, viewing key not set, or not allowed to view this approval".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockStorage, MockApi, MockQuerier};
+    use cosmwasm_std::from_binary;
+
+    fn init_helper() -> (
+            InitResult,
+            Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("admin", &[]);
+
+        let init_msg = InitMsg {admin: None};
+        (init(&mut deps, env, init_msg), deps)
+    }
+
+    fn set_key(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, address: &str) {
+        let handle_msg = HandleMsg::SetViewingKey {key: format!("{}key", address), padding: None};
+        handle(deps, mock_env(address, &[]), handle_msg).unwrap();
+    }
+
+    fn is_approved_as(deps: &Extern<MockStorage, MockApi, MockQuerier>, viewer: &str, lender: &str, time: u64) -> QueryAnswer {
+        let query_msg = QueryMsg::IsApproved {
+            address: HumanAddr(lender.to_string()),
+            time,
+            viewer: HumanAddr(viewer.to_string()),
+            key: format!("{}key", viewer),
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    fn is_approved(deps: &Extern<MockStorage, MockApi, MockQuerier>, lender: &str, time: u64) -> bool {
+        match is_approved_as(deps, "auction", lender, time) {
+            QueryAnswer::IsApproved {approved} => approved,
+            other => panic!("Unexpected answer: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn approve_and_revoke() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::SetViewers {viewers: vec![HumanAddr("auction".to_string())]};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        set_key(&mut deps, "auction");
+
+        let handle_msg = HandleMsg::Approve {lender: HumanAddr("lender".to_string()), expires_at: None};
+        let handle_result = handle(&mut deps, mock_env("lender", &[]), handle_msg);
+        assert!(handle_result.is_err(), "Only the admin can approve lenders");
+        assert!(!is_approved(&deps, "lender", 0));
+
+        let env = mock_env("admin", &[]);
+        let expires_at = env.block.time + 100;
+        let handle_msg = HandleMsg::Approve {lender: HumanAddr("lender".to_string()), expires_at: Some(expires_at)};
+        handle(&mut deps, env, handle_msg).unwrap();
+        assert!(is_approved(&deps, "lender", expires_at - 1));
+        assert!(!is_approved(&deps, "lender", expires_at));
+
+        let handle_msg = HandleMsg::Revoke {lender: HumanAddr("lender".to_string())};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        assert!(!is_approved(&deps, "lender", 0));
+    }
+
+    #[test]
+    fn approvals_are_private() {
+        let (_, mut deps) = init_helper();
+        let handle_msg = HandleMsg::Approve {lender: HumanAddr("lender".to_string()), expires_at: None};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        set_key(&mut deps, "lender");
+        set_key(&mut deps, "stranger");
+
+        // an address that is not a viewer only sees its own approval
+        match is_approved_as(&deps, "stranger", "lender", 0) {
+            QueryAnswer::ViewingKeyError {..} => {}
+            other => panic!("Unexpected answer: {:?}", other),
+        }
+        assert_eq!(is_approved_as(&deps, "lender", "lender", 0), QueryAnswer::IsApproved {approved: true});
+
+        let approval = |key: &str| QueryMsg::Approval {address: HumanAddr("lender".to_string()), key: key.to_string()};
+        match from_binary(&query(&deps, approval("stranger")).unwrap()).unwrap() {
+            QueryAnswer::ViewingKeyError {..} => {}
+            other => panic!("Unexpected answer: {:?}", other),
+        }
+        match from_binary(&query(&deps, approval("lenderkey")).unwrap()).unwrap() {
+            QueryAnswer::Approval {approval} => assert_eq!(approval, Some(Approval {expires_at: None})),
+            other => panic!("Unexpected answer: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn viewers_can_be_added_and_removed() {
+        let (_, mut deps) = init_helper();
+        let handle_msg = HandleMsg::Approve {lender: HumanAddr("lender".to_string()), expires_at: None};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        set_key(&mut deps, "auction");
+        set_key(&mut deps, "market");
+        let viewers = |addresses: &[&str]| addresses.iter().map(|address| HumanAddr(address.to_string())).collect::<Vec<HumanAddr>>();
+        let sees = |deps: &Extern<MockStorage, MockApi, MockQuerier>, viewer: &str| {
+            is_approved_as(deps, viewer, "lender", 0) == QueryAnswer::IsApproved {approved: true}
+        };
+
+        let handle_msg = HandleMsg::AddViewers {viewers: viewers(&["auction"])};
+        let handle_result = handle(&mut deps, mock_env("auction", &[]), handle_msg);
+        assert!(handle_result.is_err(), "Only the admin can add viewers");
+        assert!(!sees(&deps, "auction"));
+
+        // adding keeps the viewers already allowed
+        let handle_msg = HandleMsg::AddViewers {viewers: viewers(&["auction"])};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::AddViewers {viewers: viewers(&["market", "auction"])};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        assert!(sees(&deps, "auction"));
+        assert!(sees(&deps, "market"));
+
+        let handle_msg = HandleMsg::RemoveViewers {viewers: viewers(&["auction", "stranger"])};
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        assert!(!sees(&deps, "auction"));
+        assert!(sees(&deps, "market"));
+    }
+}
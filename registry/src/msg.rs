@@ -0,0 +1,128 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::HumanAddr;
+
+/// storage key for registry state
+pub static CONFIG_KEY: &[u8] = b"config";
+
+/// storage prefix for lender approvals
+pub static PREFIX_APPROVALS: &[u8] = b"approvals";
+
+/// storage prefix for hashed viewing keys
+pub static PREFIX_VIEW_KEY: &[u8] = b"viewingkey";
+
+/// Instantiation message
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InitMsg {
+    /// Optional admin address.  Defaults to the instantiating address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin: Option<HumanAddr>,
+}
+
+/// Handle message
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    /// approve a lender, replacing any earlier approval
+    Approve {
+        /// lender address
+        lender: HumanAddr,
+        /// Optional time the approval expires
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>,
+    },
+    /// revoke the approval of a lender
+    Revoke {
+        /// lender address
+        lender: HumanAddr,
+    },
+    /// hand the admin role to another address
+    ChangeAdmin {
+        /// new admin address
+        admin: HumanAddr,
+    },
+    /// replace the contracts allowed to check the approval of any lender
+    SetViewers {
+        /// addresses of the auctions and marketplaces that check approvals
+        viewers: Vec<HumanAddr>,
+    },
+    /// allow more contracts to check the approval of any lender, such as each new auction
+    AddViewers {
+        /// addresses of the auctions and marketplaces to add
+        viewers: Vec<HumanAddr>,
+    },
+    /// stop contracts from checking approvals.  Addresses that are not viewers are ignored
+    RemoveViewers {
+        /// addresses of the auctions and marketplaces to remove
+        viewers: Vec<HumanAddr>,
+    },
+    /// set the viewing key of the calling address.  Anyone can set a key
+    SetViewingKey {
+        /// the viewing key
+        key: String,
+        /// Optional padding to hide the length of the key
+        #[serde(skip_serializing_if = "Option::is_none")]
+        padding: Option<String>,
+    },
+}
+
+/// Query message
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// is the lender approved at the given time.  Only the lender and the viewers can ask
+    IsApproved {
+        /// lender address
+        address: HumanAddr,
+        /// time to check the approval at
+        time: u64,
+        /// address asking, either the lender or a viewer
+        viewer: HumanAddr,
+        /// viewing key of the address asking
+        key: String,
+    },
+    /// get the approval of a lender.  Only the lender can ask
+    Approval {
+        /// lender address
+        address: HumanAddr,
+        /// viewing key of the lender
+        key: String,
+    },
+}
+
+/// Query responses
+#[derive(Serialize, Deserialize, Debug, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryAnswer {
+    /// IsApproved query response
+    IsApproved {
+        /// true if the lender is approved
+        approved: bool,
+    },
+    /// Approval query response
+    Approval {
+        /// Optional approval, None if the lender was never approved or has been revoked
+        #[serde(skip_serializing_if = "Option::is_none")]
+        approval: Option<Approval>,
+    },
+    /// response when a viewing key is wrong, or the address asking may not see the approval
+    ViewingKeyError {
+        /// error description
+        msg: String,
+    },
+}
+
+/// Lender approval
+#[derive(Serialize, Deserialize, Clone, JsonSchema, PartialEq, Debug)]
+pub struct Approval {
+    /// Optional time the approval expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl Approval {
+    /// Returns true if the approval is still valid at the given time
+    pub fn is_valid_at(&self, time: u64) -> bool {
+        self.expires_at.map_or(true, |expiry| time < expiry)
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use crate::msg::{Approval, PREFIX_APPROVALS, PREFIX_VIEW_KEY};
+use crate::viewing_key::ViewingKey;
+use secret_toolkit::serialization::{Bincode2, Serde};
+use serde::de::DeserializeOwned;
+use std::any::type_name;
+
+/// state of the registry
+#[derive(Serialize, Deserialize, Clone)]
+pub struct State {
+    pub admin: HumanAddr,
+    /// contracts allowed to check the approval of any lender, such as auctions and marketplaces
+    pub viewers: Vec<CanonicalAddr>,
+}
+
+pub fn save<T: Serialize, S: Storage>(storage: &mut S, key: &[u8], value: &T) -> StdResult<()> {
+    storage.set(key, &Bincode2::serialize(value)?);
+    Ok(())
+}
+
+pub fn load<T: DeserializeOwned, S: ReadonlyStorage>(storage: &S, key: &[u8]) -> StdResult<T> {
+    Bincode2::deserialize(
+        &storage
+            .get(key)
+            .ok_or_else(|| StdError::not_found(type_name::<T>()))?,
+    )
+}
+
+pub fn save_approval<S: Storage>(storage: &mut S, lender: &CanonicalAddr, approval: &Approval) -> StdResult<()> {
+    let mut approvals = PrefixedStorage::new(PREFIX_APPROVALS, storage);
+    save(&mut approvals, lender.as_slice(), approval)
+}
+
+pub fn remove_approval<S: Storage>(storage: &mut S, lender: &CanonicalAddr) {
+    let mut approvals = PrefixedStorage::new(PREFIX_APPROVALS, storage);
+    approvals.remove(lender.as_slice());
+}
+
+pub fn may_load_approval<S: ReadonlyStorage>(storage: &S, lender: &CanonicalAddr) -> StdResult<Option<Approval>> {
+    let approvals = ReadonlyPrefixedStorage::new(PREFIX_APPROVALS, storage);
+    match approvals.get(lender.as_slice()) {
+        Some(value) => Bincode2::deserialize(&value).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub fn write_viewing_key<S: Storage>(storage: &mut S, owner: &CanonicalAddr, key: &ViewingKey) {
+    let mut key_store = PrefixedStorage::new(PREFIX_VIEW_KEY, storage);
+    key_store.set(owner.as_slice(), &key.to_hashed());
+}
+
+pub fn read_viewing_key<S: ReadonlyStorage>(storage: &S, owner: &CanonicalAddr) -> Option<Vec<u8>> {
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_VIEW_KEY, storage);
+    key_store.get(owner.as_slice())
+}
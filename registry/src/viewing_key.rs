@@ -0,0 +1,39 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// size of a hashed viewing key
+pub const VIEWING_KEY_SIZE: usize = 32;
+
+/// viewing key used to authenticate queries
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct ViewingKey(pub String);
+
+impl ViewingKey {
+    /// Returns the hash of this key that gets saved in storage
+    pub fn to_hashed(&self) -> [u8; VIEWING_KEY_SIZE] {
+        sha_256(self.0.as_bytes())
+    }
+
+    /// Returns true if this key matches the saved hash.  Compares in constant time
+    ///
+    /// # Arguments
+    ///
+    /// * `hashed` - the saved hash of a viewing key
+    pub fn check_viewing_key(&self, hashed: &[u8]) -> bool {
+        bool::from(self.to_hashed()[..].ct_eq(hashed))
+    }
+}
+
+/// Returns the sha256 hash of the data
+///
+/// # Arguments
+///
+/// * `data` - the data to hash
+pub fn sha_256(data: &[u8]) -> [u8; VIEWING_KEY_SIZE] {
+    let mut hashed = [0u8; VIEWING_KEY_SIZE];
+    hashed.copy_from_slice(&Sha256::digest(data));
+    hashed
+}
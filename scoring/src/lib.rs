@@ -0,0 +1,120 @@
+//! Credit score of a borrower's history.  The oracle caps exposures with it, the auction opens
+//! with it and the history import tool reports it, so all three share this one implementation.
+//! The oracle's limit is only sound if it scores a history exactly as the auction does, which two
+//! copies of the calculation could not guarantee once one of them was changed.  It works on plain
+//! integers so the contracts and the client can use it with their own history types, and it has
+//! no dependencies, so the client scores histories without depending on the auction contract
+
+/// credit of a history, as the score sees it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoredCredit {
+    /// amount of money
+    pub sum: u128,
+    /// interest rate of credit
+    pub interest_rate: u128,
+    /// time to close credit (in months)
+    pub time: u128,
+    /// condition of closing
+    pub is_closed: bool,
+}
+
+/// Returns the power of ten with as many digits as the payment, which scales the score and the
+/// average bid.  The payment must not be zero
+///
+/// # Arguments
+///
+/// * `payment` - alleged sum to pay
+pub fn perfect_proposal(payment: u128) -> u128 {
+    let mut number = payment;
+    let mut len: u32 = 0;
+    while number > 0 {
+        number /= 10;
+        len += 1;
+    }
+    10u128.pow(len - 1)
+}
+
+/// Returns the score of a credit history for an auction of the payment, the amount of sale
+/// tokens the borrower may offer.  None if the score is too low to borrow, which includes debts
+/// outweighing the credits, or if the payment is zero.  The history must be valid, or the
+/// calculation may overflow
+///
+/// # Arguments
+///
+/// * `credits` - the credits of the history
+/// * `debts` - what is still owed on the open credits
+/// * `payment` - alleged sum to pay
+pub fn score(credits: &[ScoredCredit], debts: u128, payment: u128) -> Option<u128> {
+    if payment == 0 {
+        return None;
+    }
+    let mul = perfect_proposal(payment);
+    let mut score: i128 = 0;
+    let mut cred_hist: i128 = 0;
+    for credit in credits {
+        let sum: u128 = credit.sum * credit.interest_rate * credit.time;
+        if credit.is_closed {
+            cred_hist += sum as i128;
+        } else {
+            cred_hist -= sum as i128;
+        }
+    }
+    if cred_hist > 0 {
+        score += cred_hist / 2 + cred_hist % 2;
+    }
+    score -= (debts / 3 + debts % 3) as i128;
+    let length = credits.len() as i128;
+    score += (length * mul as i128) / 5 + (length * mul as i128) % 5;
+    // debts can outweigh everything else, and a negative score must not wrap around
+    if score <= 0 {
+        return None;
+    }
+    let result = (score as u128 / mul + score as u128 % mul) / 100;
+    if result == 0 {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credit(sum: u128, is_closed: bool) -> ScoredCredit {
+        ScoredCredit {
+            sum,
+            interest_rate: 10,
+            time: 12,
+            is_closed,
+        }
+    }
+
+    #[test]
+    fn perfect_proposal_has_the_digits_of_the_payment() {
+        assert_eq!(perfect_proposal(1), 1);
+        assert_eq!(perfect_proposal(1200000), 1000000);
+        assert_eq!(perfect_proposal(9999999), 1000000);
+    }
+
+    #[test]
+    fn outweighing_debts_and_zero_payments_score_nothing() {
+        let credits = [credit(1000000, false)];
+        assert_eq!(score(&credits, 1000000, 1000000), None);
+        assert_eq!(score(&[credit(1000000, true)], 0, 0), None);
+    }
+
+    #[test]
+    fn negative_scores_do_not_wrap_around() {
+        let credits = [credit(1000000, false)];
+        let mul = perfect_proposal(1000000);
+        // the open credit adds nothing and the debts outweigh what the credit count adds
+        let negative = (mul as i128) / 5 - (1000000 / 3 + 1000000 % 3) as i128;
+        assert_eq!(negative, -133334);
+        // the result used to be taken from the score cast to u128, which scored the borrower
+        // higher than any honest history
+        let wrapped = (negative as u128 / mul + negative as u128 % mul) / 100;
+        assert_eq!(wrapped, 3402823669209384634633746075098);
+        assert_eq!(score(&credits, 1000000, 1000000), None);
+    }
+}
@@ -0,0 +1,19 @@
+//! Fuzzes the auction's escrow with sequences of consign, bid, view_bid, finalize and return_all.
+//! Run with `cargo fuzz run escrow` from the simulator directory.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use simulator::invariants::{EscrowHarness, Op};
+
+/// longest sequence of operations run on one auction
+const MAX_OPS: usize = 64;
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut harness = EscrowHarness::new();
+    for op in ops.iter().take(MAX_OPS) {
+        if let Err(e) = harness.apply(op) {
+            panic!("{:?} after {:?}: {}", op, ops, e);
+        }
+    }
+});
@@ -0,0 +1,82 @@
+//! Runs scenario files against the contracts in-process and reports whether each passed.
+//!
+//! Usage: `scenario [--json] <file or directory>...`.  Directories are searched for `.json`
+//! files.  Exits with 1 if any scenario failed.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use simulator::scenario::{Report, Scenario};
+
+/// Returns the scenario files at the path, sorted by name
+///
+/// # Arguments
+///
+/// * `path` - a scenario file, or a directory of them
+fn scenario_files(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    let mut files: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.extension().map_or(false, |ext| ext == "json"))
+            .collect(),
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path.display(), e);
+            process::exit(2);
+        }
+    };
+    files.sort();
+    files
+}
+
+fn main() {
+    let mut json = false;
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("Usage: scenario [--json] <file or directory>...");
+                return;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: scenario [--json] <file or directory>...");
+        process::exit(2);
+    }
+
+    let mut reports: Vec<Report> = Vec::new();
+    for file in paths.iter().flat_map(|path| scenario_files(path)) {
+        let report = match Scenario::load(&file) {
+            Ok(scenario) => scenario.run(),
+            Err(e) => Report {
+                name: file.display().to_string(),
+                steps_passed: 0,
+                failure: Some(e.to_string()),
+            },
+        };
+        if !json {
+            match &report.failure {
+                None => println!("PASS {} ({} steps)", report.name, report.steps_passed),
+                Some(failure) => println!("FAIL {}: {}", report.name, failure),
+            }
+        }
+        reports.push(report);
+    }
+
+    let failed = reports.iter().filter(|report| !report.passed()).count();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        println!("\n{} passed, {} failed", reports.len() - failed, failed);
+    }
+    if failed > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,605 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use cosmwasm_std::testing::{mock_env, MockApi};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Binary, BlockInfo, CosmosMsg, Empty, Env, Extern,
+    HandleResponse, HumanAddr, Querier, QuerierResult, QueryRequest, StdError, StdResult,
+    SystemError, WasmMsg, WasmQuery,
+};
+
+use crate::code::{Code, Deps};
+use crate::ledger::Ledger;
+use crate::storage::MemoryStorage;
+
+/// length of canonical addresses of the simulated chain
+pub const CANONICAL_LENGTH: usize = 20;
+
+/// maximum depth of messages emitted while executing other messages
+pub const MAX_DEPTH: usize = 10;
+
+/// Returns the code hash of the contract at the address.  Simulated code hashes are derived from
+/// the address, so messages can name the contracts they call before they are instantiated
+///
+/// # Arguments
+///
+/// * `address` - address of the contract
+pub fn code_hash(address: &HumanAddr) -> String {
+    format!("{}hash", address)
+}
+
+/// a contract instance hosted by the chain
+#[derive(Clone)]
+struct Instance {
+    /// code of the contract
+    code: Rc<dyn Code>,
+    /// storage of the instance
+    storage: MemoryStorage,
+}
+
+/// everything a transaction can change, which is restored if it fails
+#[derive(Clone, Default)]
+struct World {
+    /// contract instances by address
+    instances: BTreeMap<HumanAddr, Instance>,
+    /// balances of the simulated SNIP-20 tokens
+    ledger: Ledger,
+}
+
+/// response of a contract or token to a message executed in a transaction
+#[derive(Debug)]
+pub struct Executed {
+    /// address of the contract or token that executed the message
+    pub contract: HumanAddr,
+    /// response to the message.  Init responses are converted to a HandleResponse without data
+    pub response: HandleResponse,
+}
+
+/// Querier of simulated contracts.  Smart queries are answered by the contract or token at the
+/// queried address, using its state as of the start of the current message
+#[derive(Clone)]
+pub struct ChainQuerier {
+    world: Rc<RefCell<World>>,
+}
+
+impl Querier for ChainQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(request) => request,
+            Err(e) => {
+                return Err(SystemError::InvalidRequest {
+                    error: e.to_string(),
+                    request: Binary(bin_request.to_vec()),
+                })
+            }
+        };
+        match request {
+            QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr, msg, ..
+            }) => self.query_contract(&contract_addr, &msg),
+            _ => Err(SystemError::UnsupportedRequest {
+                kind: "only smart queries are simulated".to_string(),
+            }),
+        }
+    }
+}
+
+impl ChainQuerier {
+    /// Returns the answer of a contract or token to a smart query
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the queried contract
+    /// * `msg` - JSON encoded query
+    fn query_contract(&self, contract: &HumanAddr, msg: &Binary) -> QuerierResult {
+        let (code, storage) = {
+            let world = self.world.borrow();
+            if world.ledger.is_token(contract) {
+                return Ok(world.ledger.query(contract, msg));
+            }
+            let instance = world
+                .instances
+                .get(contract)
+                .ok_or_else(|| SystemError::NoSuchContract {
+                    addr: contract.clone(),
+                })?;
+            (instance.code.clone(), instance.storage.clone())
+        };
+        let deps = deps(storage, self.world.clone());
+        Ok(code.query(&deps, msg))
+    }
+}
+
+/// Returns the dependencies of an instance
+///
+/// # Arguments
+///
+/// * `storage` - storage of the instance
+/// * `world` - state of the chain the instance queries
+fn deps(storage: MemoryStorage, world: Rc<RefCell<World>>) -> Deps {
+    Extern {
+        storage,
+        api: MockApi::new(CANONICAL_LENGTH),
+        querier: ChainQuerier { world },
+    }
+}
+
+/// deterministic, in-process chain hosting contract instances and SNIP-20 tokens.  Messages
+/// returned by contracts are executed depth first, and a failure anywhere in a transaction
+/// rolls the whole transaction back
+pub struct Chain {
+    world: Rc<RefCell<World>>,
+    block: BlockInfo,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Chain::new()
+    }
+}
+
+impl Chain {
+    /// Returns a chain without contracts or tokens
+    pub fn new() -> Self {
+        Chain {
+            world: Rc::new(RefCell::new(World::default())),
+            block: mock_env("chain", &[]).block,
+        }
+    }
+
+    /// Returns the current block
+    pub fn block(&self) -> &BlockInfo {
+        &self.block
+    }
+
+    /// Moves to the next block, the given number of seconds later
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - number of seconds to advance
+    pub fn advance(&mut self, seconds: u64) {
+        self.block.height += 1;
+        self.block.time += seconds;
+    }
+
+    /// Adds a SNIP-20 token
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the token
+    /// * `symbol` - symbol of the token
+    /// * `decimals` - number of decimals of the token
+    pub fn add_token(&mut self, address: &str, symbol: &str, decimals: u8) -> StdResult<()> {
+        let address = HumanAddr(address.to_string());
+        let mut world = self.world.borrow_mut();
+        if world.instances.contains_key(&address) || world.ledger.is_token(&address) {
+            return Err(address_in_use(&address));
+        }
+        world.ledger.add_token(address, symbol, decimals);
+        Ok(())
+    }
+
+    /// Adds tokens to an address's balance
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `owner` - address receiving the tokens
+    /// * `amount` - amount of tokens
+    pub fn mint(&mut self, token: &str, owner: &str, amount: u128) -> StdResult<()> {
+        self.world.borrow_mut().ledger.mint(
+            &HumanAddr(token.to_string()),
+            HumanAddr(owner.to_string()),
+            amount,
+        )
+    }
+
+    /// Returns the token balance of an address
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `owner` - address holding the tokens
+    pub fn balance(&self, token: &str, owner: &str) -> u128 {
+        self.world.borrow().ledger.balance(
+            &HumanAddr(token.to_string()),
+            &HumanAddr(owner.to_string()),
+        )
+    }
+
+    /// Returns the responses of instantiating a contract and executing the messages its init
+    /// returned.  Nothing is changed if any of them fails
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - code of the contract
+    /// * `address` - address of the new instance
+    /// * `sender` - address instantiating the contract
+    /// * `msg` - InitMsg of the contract
+    pub fn instantiate<M: Serialize>(
+        &mut self,
+        code: Box<dyn Code>,
+        address: &str,
+        sender: &str,
+        msg: &M,
+    ) -> StdResult<Vec<Executed>> {
+        self.instantiate_binary(code, address, sender, to_binary(msg)?)
+    }
+
+    /// Returns the responses of instantiating a contract with a JSON encoded InitMsg
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - code of the contract
+    /// * `address` - address of the new instance
+    /// * `sender` - address instantiating the contract
+    /// * `msg` - JSON encoded InitMsg of the contract
+    pub fn instantiate_binary(
+        &mut self,
+        code: Box<dyn Code>,
+        address: &str,
+        sender: &str,
+        msg: Binary,
+    ) -> StdResult<Vec<Executed>> {
+        let address = HumanAddr(address.to_string());
+        let sender = HumanAddr(sender.to_string());
+        let code: Rc<dyn Code> = Rc::from(code);
+        self.transaction(|chain, executed| {
+            {
+                let mut world = chain.world.borrow_mut();
+                if world.instances.contains_key(&address) || world.ledger.is_token(&address) {
+                    return Err(address_in_use(&address));
+                }
+                let instance = Instance {
+                    code: code.clone(),
+                    storage: MemoryStorage::default(),
+                };
+                world.instances.insert(address.clone(), instance);
+            }
+            let mut deps = deps(MemoryStorage::default(), chain.world.clone());
+            let response = code.init(&mut deps, chain.env(&sender, &address), &msg)?;
+            chain.save_storage(&address, deps.storage);
+            let response = HandleResponse {
+                messages: response.messages,
+                log: response.log,
+                data: None,
+            };
+            chain.run_messages(&address, response, 0, executed)
+        })
+    }
+
+    /// Returns the responses of executing a message and every message it resulted in.  Nothing
+    /// is changed if any of them fails
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - address executing the message
+    /// * `contract` - address of the contract or token the message is sent to
+    /// * `msg` - HandleMsg of the contract or token
+    pub fn execute<M: Serialize>(
+        &mut self,
+        sender: &str,
+        contract: &str,
+        msg: &M,
+    ) -> StdResult<Vec<Executed>> {
+        self.execute_binary(sender, contract, to_binary(msg)?)
+    }
+
+    /// Returns the responses of executing a JSON encoded message and every message it resulted
+    /// in
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - address executing the message
+    /// * `contract` - address of the contract or token the message is sent to
+    /// * `msg` - JSON encoded HandleMsg of the contract or token
+    pub fn execute_binary(
+        &mut self,
+        sender: &str,
+        contract: &str,
+        msg: Binary,
+    ) -> StdResult<Vec<Executed>> {
+        let sender = HumanAddr(sender.to_string());
+        let contract = HumanAddr(contract.to_string());
+        self.transaction(|chain, executed| chain.execute_wasm(&sender, &contract, &msg, 0, executed))
+    }
+
+    /// Returns the answer of a contract or token to a query
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the queried contract or token
+    /// * `msg` - QueryMsg of the contract or token
+    pub fn query<M: Serialize, T: DeserializeOwned>(&self, contract: &str, msg: &M) -> StdResult<T> {
+        from_binary(&self.query_binary(contract, &to_binary(msg)?)?)
+    }
+
+    /// Returns the JSON encoded answer of a contract or token to a JSON encoded query
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the queried contract or token
+    /// * `msg` - JSON encoded QueryMsg of the contract or token
+    pub fn query_binary(&self, contract: &str, msg: &Binary) -> StdResult<Binary> {
+        let querier = ChainQuerier {
+            world: self.world.clone(),
+        };
+        querier
+            .query_contract(&HumanAddr(contract.to_string()), msg)
+            .map_err(|e| StdError::generic_err(format!("Querier system error: {:?}", e)))?
+    }
+
+    /// Returns a copy of the storage of a contract instance, None if there is no instance at the
+    /// address
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - address of the instance
+    pub fn storage(&self, address: &str) -> Option<MemoryStorage> {
+        let address = HumanAddr(address.to_string());
+        self.world
+            .borrow()
+            .instances
+            .get(&address)
+            .map(|instance| instance.storage.clone())
+    }
+
+    /// Returns true if a contract or token is at the address
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - the address
+    pub fn is_contract(&self, address: &str) -> bool {
+        let address = HumanAddr(address.to_string());
+        let world = self.world.borrow();
+        world.instances.contains_key(&address) || world.ledger.is_token(&address)
+    }
+
+    /// Returns the responses of a transaction, or its error after restoring the state it started
+    /// from
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - executes the messages of the transaction, collecting their responses
+    fn transaction<F>(&mut self, run: F) -> StdResult<Vec<Executed>>
+    where
+        F: FnOnce(&Self, &mut Vec<Executed>) -> StdResult<()>,
+    {
+        let snapshot = self.world.borrow().clone();
+        let mut executed = Vec::new();
+        match run(self, &mut executed) {
+            Ok(()) => Ok(executed),
+            Err(e) => {
+                *self.world.borrow_mut() = snapshot;
+                Err(e)
+            }
+        }
+    }
+
+    /// Executes a message sent to a contract or token, and the messages it results in
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - address executing the message
+    /// * `contract` - address of the contract or token
+    /// * `msg` - JSON encoded HandleMsg
+    /// * `depth` - number of messages this one was emitted under
+    /// * `executed` - responses of the transaction so far
+    fn execute_wasm(
+        &self,
+        sender: &HumanAddr,
+        contract: &HumanAddr,
+        msg: &Binary,
+        depth: usize,
+        executed: &mut Vec<Executed>,
+    ) -> StdResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(StdError::generic_err(format!(
+                "Messages can not be nested deeper than {}",
+                MAX_DEPTH
+            )));
+        }
+        let is_token = self.world.borrow().ledger.is_token(contract);
+        let response = if is_token {
+            self.world.borrow_mut().ledger.handle(contract, sender, msg)?
+        } else {
+            let (code, storage) = {
+                let world = self.world.borrow();
+                let instance = world.instances.get(contract).ok_or_else(|| {
+                    StdError::generic_err(format!("There is no contract at {}", contract))
+                })?;
+                (instance.code.clone(), instance.storage.clone())
+            };
+            let mut deps = deps(storage, self.world.clone());
+            let response = code.handle(&mut deps, self.env(sender, contract), msg)?;
+            self.save_storage(contract, deps.storage);
+            response
+        };
+        self.run_messages(contract, response, depth, executed)
+    }
+
+    /// Records a response and executes the messages it returned
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the contract or token that responded
+    /// * `response` - the response
+    /// * `depth` - number of messages the response's message was emitted under
+    /// * `executed` - responses of the transaction so far
+    fn run_messages(
+        &self,
+        contract: &HumanAddr,
+        response: HandleResponse,
+        depth: usize,
+        executed: &mut Vec<Executed>,
+    ) -> StdResult<()> {
+        let messages = response.messages.clone();
+        executed.push(Executed {
+            contract: contract.clone(),
+            response,
+        });
+        for message in messages {
+            match message {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) => self.execute_wasm(contract, &contract_addr, &msg, depth + 1, executed)?,
+                _ => {
+                    return Err(StdError::generic_err(
+                        "Only WasmMsg::Execute messages can be simulated",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the storage of an instance
+    ///
+    /// # Arguments
+    ///
+    /// * `contract` - address of the instance
+    /// * `storage` - new storage of the instance
+    fn save_storage(&self, contract: &HumanAddr, storage: MemoryStorage) {
+        if let Some(instance) = self.world.borrow_mut().instances.get_mut(contract) {
+            instance.storage = storage;
+        }
+    }
+
+    /// Returns the Env of a message executed in the current block
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - address executing the message
+    /// * `contract` - address of the contract
+    fn env(&self, sender: &HumanAddr, contract: &HumanAddr) -> Env {
+        let mut env = mock_env(sender.clone(), &[]);
+        env.block = self.block.clone();
+        env.contract.address = contract.clone();
+        env.contract_code_hash = code_hash(contract);
+        env
+    }
+}
+
+/// Returns the error of adding a contract or token at an address that is taken
+///
+/// # Arguments
+///
+/// * `address` - the address
+fn address_in_use(address: &HumanAddr) -> StdError {
+    StdError::generic_err(format!("Address {} is already in use", address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code::{auction, oracle};
+    use crate::ledger::TokenHandleMsg;
+    use cosmwasm_std::Uint128;
+    use diploma_master::fixtures::auction_init_msg;
+    use diploma_master::msg::{HandleMsg as AuctionHandleMsg, ReceiveMsg};
+    use ::oracle::fixtures::sample_history;
+    use ::oracle::msg::{
+        ExposureResponse, HandleMsg as OracleHandleMsg, InitMsg as OracleInitMsg,
+        QueryMsg as OracleQueryMsg,
+    };
+
+    fn send(recipient: &str, amount: u128, msg: Option<Binary>) -> TokenHandleMsg {
+        TokenHandleMsg::Send {
+            recipient: HumanAddr(recipient.to_string()),
+            amount: Uint128(amount),
+            msg,
+        }
+    }
+
+    fn chain_helper() -> Chain {
+        let mut chain = Chain::new();
+        chain.add_token("selltoken", "SELL", 6).unwrap();
+        chain.add_token("bidtoken", "BID", 6).unwrap();
+        chain.mint("selltoken", "seller", 1666).unwrap();
+        chain.mint("bidtoken", "lender1", 2000000).unwrap();
+        chain.mint("bidtoken", "lender2", 2000000).unwrap();
+
+        let init_msg = OracleInitMsg {
+            auction_code_hash: Some(code_hash(&HumanAddr("auction".to_string()))),
+            ..OracleInitMsg::default()
+        };
+        chain.instantiate(oracle(), "oracle", "owner", &init_msg).unwrap();
+        let add_history = OracleHandleMsg::AddHistory {
+            user: HumanAddr("seller".to_string()),
+            history: sample_history(),
+        };
+        chain.execute("owner", "oracle", &add_history).unwrap();
+        chain
+            .instantiate(auction(), "auction", "seller", &auction_init_msg())
+            .unwrap();
+        chain
+    }
+
+    #[test]
+    fn finalize_settles_balances() {
+        let mut chain = chain_helper();
+
+        let consign = to_binary(&ReceiveMsg::Consign {}).unwrap();
+        chain
+            .execute("seller", "selltoken", &send("auction", 1666, Some(consign)))
+            .unwrap();
+        chain
+            .execute("lender1", "bidtoken", &send("auction", 1100000, None))
+            .unwrap();
+        chain
+            .execute("lender2", "bidtoken", &send("auction", 1050000, None))
+            .unwrap();
+        assert_eq!(chain.balance("selltoken", "auction"), 1666);
+        assert_eq!(chain.balance("bidtoken", "auction"), 2150000);
+
+        let finalize = AuctionHandleMsg::Finalize { only_if_bids: true };
+        chain.execute("seller", "auction", &finalize).unwrap();
+
+        // the winner received the score in sell tokens, the seller the winning bid, and the
+        // losing bid was refunded
+        assert_eq!(chain.balance("selltoken", "lender2"), 1666);
+        assert_eq!(chain.balance("bidtoken", "lender2"), 950000);
+        assert_eq!(chain.balance("bidtoken", "seller"), 1050000);
+        assert_eq!(chain.balance("bidtoken", "lender1"), 2000000);
+        assert_eq!(chain.balance("selltoken", "auction"), 0);
+        assert_eq!(chain.balance("bidtoken", "auction"), 0);
+
+        let get_exposure = OracleQueryMsg::GetExposure {
+            user: HumanAddr("seller".to_string()),
+        };
+        let exposure: ExposureResponse = chain.query("oracle", &get_exposure).unwrap();
+        assert_eq!(exposure.open_auctions, 0);
+        assert_eq!(exposure.outstanding_loans, 1);
+    }
+
+    #[test]
+    fn failures_roll_back() {
+        let mut chain = chain_helper();
+
+        // the auction rejects the payload, so the transfer to it is reverted
+        let invalid = Some(Binary(b"{\"unknown\":{}}".to_vec()));
+        assert!(chain
+            .execute("lender1", "bidtoken", &send("auction", 1100000, invalid))
+            .is_err());
+        assert_eq!(chain.balance("bidtoken", "lender1"), 2000000);
+        assert_eq!(chain.balance("bidtoken", "auction"), 0);
+
+        // the oracle rejects a second auction over the seller's exposure limit, so the new
+        // instance is removed
+        assert!(chain
+            .instantiate(auction(), "auction2", "seller", &auction_init_msg())
+            .is_err());
+        let auction_info = diploma_master::msg::QueryMsg::AuctionInfo {};
+        let answer: StdResult<Binary> = chain.query("auction2", &auction_info);
+        assert!(answer.is_err());
+
+        assert!(chain
+            .execute("lender1", "bidtoken", &send("lender2", 3000000, None))
+            .is_err());
+        assert_eq!(chain.balance("bidtoken", "lender2"), 2000000);
+    }
+}
@@ -0,0 +1,106 @@
+use serde::de::DeserializeOwned;
+
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{from_binary, Binary, Env, Extern, HandleResult, InitResult, QueryResult};
+
+use crate::chain::ChainQuerier;
+use crate::storage::MemoryStorage;
+
+/// dependencies a simulated contract instance runs with
+pub type Deps = Extern<MemoryStorage, MockApi, ChainQuerier>;
+
+/// entry points of contract code the chain can instantiate.  Messages are passed as the JSON
+/// the chain received, so every contract can be driven the same way
+pub trait Code {
+    /// Returns InitResult of instantiating the contract
+    ///
+    /// # Arguments
+    ///
+    /// * `deps` - mutable reference to the dependencies of the new instance
+    /// * `env` - Env of the instantiation
+    /// * `msg` - JSON encoded InitMsg
+    fn init(&self, deps: &mut Deps, env: Env, msg: &Binary) -> InitResult;
+
+    /// Returns HandleResult of executing a message
+    ///
+    /// # Arguments
+    ///
+    /// * `deps` - mutable reference to the dependencies of the instance
+    /// * `env` - Env of the execution
+    /// * `msg` - JSON encoded HandleMsg
+    fn handle(&self, deps: &mut Deps, env: Env, msg: &Binary) -> HandleResult;
+
+    /// Returns QueryResult of a query
+    ///
+    /// # Arguments
+    ///
+    /// * `deps` - reference to the dependencies of the instance
+    /// * `msg` - JSON encoded QueryMsg
+    fn query(&self, deps: &Deps, msg: &Binary) -> QueryResult;
+}
+
+/// Code made of the init, handle and query functions of a contract crate
+pub struct ContractCode<I, H, Q> {
+    init: fn(&mut Deps, Env, I) -> InitResult,
+    handle: fn(&mut Deps, Env, H) -> HandleResult,
+    query: fn(&Deps, Q) -> QueryResult,
+}
+
+impl<I, H, Q> Code for ContractCode<I, H, Q>
+where
+    I: DeserializeOwned,
+    H: DeserializeOwned,
+    Q: DeserializeOwned,
+{
+    fn init(&self, deps: &mut Deps, env: Env, msg: &Binary) -> InitResult {
+        (self.init)(deps, env, from_binary(msg)?)
+    }
+
+    fn handle(&self, deps: &mut Deps, env: Env, msg: &Binary) -> HandleResult {
+        (self.handle)(deps, env, from_binary(msg)?)
+    }
+
+    fn query(&self, deps: &Deps, msg: &Binary) -> QueryResult {
+        (self.query)(deps, from_binary(msg)?)
+    }
+}
+
+/// Returns the code of the auction contract
+pub fn auction() -> Box<dyn Code> {
+    use diploma_master::contract::{handle, init, query};
+    Box::new(ContractCode {
+        init,
+        handle,
+        query,
+    })
+}
+
+/// Returns the code of the marketplace contract, which hosts many auctions
+pub fn marketplace() -> Box<dyn Code> {
+    use diploma_master::marketplace::{handle, init, query};
+    Box::new(ContractCode {
+        init,
+        handle,
+        query,
+    })
+}
+
+/// Returns the code of the oracle contract
+pub fn oracle() -> Box<dyn Code> {
+    use ::oracle::contract::{handle, init, query};
+    Box::new(ContractCode {
+        init,
+        handle,
+        query,
+    })
+}
+
+/// Returns the code of the lender registry contract
+pub fn registry() -> Box<dyn Code> {
+    use ::registry::contract::{handle, init, query};
+    Box::new(ContractCode {
+        init,
+        handle,
+        query,
+    })
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{to_binary, Api, Binary, CanonicalAddr, HumanAddr, StdResult, Uint128};
+
+use diploma_master::fixtures::auction_init_msg;
+use diploma_master::msg::{HandleMsg as AuctionHandleMsg, ReceiveMsg};
+use diploma_master::state::{load_bid, load_state, State};
+use oracle::fixtures::sample_history;
+use oracle::msg::{HandleMsg as OracleHandleMsg, InitMsg as OracleInitMsg};
+
+use crate::chain::{code_hash, Chain, Executed, CANONICAL_LENGTH};
+use crate::code;
+use crate::ledger::TokenHandleMsg;
+
+/// address of the auction under test
+pub const AUCTION: &str = "auction";
+/// address of the borrower
+pub const SELLER: &str = "seller";
+/// addresses of the lenders
+pub const LENDERS: [&str; 3] = ["lender0", "lender1", "lender2"];
+/// sell tokens the borrower starts with
+pub const SELLER_FUNDS: u128 = 5_000;
+/// bid tokens each lender starts with
+pub const LENDER_FUNDS: u128 = 10_000_000;
+
+/// operation on the auction under test.  Lender and caller indexes wrap around the lenders, and
+/// a caller index past the lenders is the borrower
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Op {
+    /// the borrower consigns sell tokens
+    Consign { amount: u32 },
+    /// a lender bids
+    Bid { lender: u8, amount: u32 },
+    /// a lender views their bid
+    ViewBid { lender: u8 },
+    /// the borrower finalizes the auction
+    Finalize { only_if_bids: bool },
+    /// anyone returns the outstanding funds of a closed auction
+    ReturnAll { caller: u8 },
+}
+
+/// an auction with a borrower and three lenders, and the escrow invariants checked after every
+/// operation on it
+pub struct EscrowHarness {
+    chain: Chain,
+}
+
+impl Default for EscrowHarness {
+    fn default() -> Self {
+        EscrowHarness::new()
+    }
+}
+
+impl EscrowHarness {
+    /// Returns a harness with an open auction whose borrower scores 1666
+    pub fn new() -> Self {
+        let mut chain = Chain::new();
+        chain.add_token("selltoken", "SELL", 6).unwrap();
+        chain.add_token("bidtoken", "BID", 6).unwrap();
+        chain.mint("selltoken", SELLER, SELLER_FUNDS).unwrap();
+        for lender in LENDERS.iter() {
+            chain.mint("bidtoken", lender, LENDER_FUNDS).unwrap();
+        }
+        let init_msg = OracleInitMsg {
+            auction_code_hash: Some(code_hash(&HumanAddr(AUCTION.to_string()))),
+            ..OracleInitMsg::default()
+        };
+        chain
+            .instantiate(code::oracle(), "oracle", "owner", &init_msg)
+            .unwrap();
+        let add_history = OracleHandleMsg::AddHistory {
+            user: HumanAddr(SELLER.to_string()),
+            history: sample_history(),
+        };
+        chain.execute("owner", "oracle", &add_history).unwrap();
+        chain
+            .instantiate(code::auction(), AUCTION, SELLER, &auction_init_msg())
+            .unwrap();
+        EscrowHarness { chain }
+    }
+
+    /// Returns the chain the auction runs on
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Returns an error describing a broken invariant after applying the operation.  Operations
+    /// the auction rejects are rolled back and are not errors
+    ///
+    /// # Arguments
+    ///
+    /// * `op` - the operation
+    pub fn apply(&mut self, op: &Op) -> Result<(), String> {
+        let result = match op {
+            Op::Consign { amount } => {
+                let consign = to_binary(&ReceiveMsg::Consign {}).unwrap();
+                self.send(SELLER, "selltoken", *amount, Some(consign))
+            }
+            Op::Bid { lender, amount } => self.send(lender_at(*lender), "bidtoken", *amount, None),
+            Op::ViewBid { lender } => {
+                self.chain
+                    .execute(lender_at(*lender), AUCTION, &AuctionHandleMsg::ViewBid {})
+            }
+            Op::Finalize { only_if_bids } => {
+                let finalize = AuctionHandleMsg::Finalize {
+                    only_if_bids: *only_if_bids,
+                };
+                self.chain.execute(SELLER, AUCTION, &finalize)
+            }
+            Op::ReturnAll { caller } => {
+                let caller = LENDERS.get(*caller as usize).copied().unwrap_or(SELLER);
+                self.chain
+                    .execute(caller, AUCTION, &AuctionHandleMsg::ReturnAll {})
+            }
+        };
+        self.check()?;
+        if let (Op::ReturnAll { .. }, Ok(_)) = (op, &result) {
+            self.check_escrow_empty()?;
+        }
+        Ok(())
+    }
+
+    /// Returns an error describing the first escrow invariant that does not hold
+    pub fn check(&self) -> Result<(), String> {
+        let (state, bids) = self.auction()?;
+        let active: u128 = bids.values().sum();
+
+        // the auction holds exactly the consignment and the active bids
+        let held_sell = self.chain.balance("selltoken", AUCTION);
+        if held_sell != state.currently_consigned {
+            return Err(format!(
+                "auction holds {} sell tokens but {} are consigned",
+                held_sell, state.currently_consigned
+            ));
+        }
+        let held_bid = self.chain.balance("bidtoken", AUCTION);
+        if held_bid != active {
+            return Err(format!(
+                "auction holds {} bid tokens but active bids are {}",
+                held_bid, active
+            ));
+        }
+
+        // every lender's tokens are in their balance, their active bid, or paid for the sale.
+        // A bid refunded twice would leave a lender with more than they started with
+        for lender in LENDERS.iter() {
+            let address = HumanAddr(lender.to_string());
+            let paid = if state.lender.as_ref() == Some(&address) {
+                state.winning_bid
+            } else {
+                0
+            };
+            let bid = bids.get(&address).copied().unwrap_or(0);
+            let balance = self.chain.balance("bidtoken", lender);
+            if balance + bid + paid != LENDER_FUNDS {
+                return Err(format!(
+                    "{} holds {} bid tokens with {} bid and {} paid, started with {}",
+                    lender, balance, bid, paid, LENDER_FUNDS
+                ));
+            }
+        }
+
+        // the borrower's sell tokens are in their balance, the consignment, or sold
+        let sold = if state.lender.is_some() { state.score } else { 0 };
+        let balance = self.chain.balance("selltoken", SELLER);
+        if balance + state.currently_consigned + sold != SELLER_FUNDS {
+            return Err(format!(
+                "{} holds {} sell tokens with {} consigned and {} sold, started with {}",
+                SELLER, balance, state.currently_consigned, sold, SELLER_FUNDS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the auction still holds any tokens
+    fn check_escrow_empty(&self) -> Result<(), String> {
+        for token in ["selltoken", "bidtoken"].iter() {
+            let held = self.chain.balance(token, AUCTION);
+            if held != 0 {
+                return Err(format!("auction holds {} of {} after return_all", held, token));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the state of the auction and its active bids by lender
+    fn auction(&self) -> Result<(State, BTreeMap<HumanAddr, u128>), String> {
+        let storage = self
+            .chain
+            .storage(AUCTION)
+            .ok_or_else(|| "the auction has not been instantiated".to_string())?;
+        let state = load_state(&storage).map_err(|e| e.to_string())?;
+        let api = MockApi::new(CANONICAL_LENGTH);
+        let mut bids = BTreeMap::new();
+        for bidder in &state.bidders {
+            let bid = load_bid(&storage, bidder)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "a bidder has no bid".to_string())?;
+            let address = api
+                .human_address(&CanonicalAddr::from(bidder.as_slice()))
+                .map_err(|e| e.to_string())?;
+            bids.insert(address, bid.amount);
+        }
+        Ok((state, bids))
+    }
+
+    /// Returns the result of sending tokens to the auction
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - address sending the tokens
+    /// * `token` - address of the token
+    /// * `amount` - amount of tokens
+    /// * `msg` - Optional msg passed on to the auction's Receive
+    fn send(
+        &mut self,
+        sender: &str,
+        token: &str,
+        amount: u32,
+        msg: Option<Binary>,
+    ) -> StdResult<Vec<Executed>> {
+        let send = TokenHandleMsg::Send {
+            recipient: HumanAddr(AUCTION.to_string()),
+            amount: Uint128(u128::from(amount)),
+            msg,
+        };
+        self.chain.execute(sender, token, &send)
+    }
+}
+
+/// Returns the address of the lender at the index, wrapping around the lenders
+///
+/// # Arguments
+///
+/// * `index` - index of the lender
+fn lender_at(index: u8) -> &'static str {
+    LENDERS[index as usize % LENDERS.len()]
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, CosmosMsg, HandleResponse, HumanAddr, QueryResult, StdError,
+    StdResult, Uint128, WasmMsg,
+};
+
+/// SNIP-20 handles the ledger executes
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenHandleMsg {
+    /// move tokens to the recipient
+    Transfer {
+        /// address receiving the tokens
+        recipient: HumanAddr,
+        /// amount of tokens
+        amount: Uint128,
+    },
+    /// move tokens to the recipient, calling its Receive if it registered with the token
+    Send {
+        /// address receiving the tokens
+        recipient: HumanAddr,
+        /// amount of tokens
+        amount: Uint128,
+        /// Optional msg passed on to the recipient's Receive
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        msg: Option<Binary>,
+    },
+    /// register the sender to be called when it is sent tokens
+    RegisterReceive {
+        /// code hash of the sender
+        code_hash: String,
+    },
+}
+
+/// SNIP-20 queries the ledger answers
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenQueryMsg {
+    TokenInfo {},
+}
+
+/// SNIP-20 query responses
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenQueryAnswer<'a> {
+    TokenInfo(&'a TokenInfo),
+}
+
+/// message a SNIP-20 token sends to a registered contract it has sent tokens to
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ReceiverHandleMsg {
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        msg: Option<Binary>,
+    },
+}
+
+/// public information of a simulated token
+#[derive(Serialize, Clone, Debug)]
+pub struct TokenInfo {
+    /// name of the token
+    pub name: String,
+    /// symbol of the token
+    pub symbol: String,
+    /// number of decimals of the token
+    pub decimals: u8,
+    /// total supply, which simulated tokens do not disclose
+    pub total_supply: Option<Uint128>,
+}
+
+/// a simulated SNIP-20 token
+#[derive(Clone, Debug)]
+struct Token {
+    /// public information of the token
+    info: TokenInfo,
+    /// balances of every address holding the token
+    balances: BTreeMap<HumanAddr, u128>,
+    /// code hashes of the contracts that registered to receive the token
+    receivers: BTreeMap<HumanAddr, String>,
+}
+
+/// in-memory ledger of SNIP-20 tokens.  The chain routes every message and query addressed to a
+/// token here instead of to a contract instance
+#[derive(Default, Clone, Debug)]
+pub struct Ledger {
+    tokens: BTreeMap<HumanAddr, Token>,
+}
+
+impl Ledger {
+    /// Returns true if the address is a token of the ledger
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address to check
+    pub fn is_token(&self, token: &HumanAddr) -> bool {
+        self.tokens.contains_key(token)
+    }
+
+    /// Adds a token without any balances
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `symbol` - symbol of the token
+    /// * `decimals` - number of decimals of the token
+    pub fn add_token(&mut self, token: HumanAddr, symbol: &str, decimals: u8) {
+        let info = TokenInfo {
+            name: format!("{} token", symbol),
+            symbol: symbol.to_string(),
+            decimals,
+            total_supply: None,
+        };
+        self.tokens.insert(
+            token,
+            Token {
+                info,
+                balances: BTreeMap::new(),
+                receivers: BTreeMap::new(),
+            },
+        );
+    }
+
+    /// Adds tokens to an address's balance
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `owner` - address receiving the tokens
+    /// * `amount` - amount of tokens
+    pub fn mint(&mut self, token: &HumanAddr, owner: HumanAddr, amount: u128) -> StdResult<()> {
+        let balance = self.token_mut(token)?.balances.entry(owner).or_insert(0);
+        *balance += amount;
+        Ok(())
+    }
+
+    /// Returns the balance of an address, 0 if the token is unknown
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `owner` - address holding the tokens
+    pub fn balance(&self, token: &HumanAddr, owner: &HumanAddr) -> u128 {
+        self.tokens
+            .get(token)
+            .and_then(|t| t.balances.get(owner))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the HandleResponse of a token to a message.  Sends to registered contracts are
+    /// answered with the Receive message the chain must execute next
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `sender` - address executing the message
+    /// * `msg` - JSON encoded SNIP-20 HandleMsg
+    pub fn handle(
+        &mut self,
+        token: &HumanAddr,
+        sender: &HumanAddr,
+        msg: &Binary,
+    ) -> StdResult<HandleResponse> {
+        let mut messages = Vec::new();
+        match from_binary(msg)? {
+            TokenHandleMsg::Transfer { recipient, amount } => {
+                self.move_tokens(token, sender, recipient, amount.u128())?;
+            }
+            TokenHandleMsg::Send {
+                recipient,
+                amount,
+                msg,
+            } => {
+                self.move_tokens(token, sender, recipient.clone(), amount.u128())?;
+                if let Some(code_hash) = self.token_mut(token)?.receivers.get(&recipient) {
+                    let receive = ReceiverHandleMsg::Receive {
+                        sender: sender.clone(),
+                        from: sender.clone(),
+                        amount,
+                        msg,
+                    };
+                    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: recipient,
+                        callback_code_hash: code_hash.clone(),
+                        msg: to_binary(&receive)?,
+                        send: vec![],
+                    }));
+                }
+            }
+            TokenHandleMsg::RegisterReceive { code_hash } => {
+                self.token_mut(token)?
+                    .receivers
+                    .insert(sender.clone(), code_hash);
+            }
+        }
+        Ok(HandleResponse {
+            messages,
+            log: vec![],
+            data: None,
+        })
+    }
+
+    /// Returns QueryResult of a token query
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `msg` - JSON encoded SNIP-20 QueryMsg
+    pub fn query(&self, token: &HumanAddr, msg: &Binary) -> QueryResult {
+        let info = &self
+            .tokens
+            .get(token)
+            .ok_or_else(|| StdError::generic_err(format!("{} is not a token", token)))?
+            .info;
+        match from_binary(msg)? {
+            TokenQueryMsg::TokenInfo {} => to_binary(&TokenQueryAnswer::TokenInfo(info)),
+        }
+    }
+
+    /// Moves tokens between balances
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    /// * `from` - address sending the tokens
+    /// * `to` - address receiving the tokens
+    /// * `amount` - amount of tokens
+    fn move_tokens(
+        &mut self,
+        token: &HumanAddr,
+        from: &HumanAddr,
+        to: HumanAddr,
+        amount: u128,
+    ) -> StdResult<()> {
+        let balances = &mut self.token_mut(token)?.balances;
+        let balance = balances.get(from).copied().unwrap_or(0);
+        if balance < amount {
+            return Err(StdError::generic_err(format!(
+                "insufficient funds: balance={}, required={}",
+                balance, amount
+            )));
+        }
+        balances.insert(from.clone(), balance - amount);
+        *balances.entry(to).or_insert(0) += amount;
+        Ok(())
+    }
+
+    /// Returns a mutable reference to a token
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - address of the token
+    fn token_mut(&mut self, token: &HumanAddr) -> StdResult<&mut Token> {
+        self.tokens
+            .get_mut(token)
+            .ok_or_else(|| StdError::generic_err(format!("{} is not a token", token)))
+    }
+}
@@ -0,0 +1,6 @@
+pub mod chain;
+pub mod code;
+pub mod invariants;
+pub mod ledger;
+pub mod scenario;
+pub mod storage;
@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use cosmwasm_std::{Binary, HumanAddr, StdError, StdResult, Uint128};
+
+use oracle::msg::{History, InitMsg as OracleInitMsg};
+
+use crate::chain::Chain;
+use crate::code::{self, Code};
+use crate::ledger::TokenHandleMsg;
+
+/// scripted run of the contracts: who takes part, what they start with, and the actions they
+/// take with the outcome each action is expected to have
+#[derive(Deserialize, Debug)]
+pub struct Scenario {
+    /// name of the scenario
+    pub name: String,
+    /// Optional description of the scenario
+    #[serde(default)]
+    pub description: Option<String>,
+    /// addresses that sign actions.  Actions can also be sent by contracts and tokens
+    #[serde(default)]
+    pub actors: Vec<HumanAddr>,
+    /// tokens of the simulated chain
+    #[serde(default)]
+    pub tokens: Vec<TokenSpec>,
+    /// balances before the first action
+    #[serde(default)]
+    pub balances: Vec<Balance>,
+    /// Optional oracle instantiated before the first action
+    #[serde(default)]
+    pub oracle: Option<OracleSpec>,
+    /// ordered actions
+    pub steps: Vec<Step>,
+}
+
+/// a token of the scenario
+#[derive(Deserialize, Debug)]
+pub struct TokenSpec {
+    /// address of the token
+    pub address: HumanAddr,
+    /// symbol of the token
+    pub symbol: String,
+    /// number of decimals of the token
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+}
+
+/// Returns the number of decimals of tokens that do not state it
+fn default_decimals() -> u8 {
+    6
+}
+
+/// token balance of an address
+#[derive(Deserialize, Debug)]
+pub struct Balance {
+    /// address of the token
+    pub token: HumanAddr,
+    /// address holding the tokens
+    pub owner: HumanAddr,
+    /// amount of tokens
+    pub amount: Uint128,
+}
+
+/// oracle of the scenario, the credit histories it starts with and the contracts that may open
+/// exposures
+#[derive(Deserialize, Debug)]
+pub struct OracleSpec {
+    /// address of the oracle
+    pub address: HumanAddr,
+    /// address that instantiates the oracle and adds the histories
+    pub owner: HumanAddr,
+    /// credit histories by user, in the format of `history.json`
+    #[serde(default)]
+    pub histories: BTreeMap<HumanAddr, History>,
+    /// Optional code hash of the auction contract, whose instances may open exposures
+    #[serde(default)]
+    pub auction_code_hash: Option<String>,
+    /// contracts that host auctions and may open exposures for them
+    #[serde(default)]
+    pub factories: Vec<HumanAddr>,
+}
+
+/// contract code a scenario can instantiate
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeName {
+    Auction,
+    Marketplace,
+    Oracle,
+    Registry,
+}
+
+impl CodeName {
+    /// Returns the code with this name
+    pub fn code(self) -> Box<dyn Code> {
+        match self {
+            CodeName::Auction => code::auction(),
+            CodeName::Marketplace => code::marketplace(),
+            CodeName::Oracle => code::oracle(),
+            CodeName::Registry => code::registry(),
+        }
+    }
+}
+
+/// an action and the outcome it is expected to have
+#[derive(Deserialize, Debug)]
+pub struct Step {
+    /// the action
+    #[serde(flatten)]
+    pub action: Action,
+    /// expected outcome.  Defaults to the action succeeding
+    #[serde(default)]
+    pub expect: Expect,
+}
+
+/// action of a scenario
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// instantiate a contract
+    Instantiate {
+        /// code of the contract
+        code: CodeName,
+        /// address of the new instance
+        address: HumanAddr,
+        /// address instantiating the contract
+        sender: HumanAddr,
+        /// InitMsg of the contract
+        msg: Value,
+    },
+    /// execute a message
+    Execute {
+        /// address executing the message
+        sender: HumanAddr,
+        /// address of the contract or token
+        contract: HumanAddr,
+        /// HandleMsg of the contract or token
+        msg: Value,
+    },
+    /// send tokens to a contract through the SNIP-20 Send handle
+    Send {
+        /// address sending the tokens
+        sender: HumanAddr,
+        /// address of the token
+        token: HumanAddr,
+        /// address receiving the tokens
+        recipient: HumanAddr,
+        /// amount of tokens
+        amount: Uint128,
+        /// Optional msg passed on to the recipient's Receive, written as JSON
+        #[serde(default)]
+        msg: Option<Value>,
+    },
+    /// query a contract or token
+    Query {
+        /// address of the contract or token
+        contract: HumanAddr,
+        /// QueryMsg of the contract or token
+        msg: Value,
+    },
+    /// move to the next block
+    Advance {
+        /// number of seconds the next block is later
+        seconds: u64,
+    },
+}
+
+/// expected outcome of an action
+#[derive(Deserialize, Debug, Default)]
+pub struct Expect {
+    /// Optional error the action must fail with.  The action must succeed if not given
+    #[serde(default)]
+    pub error: Option<ExpectedError>,
+    /// balances after the action
+    #[serde(default)]
+    pub balances: Vec<Balance>,
+    /// Optional JSON a query answer must contain.  Objects in the answer may have keys that are
+    /// not expected
+    #[serde(default)]
+    pub answer: Option<Value>,
+}
+
+/// error an action is expected to fail with
+#[derive(Deserialize, Debug, Default)]
+pub struct ExpectedError {
+    /// Optional code of a ContractError
+    #[serde(default)]
+    pub code: Option<u16>,
+    /// Optional text the error message must contain
+    #[serde(default)]
+    pub contains: Option<String>,
+}
+
+/// machine-readable form of a contract error
+#[derive(Deserialize)]
+struct ErrorResponse {
+    code: u16,
+}
+
+/// result of running a scenario
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// name of the scenario
+    pub name: String,
+    /// number of steps that met their expectation
+    pub steps_passed: usize,
+    /// Optional description of the first step that did not meet its expectation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+impl Report {
+    /// Returns true if every step met its expectation
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl Scenario {
+    /// Returns the scenario in a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path of the file
+    pub fn load(path: &Path) -> StdResult<Self> {
+        let json = fs::read(path).map_err(|e| {
+            StdError::generic_err(format!("Could not read {}: {}", path.display(), e))
+        })?;
+        serde_json::from_slice(&json).map_err(|e| {
+            StdError::generic_err(format!("Invalid scenario {}: {}", path.display(), e))
+        })
+    }
+
+    /// Returns the Report of running the scenario on a new chain.  The run stops at the first
+    /// step that does not meet its expectation
+    pub fn run(&self) -> Report {
+        let mut report = Report {
+            name: self.name.clone(),
+            steps_passed: 0,
+            failure: None,
+        };
+        let mut chain = match self.setup() {
+            Ok(chain) => chain,
+            Err(e) => {
+                report.failure = Some(format!("setup: {}", e));
+                return report;
+            }
+        };
+        for (index, step) in self.steps.iter().enumerate() {
+            if let Err(failure) = self.run_step(&mut chain, step) {
+                report.failure = Some(format!("step {}: {}", index + 1, failure));
+                break;
+            }
+            report.steps_passed += 1;
+        }
+        report
+    }
+
+    /// Returns a chain with the tokens, balances and oracle of the scenario
+    fn setup(&self) -> StdResult<Chain> {
+        let mut chain = Chain::new();
+        for token in &self.tokens {
+            chain.add_token(&token.address.0, &token.symbol, token.decimals)?;
+        }
+        for balance in &self.balances {
+            chain.mint(&balance.token.0, &balance.owner.0, balance.amount.u128())?;
+        }
+        if let Some(oracle) = &self.oracle {
+            let init_msg = OracleInitMsg {
+                histories: Some(oracle.histories.clone().into_iter().collect()),
+                auction_code_hash: oracle.auction_code_hash.clone(),
+                factories: Some(oracle.factories.clone()),
+                ..OracleInitMsg::default()
+            };
+            chain.instantiate(code::oracle(), &oracle.address.0, &oracle.owner.0, &init_msg)?;
+        }
+        Ok(chain)
+    }
+
+    /// Returns an error describing how the step did not meet its expectation
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - chain the scenario runs on
+    /// * `step` - the step
+    fn run_step(&self, chain: &mut Chain, step: &Step) -> Result<(), String> {
+        let mut answer: Option<Value> = None;
+        let result = match &step.action {
+            Action::Instantiate {
+                code,
+                address,
+                sender,
+                msg,
+            } => {
+                self.check_sender(chain, sender)?;
+                chain
+                    .instantiate_binary(code.code(), &address.0, &sender.0, to_json(msg)?)
+                    .map(|_| ())
+            }
+            Action::Execute {
+                sender,
+                contract,
+                msg,
+            } => {
+                self.check_sender(chain, sender)?;
+                chain
+                    .execute_binary(&sender.0, &contract.0, to_json(msg)?)
+                    .map(|_| ())
+            }
+            Action::Send {
+                sender,
+                token,
+                recipient,
+                amount,
+                msg,
+            } => {
+                self.check_sender(chain, sender)?;
+                let msg = msg.as_ref().map(to_json).transpose()?;
+                let send = TokenHandleMsg::Send {
+                    recipient: recipient.clone(),
+                    amount: *amount,
+                    msg,
+                };
+                chain.execute(&sender.0, &token.0, &send).map(|_| ())
+            }
+            Action::Query { contract, msg } => chain
+                .query_binary(&contract.0, &to_json(msg)?)
+                .and_then(|binary| {
+                    serde_json::from_slice(binary.as_slice())
+                        .map_err(|e| StdError::generic_err(e.to_string()))
+                })
+                .map(|value| answer = Some(value)),
+            Action::Advance { seconds } => {
+                chain.advance(*seconds);
+                Ok(())
+            }
+        };
+
+        match (&step.expect.error, result) {
+            (None, Err(e)) => return Err(format!("unexpected error: {}", e)),
+            (Some(_), Ok(())) => return Err("succeeded but was expected to fail".to_string()),
+            (Some(expected), Err(e)) => check_error(expected, &e)?,
+            (None, Ok(())) => {}
+        }
+        if let Some(expected) = &step.expect.answer {
+            match &answer {
+                Some(actual) if contains(actual, expected) => {}
+                Some(actual) => return Err(format!("unexpected answer: {}", actual)),
+                None => return Err("only a query has an answer".to_string()),
+            }
+        }
+        for balance in &step.expect.balances {
+            let actual = chain.balance(&balance.token.0, &balance.owner.0);
+            if actual != balance.amount.u128() {
+                return Err(format!(
+                    "{} holds {} of {}, expected {}",
+                    balance.owner, actual, balance.token, balance.amount
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the sender is neither an actor nor a contract or token
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - chain the scenario runs on
+    /// * `sender` - address sending an action
+    fn check_sender(&self, chain: &Chain, sender: &HumanAddr) -> Result<(), String> {
+        if self.actors.contains(sender) || chain.is_contract(&sender.0) {
+            Ok(())
+        } else {
+            Err(format!("{} is not an actor of the scenario", sender))
+        }
+    }
+}
+
+/// Returns the JSON encoding of a message
+///
+/// # Arguments
+///
+/// * `msg` - the message
+fn to_json(msg: &Value) -> Result<Binary, String> {
+    serde_json::to_vec(msg)
+        .map(Binary)
+        .map_err(|e| format!("invalid message: {}", e))
+}
+
+/// Returns an error if the error an action failed with is not the expected one
+///
+/// # Arguments
+///
+/// * `expected` - the expected error
+/// * `error` - the error the action failed with
+fn check_error(expected: &ExpectedError, error: &StdError) -> Result<(), String> {
+    let message = match error {
+        StdError::GenericErr { msg, .. } => msg.clone(),
+        other => other.to_string(),
+    };
+    if let Some(code) = expected.code {
+        match serde_json::from_str::<ErrorResponse>(&message) {
+            Ok(response) if response.code == code => {}
+            _ => return Err(format!("expected error code {}, got: {}", code, message)),
+        }
+    }
+    if let Some(text) = &expected.contains {
+        if !message.contains(text.as_str()) {
+            return Err(format!("expected error containing {:?}, got: {}", text, message));
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if every value of the expected JSON is in the actual JSON.  Objects may have
+/// keys that are not expected, anything else must be equal
+///
+/// # Arguments
+///
+/// * `actual` - the actual JSON
+/// * `expected` - the expected JSON
+fn contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .map_or(false, |actual| contains(actual, value))
+        }),
+        _ => actual == expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_scenarios_pass() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            let report = Scenario::load(&path).unwrap().run();
+            assert!(report.passed(), "{}: {:?}", path.display(), report.failure);
+        }
+    }
+
+    #[test]
+    fn reports_the_failed_step() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "name": "overdraft",
+                "actors": ["alice"],
+                "tokens": [{"address": "token", "symbol": "TKN"}],
+                "balances": [{"token": "token", "owner": "alice", "amount": "10"}],
+                "steps": [
+                    {
+                        "execute": {
+                            "sender": "alice",
+                            "contract": "token",
+                            "msg": {"transfer": {"recipient": "bob", "amount": "4"}}
+                        },
+                        "expect": {"balances": [{"token": "token", "owner": "bob", "amount": "4"}]}
+                    },
+                    {
+                        "execute": {
+                            "sender": "alice",
+                            "contract": "token",
+                            "msg": {"transfer": {"recipient": "bob", "amount": "7"}}
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let report = scenario.run();
+        assert_eq!(report.steps_passed, 1);
+        assert!(report.failure.unwrap().starts_with("step 2: unexpected error"));
+    }
+}
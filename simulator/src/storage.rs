@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{ReadonlyStorage, Storage};
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, KV};
+
+/// storage of a simulated contract instance.  Unlike MockStorage it can be cloned, which is how
+/// the chain snapshots state to roll back failed transactions
+#[derive(Default, Clone, Debug)]
+pub struct MemoryStorage {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ReadonlyStorage for MemoryStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range<'a>(
+        &'a self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = KV> + 'a> {
+        use std::ops::Bound;
+
+        let start = start.map_or(Bound::Unbounded, |key| Bound::Included(key.to_vec()));
+        let end = end.map_or(Bound::Unbounded, |key| Bound::Excluded(key.to_vec()));
+        let iter = self
+            .data
+            .range((start, end))
+            .map(|(key, value)| (key.clone(), value.clone()));
+        match order {
+            Order::Ascending => Box::new(iter),
+            Order::Descending => Box::new(iter.rev()),
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.data.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.data.remove(key);
+    }
+}
@@ -0,0 +1,51 @@
+//! Property tests of the auction's escrow.  Random sequences of consign, bid, view_bid, finalize
+//! and return_all run on the simulator, and the escrow invariants of
+//! `simulator::invariants::EscrowHarness` are checked after every operation.
+
+use proptest::prelude::*;
+
+use simulator::invariants::{EscrowHarness, Op, AUCTION, LENDERS};
+
+fn op() -> impl Strategy<Value = Op> {
+    let lender = 0..LENDERS.len() as u8;
+    prop_oneof![
+        (0u32..3_000).prop_map(|amount| Op::Consign { amount }),
+        // around the average bid of 1200000, so bids are accepted, outbid and refunded
+        (lender.clone(), 900_000u32..1_300_000)
+            .prop_map(|(lender, amount)| Op::Bid { lender, amount }),
+        // a round power of ten is always rejected
+        (lender, Just(1_000_000u32)).prop_map(|(lender, amount)| Op::Bid { lender, amount }),
+        (0..LENDERS.len() as u8).prop_map(|lender| Op::ViewBid { lender }),
+        any::<bool>().prop_map(|only_if_bids| Op::Finalize { only_if_bids }),
+        (0..=LENDERS.len() as u8).prop_map(|caller| Op::ReturnAll { caller }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn escrow_invariants_hold(ops in prop::collection::vec(op(), 1..40)) {
+        let mut harness = EscrowHarness::new();
+        for op in &ops {
+            let result = harness.apply(op);
+            prop_assert!(result.is_ok(), "{:?} after {:?}: {}", op, ops, result.unwrap_err());
+        }
+    }
+}
+
+#[test]
+fn return_all_after_finalize_empties_escrow() {
+    let mut harness = EscrowHarness::new();
+    let ops = [
+        Op::Consign { amount: 1_000 },
+        Op::Bid { lender: 0, amount: 1_100_000 },
+        Op::Bid { lender: 1, amount: 1_150_000 },
+        Op::Bid { lender: 0, amount: 1_050_000 },
+        Op::Finalize { only_if_bids: false },
+        Op::ReturnAll { caller: 2 },
+    ];
+    for op in ops.iter() {
+        harness.apply(op).unwrap();
+    }
+    assert_eq!(harness.chain().balance("selltoken", AUCTION), 0);
+    assert_eq!(harness.chain().balance("bidtoken", AUCTION), 0);
+}